@@ -22,4 +22,13 @@ describe('LoginPage', () => {
         cy.get('svg#loading').should('not.be.visible');
         cy.get('ul.scrollable').should('be.visible');
     })
+
+    it('Shows the missing password', () => {
+        cy.get('form#login_form').within(() => {
+            cy.get('input#homeserver').type("http://localhost:8448");
+            cy.get('input#username').type("@carl:example.com");
+        });
+        cy.get('form#login_form').submit();
+        cy.get('input#password').should('have.class', 'uk-form-danger');
+    })
 })
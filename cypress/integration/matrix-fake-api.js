@@ -8,7 +8,32 @@ const fake_matrix_api_handler = (arg, fetch, win) => {
     } else if (typeof arg === "object") {
         console.log("REQUESTED: ", arg);
         console.log("Special mode");
-        if (arg["url"] === "http://localhost:8448/_matrix/client/r0/login") {
+        if (arg["url"] === "http://localhost:8448/_matrix/client/r0/login" && arg["method"] === "GET") {
+            console.log("handling login flows");
+            return json_response(win, arg, {
+                "flows": [
+                    {"type": "m.login.password"}
+                ]
+            });
+        } else if (arg["url"] === "https://example.com/.well-known/matrix/client") {
+            console.log("handling homeserver discovery");
+            return json_response(win, arg, {
+                "m.homeserver": {
+                    "base_url": "http://localhost:8448"
+                }
+            });
+        } else if (arg["url"] === "http://localhost:8448/_matrix/client/versions") {
+            console.log("handling versions");
+            return json_response(win, arg, {
+                "versions": ["r0.5.0", "r0.6.0"]
+            });
+        } else if (arg["url"].includes("/room_keys/version")) {
+            console.log("handling key backup");
+            return json_response(win, arg, {
+                "errcode": "M_NOT_FOUND",
+                "error": "No current backup version"
+            }, 404);
+        } else if (arg["url"] === "http://localhost:8448/_matrix/client/r0/login") {
             console.log("handling login");
             return new Promise((resolve, reject) => {
                     const resp_data = {
@@ -189,5 +214,18 @@ const fake_matrix_api_handler = (arg, fetch, win) => {
     }
 
 }
+const json_response = (win, arg, resp_data, status = 200) => {
+    const resp = new Blob([JSON.stringify(resp_data, null, 2)], {type: 'application/json'});
+
+    const init = {
+        "status": status,
+        "statusText": status === 200 ? "Ok" : "Not Found",
+        headers: {'Content-type': 'application/json'}
+    };
+    const response = new win.Response(resp, init);
+    Object.defineProperty(response, "url", {value: arg["url"]});
+    return Promise.resolve(response);
+}
+
 export {fake_matrix_api_handler}
 export default fake_matrix_api_handler
@@ -0,0 +1,341 @@
+use std::convert::TryFrom;
+
+use matrix_sdk::identifiers::UserId;
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    create_room::NewRoom,
+    directory::UserSearchResult,
+    Request, Response,
+};
+
+/// The "New room" dialog
+pub struct CreateRoomModal {
+    link: ComponentLink<Self>,
+    state: State,
+    matrix_agent: AccountBridge,
+    props: Props,
+}
+
+pub struct State {
+    room: NewRoom,
+    /// Names of the invitees, in the same order as `room.invites`
+    invitee_names: Vec<String>,
+    search_query: String,
+    search_results: Vec<UserSearchResult>,
+    creating: bool,
+    error: Option<String>,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum Msg {
+    NewMessage(Response),
+    SetName(String),
+    SetTopic(String),
+    SetAlias(String),
+    TogglePublic,
+    ToggleEncrypted,
+    Search(String),
+    AddInvitee(UserSearchResult),
+    RemoveInvitee(usize),
+    Create,
+    Close,
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+    #[prop_or_default]
+    pub on_close: Callback<()>,
+}
+
+impl Component for CreateRoomModal {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let matrix_callback = link.callback(Msg::NewMessage);
+        let matrix_agent = AccountBridge::new(props.account.clone(), matrix_callback);
+        let state = State {
+            room: NewRoom {
+                // Without encryption support we couldn't send anything into the room
+                encrypted: cfg!(feature = "encryption"),
+                ..Default::default()
+            },
+            invitee_names: Vec::new(),
+            search_query: String::new(),
+            search_results: Vec::new(),
+            creating: false,
+            error: None,
+        };
+        CreateRoomModal {
+            link,
+            state,
+            matrix_agent,
+            props,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::NewMessage(Response::UserSearchResults((search_term, results))) => {
+                // Answers to older searches might arrive late
+                if search_term != self.state.search_query {
+                    return false;
+                }
+                self.state.search_results = results;
+            }
+            Msg::NewMessage(Response::Error(e)) if self.state.creating => {
+                self.state.creating = false;
+                self.state.error = Some(e.to_string());
+            }
+            Msg::NewMessage(_) => return false,
+            Msg::SetName(name) => self.state.room.name = name,
+            Msg::SetTopic(topic) => self.state.room.topic = topic,
+            Msg::SetAlias(alias) => {
+                self.state.room.alias = alias.trim_start_matches('#').to_string();
+            }
+            Msg::TogglePublic => self.state.room.public = !self.state.room.public,
+            Msg::ToggleEncrypted => self.state.room.encrypted = !self.state.room.encrypted,
+            Msg::Search(query) => {
+                if query.trim().len() >= 2 {
+                    self.matrix_agent
+                        .send(Request::SearchUsers(query.trim().to_string()));
+                } else {
+                    self.state.search_results.clear();
+                }
+                self.state.search_query = query;
+            }
+            Msg::AddInvitee(user) => {
+                if !self.state.room.invites.contains(&user.user_id) {
+                    self.state.invitee_names.push(user.name().to_string());
+                    self.state.room.invites.push(user.user_id);
+                }
+                self.state.search_query.clear();
+                self.state.search_results.clear();
+            }
+            Msg::RemoveInvitee(index) => {
+                self.state.room.invites.remove(index);
+                self.state.invitee_names.remove(index);
+            }
+            Msg::Create => {
+                self.state.creating = true;
+                self.state.error = None;
+                self.matrix_agent
+                    .send(Request::CreateRoom(self.state.room.clone()));
+            }
+            Msg::Close => self.props.on_close.emit(()),
+        }
+        true
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> bool {
+        false
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let room = &self.state.room;
+        let error = match &self.state.error {
+            Some(error) => html! { <div class="uk-alert-danger" uk-alert="">{error}</div> },
+            None => html! {},
+        };
+        let encryption = if cfg!(feature = "encryption") {
+            html! {
+                <label class="create-room-option">
+                    <input
+                        class="uk-checkbox"
+                        type="checkbox"
+                        checked=room.encrypted
+                        onclick=self.link.callback(|_| Msg::ToggleEncrypted)
+                    />
+                    { " " }
+                    {
+                        tr!(
+                            // Option when creating a room, can't be turned off later
+                            "Enable end-to-end encryption"
+                        )
+                    }
+                </label>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <div class="uk-modal uk-open create-room-modal" style="display: block;">
+                <form
+                    class="uk-modal-dialog uk-modal-body uk-form-stacked"
+                    onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::Create })
+                >
+                    <h2 class="uk-modal-title">
+                        {
+                            tr!(
+                                // Title of the dialog to create a room
+                                "New room"
+                            )
+                        }
+                    </h2>
+                    { error }
+                    <label class="uk-form-label">{ tr!("Name") }</label>
+                    <input
+                        class="uk-input"
+                        required=true
+                        value=&room.name
+                        oninput=self.link.callback(|e: InputData| Msg::SetName(e.value))
+                    />
+                    <label class="uk-form-label">
+                        {
+                            tr!(
+                                // Label of the topic input when creating a room
+                                "Topic (optional)"
+                            )
+                        }
+                    </label>
+                    <input
+                        class="uk-input"
+                        value=&room.topic
+                        oninput=self.link.callback(|e: InputData| Msg::SetTopic(e.value))
+                    />
+                    <label class="uk-form-label">
+                        {
+                            tr!(
+                                // Label of the alias input when creating a room
+                                "Address (optional)"
+                            )
+                        }
+                    </label>
+                    <div class="uk-inline uk-width-1-1">
+                        <span class="uk-form-icon">{"#"}</span>
+                        <input
+                            class="uk-input"
+                            placeholder="my-room"
+                            value=&room.alias
+                            oninput=self.link.callback(|e: InputData| Msg::SetAlias(e.value))
+                        />
+                    </div>
+                    <label class="create-room-option">
+                        <input
+                            class="uk-checkbox"
+                            type="checkbox"
+                            checked=room.public
+                            onclick=self.link.callback(|_| Msg::TogglePublic)
+                        />
+                        { " " }
+                        {
+                            tr!(
+                                // Option when creating a room, public rooms can be joined by anyone
+                                "Public room, anyone can find and join it"
+                            )
+                        }
+                    </label>
+                    { encryption }
+                    { self.view_invitees() }
+                    <p class="uk-text-right">
+                        <button type="button" class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Close)>
+                            { tr!("Cancel") }
+                        </button>
+                        { " " }
+                        <button class="uk-button uk-button-primary" disabled=self.state.creating>
+                            {
+                                tr!(
+                                    // Button to create the room
+                                    "Create"
+                                )
+                            }
+                        </button>
+                    </p>
+                </form>
+            </div>
+        }
+    }
+}
+
+impl CreateRoomModal {
+    //noinspection RsTypeCheck
+    fn view_invitees(&self) -> Html {
+        let invitees: Html = self
+            .state
+            .invitee_names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                html! {
+                    <span class="invitee">
+                        {name}
+                        <a onclick=self.link.callback(move |_| Msg::RemoveInvitee(index))>
+                            <span class="material-icons">{"close"}</span>
+                        </a>
+                    </span>
+                }
+            })
+            .collect();
+
+        // A full Matrix ID can be invited even if the directory doesn't know it
+        let query = self.state.search_query.trim();
+        let typed_user = UserId::try_from(query)
+            .ok()
+            .filter(|user_id| {
+                !self
+                    .state
+                    .search_results
+                    .iter()
+                    .any(|r| r.user_id == *user_id)
+            })
+            .map(|user_id| UserSearchResult {
+                user_id,
+                display_name: None,
+            });
+        let results: Html = typed_user
+            .iter()
+            .chain(self.state.search_results.iter())
+            .map(|user| {
+                let result = user.clone();
+                html! {
+                    <li>
+                        <a onclick=self.link.callback(move |_| Msg::AddInvitee(result.clone()))>
+                            {user.name()}
+                            <small>{" "}{user.user_id.to_string()}</small>
+                        </a>
+                    </li>
+                }
+            })
+            .collect();
+
+        html! {
+            <div class="create-room-invitees">
+                <label class="uk-form-label">
+                    {
+                        tr!(
+                            // Label of the user search when creating a room
+                            "Invite people"
+                        )
+                    }
+                </label>
+                <div>{invitees}</div>
+                <input
+                    class="uk-input"
+                    placeholder={
+                        tr!(
+                            // Placeholder of the user search when creating a room
+                            "Name or @user:example.org"
+                        )
+                    }
+                    value=&self.state.search_query
+                    oninput=self.link.callback(|e: InputData| Msg::Search(e.value))
+                    // Enter would submit the whole dialog
+                    onkeydown=self.link.batch_callback(|e: KeyboardEvent| {
+                        if e.key() == "Enter" {
+                            e.prevent_default();
+                        }
+                        vec![]
+                    })
+                />
+                <ul class="uk-list search-results">{results}</ul>
+            </div>
+        }
+    }
+}
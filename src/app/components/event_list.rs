@@ -1,42 +1,133 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
 
-use crate::utils::ruma::AnyMessageEventExt;
+use crate::utils::ruma::{AnyRoomEventExt, AnySyncRoomEventExt};
 use log::*;
 use matrix_sdk::{
-    events::{room::message::MessageEventContent, AnyMessageEventContent, AnySyncMessageEvent},
-    identifiers::RoomId,
+    events::{
+        room::message::MessageEventContent, AnyMessageEventContent, AnySyncMessageEvent,
+        AnySyncRoomEvent, AnySyncStateEvent,
+    },
+    identifiers::{EventId, RoomId, UserId},
     Room,
 };
 use yew::{prelude::*, virtual_dom::VList};
 
+use tr::tr;
+
 use crate::app::components::{
-    events::{image::Image, notice::Notice, text::Text, video::Video},
+    event_menu::EventMenu,
+    events::{
+        edit_history::EditHistory,
+        encrypted::Encrypted,
+        image::Image,
+        membership_summary::MembershipSummary,
+        message_body,
+        notice::Notice,
+        reactions::{ReactionGroup, Reactions},
+        read_receipts::{ReadReceipts, Reader},
+        redacted::Redacted,
+        reply_quote::event_element_id,
+        state::{is_join_or_leave, StateEvent},
+        text::Text,
+        video::Video,
+        EventExt, RoomExt,
+    },
     input::Input,
+    member_list::MemberList,
+    thread_list::{ThreadList, ThreadSummary},
+    thread_panel::ThreadPanel,
+};
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    receipts::ReadReceipt,
+    relations::{strip_reply_fallback, MessageRelation, RelatedEvent, Relation, ReplyTo},
+    trust::{RoomTrust, TrustLevel},
+    verification::{VerificationInfo, VerificationState},
+    Request, Response,
 };
-use crate::app::matrix::{MatrixAgent, Request, Response};
 
 pub struct EventList {
+    link: ComponentLink<Self>,
     on_submit: Callback<String>,
+    on_redact: Callback<(EventId, Option<String>)>,
+    on_edit: Callback<EventId>,
+    on_reply: Callback<EventId>,
+    on_react: Callback<(EventId, String)>,
+    on_thread: Callback<EventId>,
     state: State,
-    matrix_agent: Box<dyn Bridge<MatrixAgent>>,
+    matrix_agent: AccountBridge,
     props: Props,
 }
 
 #[derive(Default)]
 pub struct State {
-    // TODO handle all events
-    pub events: HashMap<RoomId, Vec<AnySyncMessageEvent>>,
+    pub events: HashMap<RoomId, Vec<AnySyncRoomEvent>>,
+    pub trust: HashMap<RoomId, RoomTrust>,
+    pub redacted: HashSet<EventId>,
+    pub edits: HashMap<EventId, Edits>,
+    /// The `m.annotation` events of each reacted to message
+    pub reactions: HashMap<EventId, Vec<RelatedEvent>>,
+    /// The messages of each thread by the id of the root, per room
+    pub threads: HashMap<RoomId, HashMap<EventId, Vec<AnySyncMessageEvent>>>,
+    pub open_thread: Option<EventId>,
+    /// The latest read receipt of every member, per room
+    pub receipts: HashMap<RoomId, HashMap<UserId, ReadReceipt>>,
+    /// The event of the last read receipt we sent, per room
+    pub sent_receipts: HashMap<RoomId, EventId>,
+    /// Who is typing right now, per room
+    pub typing: HashMap<RoomId, Vec<UserId>>,
+    /// The room we told the server we are typing in
+    pub own_typing: Option<RoomId>,
+    pub show_threads: bool,
+    /// The own message which is edited with the input right now
+    pub editing: Option<EventId>,
+    pub replying_to: Option<EventId>,
+    /// Reply parents which aren't part of the loaded timeline
+    pub fetched_events: HashMap<EventId, AnySyncMessageEvent>,
+    pub requested_events: HashSet<EventId>,
+    /// Requested events which couldn't be fetched or aren't messages
+    pub unavailable_events: HashSet<EventId>,
+    pub show_edit_history: Option<EventId>,
+    pub show_members: bool,
+}
+
+/// The `m.replace` events of a message
+#[derive(Default)]
+pub struct Edits {
+    /// The content before the first edit, known once the message itself is loaded
+    pub original: Option<(SystemTime, MessageEventContent)>,
+    pub edits: Vec<RelatedEvent>,
 }
 
 #[allow(clippy::large_enum_variant)]
 pub enum Msg {
     NewMessage(Response),
     SendMessage(String),
+    Redact((EventId, Option<String>)),
+    React((EventId, String)),
+    Edit(EventId),
+    EditLast,
+    CancelEdit,
+    Reply(EventId),
+    CancelReply,
+    ShowEditHistory(Option<EventId>),
+    OpenThread(EventId),
+    CloseThread,
+    SendThreadMessage(String),
+    ToggleThreads,
+    Scrolled,
+    Typing((RoomId, Option<Duration>)),
+    ToggleMembers,
     Nope,
 }
 
 #[derive(Clone, PartialEq, Properties, Debug)]
 pub struct Props {
+    pub account: AccountId,
     pub current_room: Rc<Room>,
 }
 
@@ -46,19 +137,46 @@ impl Component for EventList {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let matrix_callback = link.callback(Msg::NewMessage);
-        let mut matrix_agent = MatrixAgent::bridge(matrix_callback);
+        let mut matrix_agent = AccountBridge::new(props.account.clone(), matrix_callback);
 
         let state = State {
             events: Default::default(),
+            trust: Default::default(),
+            redacted: Default::default(),
+            edits: Default::default(),
+            reactions: Default::default(),
+            threads: Default::default(),
+            open_thread: None,
+            receipts: Default::default(),
+            sent_receipts: Default::default(),
+            typing: Default::default(),
+            own_typing: None,
+            show_threads: false,
+            editing: None,
+            replying_to: None,
+            fetched_events: Default::default(),
+            requested_events: Default::default(),
+            unavailable_events: Default::default(),
+            show_edit_history: None,
+            show_members: false,
         };
 
         let room_id = props.current_room.room_id.clone();
+        if cfg!(feature = "encryption") && props.current_room.is_encrypted() {
+            matrix_agent.send(Request::GetRoomTrust(room_id.clone()));
+        }
         if !state.events.contains_key(&room_id) {
             matrix_agent.send(Request::GetOldMessages((room_id, None)));
         }
 
         EventList {
             on_submit: link.callback(Msg::SendMessage),
+            on_redact: link.callback(Msg::Redact),
+            on_edit: link.callback(Msg::Edit),
+            on_reply: link.callback(Msg::Reply),
+            on_react: link.callback(Msg::React),
+            on_thread: link.callback(Msg::OpenThread),
+            link,
             props,
             matrix_agent,
             state,
@@ -68,14 +186,19 @@ impl Component for EventList {
     fn update(&mut self, msg: Self::Message) -> bool {
         match msg {
             Msg::NewMessage(Response::Sync((room_id, raw_msg))) => {
-                // TODO handle all events
                 if let Ok(msg) = raw_msg.deserialize() {
+                    if self.apply_redaction(&msg) {
+                        self.apply_edits(&room_id);
+                        return room_id == self.props.current_room.room_id;
+                    }
                     if self.state.events.contains_key(&room_id) {
                         if !(self.state.events[&room_id]
                             .iter()
                             .any(|x| x.event_id() == msg.event_id()))
                         {
                             self.state.events.get_mut(&room_id).unwrap().push(msg);
+                            self.apply_edits(&room_id);
+                            self.fetch_reply_parents(&room_id);
                             room_id == self.props.current_room.room_id
                         } else {
                             false
@@ -83,6 +206,8 @@ impl Component for EventList {
                     } else {
                         let msgs = vec![msg];
                         self.state.events.insert(room_id.clone(), msgs);
+                        self.apply_edits(&room_id);
+                        self.fetch_reply_parents(&room_id);
                         room_id == self.props.current_room.room_id
                     }
                 } else {
@@ -90,11 +215,12 @@ impl Component for EventList {
                 }
             }
             Msg::NewMessage(Response::OldMessages((room_id, messages))) => {
-                let mut deserialized_messages: Vec<AnySyncMessageEvent> = messages
+                let mut deserialized_messages: Vec<AnySyncRoomEvent> = messages
                     .iter()
                     .map(|x| x.deserialize())
                     .filter_map(Result::ok)
-                    .map(|x| x.without_room_id())
+                    .filter_map(|x| x.without_room_id())
+                    .filter(|x| !self.apply_redaction(x))
                     .collect();
                 // This is a clippy false positive
                 #[allow(clippy::map_entry)]
@@ -104,21 +230,229 @@ impl Component for EventList {
                         .get_mut(&room_id)
                         .unwrap()
                         .append(deserialized_messages.as_mut());
-                    true
                 } else {
-                    self.state.events.insert(room_id, deserialized_messages);
-                    true
+                    self.state
+                        .events
+                        .insert(room_id.clone(), deserialized_messages);
+                }
+                self.apply_edits(&room_id);
+                self.fetch_reply_parents(&room_id);
+                true
+            }
+            Msg::NewMessage(Response::Event((room_id, raw_event))) => {
+                if let Ok(AnySyncRoomEvent::Message(event)) = raw_event.deserialize() {
+                    self.state
+                        .fetched_events
+                        .insert(event.event_id().clone(), event);
+                    room_id == self.props.current_room.room_id
+                } else {
+                    false
+                }
+            }
+            Msg::NewMessage(Response::EventUnavailable((room_id, event_id))) => {
+                self.state.unavailable_events.insert(event_id);
+                room_id == self.props.current_room.room_id
+            }
+            Msg::NewMessage(Response::Relation((room_id, related))) => {
+                // Older pages bring the redaction before the event it redacts
+                if self.state.redacted.contains(&related.event_id) {
+                    return false;
+                }
+                match &related.relation {
+                    Relation::Replace(event_id, _) => {
+                        let edits = self.state.edits.entry(event_id.clone()).or_default();
+                        if edits.edits.iter().any(|x| x.event_id == related.event_id) {
+                            return false;
+                        }
+                        edits.edits.push(related);
+                        edits.edits.sort_by_key(|x| x.origin_server_ts);
+                    }
+                    Relation::Annotation(event_id, _) => {
+                        let reactions = self.state.reactions.entry(event_id.clone()).or_default();
+                        if reactions.iter().any(|x| x.event_id == related.event_id) {
+                            return false;
+                        }
+                        reactions.push(related);
+                    }
+                    Relation::Thread(root, event) => {
+                        let thread = self
+                            .state
+                            .threads
+                            .entry(room_id.clone())
+                            .or_default()
+                            .entry(root.clone())
+                            .or_default();
+                        if thread.iter().any(|x| x.event_id() == event.event_id()) {
+                            return false;
+                        }
+                        thread.push((**event).clone());
+                        thread.sort_by_key(|x| *x.origin_server_ts());
+                        self.fetch_thread_root(&room_id, root.clone());
+                    }
+                }
+                self.apply_edits(&room_id);
+                room_id == self.props.current_room.room_id
+            }
+            Msg::NewMessage(Response::RoomTrust((room_id, trust))) => {
+                self.state.trust.insert(room_id.clone(), trust);
+                room_id == self.props.current_room.room_id
+            }
+            Msg::NewMessage(Response::Verification(VerificationInfo {
+                state: VerificationState::Done,
+                ..
+            })) => {
+                if self.props.current_room.is_encrypted() {
+                    self.matrix_agent.send(Request::GetRoomTrust(
+                        self.props.current_room.room_id.clone(),
+                    ));
                 }
+                false
+            }
+            Msg::NewMessage(Response::ReadReceipts((room_id, receipts))) => {
+                let room_receipts = self.state.receipts.entry(room_id.clone()).or_default();
+                for receipt in receipts {
+                    room_receipts.insert(receipt.user_id.clone(), receipt);
+                }
+                room_id == self.props.current_room.room_id
+            }
+            Msg::NewMessage(Response::ReadReceiptFailed((room_id, event_id))) => {
+                // Allows the next scroll or focus to send it again
+                if self.state.sent_receipts.get(&room_id) == Some(&event_id) {
+                    self.state.sent_receipts.remove(&room_id);
+                }
+                false
+            }
+            // The main view closes the room, this only forgets what was loaded for it
+            Msg::NewMessage(Response::LeftRoom(room_id)) => {
+                self.state.events.remove(&room_id);
+                self.state.trust.remove(&room_id);
+                self.state.threads.remove(&room_id);
+                self.state.receipts.remove(&room_id);
+                self.state.sent_receipts.remove(&room_id);
+                self.state.typing.remove(&room_id);
+                false
+            }
+            Msg::NewMessage(Response::Typing((room_id, user_ids))) => {
+                let changed = room_id == self.props.current_room.room_id;
+                self.state.typing.insert(room_id, user_ids);
+                changed
             }
             Msg::NewMessage(_) => false,
             Msg::SendMessage(message) => {
                 info!("Sending Message");
+                let relation = match (self.state.editing.take(), self.state.replying_to.take()) {
+                    (Some(event_id), _) => Some(MessageRelation::Edit(event_id)),
+                    (None, Some(event_id)) => self.reply_to(&event_id).map(MessageRelation::Reply),
+                    (None, None) => None,
+                };
                 self.matrix_agent.send(Request::SendMessage((
                     self.props.current_room.room_id.clone(),
                     message,
+                    relation,
+                )));
+                true
+            }
+            Msg::Edit(event_id) => {
+                self.state.replying_to = None;
+                self.state.editing = Some(event_id);
+                true
+            }
+            Msg::EditLast => {
+                self.state.editing = self
+                    .room_events()
+                    .iter()
+                    .rev()
+                    .filter_map(|event| match event {
+                        AnySyncRoomEvent::Message(message) => Some(message),
+                        _ => None,
+                    })
+                    .find(|message| self.can_edit(message))
+                    .map(|message| message.event_id().clone());
+                if self.state.editing.is_some() {
+                    self.state.replying_to = None;
+                }
+                self.state.editing.is_some()
+            }
+            Msg::CancelEdit => {
+                self.state.editing = None;
+                true
+            }
+            Msg::Reply(event_id) => {
+                self.state.editing = None;
+                self.state.replying_to = Some(event_id);
+                true
+            }
+            Msg::CancelReply => {
+                self.state.replying_to = None;
+                true
+            }
+            Msg::ShowEditHistory(event_id) => {
+                self.state.show_edit_history = event_id;
+                true
+            }
+            Msg::Redact((event_id, reason)) => {
+                info!("Redacting {}", event_id);
+                self.matrix_agent.send(Request::Redact((
+                    self.props.current_room.room_id.clone(),
+                    event_id,
+                    reason,
                 )));
                 false
             }
+            Msg::React((event_id, key)) => {
+                let room_id = self.props.current_room.room_id.clone();
+                self.matrix_agent
+                    .send(Request::React((room_id, event_id, key)));
+                false
+            }
+            Msg::OpenThread(root) => {
+                self.state.open_thread = Some(root);
+                true
+            }
+            Msg::CloseThread => {
+                self.state.open_thread = None;
+                true
+            }
+            Msg::SendThreadMessage(message) => {
+                let root = match &self.state.open_thread {
+                    Some(root) => root.clone(),
+                    None => return false,
+                };
+                let latest = self
+                    .room_threads()
+                    .and_then(|threads| threads.get(&root))
+                    .and_then(|thread| thread.last())
+                    .map(|event| event.event_id().clone())
+                    .unwrap_or_else(|| root.clone());
+                self.matrix_agent.send(Request::SendMessage((
+                    self.props.current_room.room_id.clone(),
+                    message,
+                    Some(MessageRelation::Thread { root, latest }),
+                )));
+                false
+            }
+            Msg::ToggleThreads => {
+                self.state.show_threads = !self.state.show_threads;
+                true
+            }
+            Msg::Scrolled => {
+                self.send_read_receipt();
+                false
+            }
+            Msg::Typing((room_id, timeout)) => {
+                // Switching rooms already stopped the notice in the old room
+                if timeout.is_none() && self.state.own_typing.as_ref() != Some(&room_id) {
+                    return false;
+                }
+                self.state.own_typing = timeout.map(|_| room_id.clone());
+                self.matrix_agent
+                    .send(Request::SetTyping((room_id, timeout)));
+                false
+            }
+            Msg::ToggleMembers => {
+                self.state.show_members = !self.state.show_members;
+                true
+            }
             Msg::Nope => false,
         }
     }
@@ -126,11 +460,25 @@ impl Component for EventList {
     fn change(&mut self, props: Self::Properties) -> bool {
         if self.props != props {
             let room_id = props.current_room.room_id.clone();
+            if cfg!(feature = "encryption") && props.current_room.is_encrypted() {
+                self.matrix_agent
+                    .send(Request::GetRoomTrust(room_id.clone()));
+            }
             if !self.state.events.contains_key(&room_id) {
                 self.matrix_agent
                     .send(Request::GetOldMessages((room_id, None)));
             }
 
+            // The notice would otherwise only end with its timeout
+            if let Some(typing_room_id) = self.state.own_typing.take() {
+                self.matrix_agent
+                    .send(Request::SetTyping((typing_room_id, None)));
+            }
+            self.state.editing = None;
+            self.state.replying_to = None;
+            self.state.show_edit_history = None;
+            self.state.open_thread = None;
+            self.state.show_threads = false;
             self.props = props;
             true
         } else {
@@ -138,6 +486,10 @@ impl Component for EventList {
         }
     }
 
+    fn rendered(&mut self, _first_render: bool) {
+        self.send_read_receipt();
+    }
+
     fn view(&self) -> Html {
         let events = if self
             .state
@@ -147,36 +499,663 @@ impl Component for EventList {
             let events = &self.state.events[&self.props.current_room.room_id];
 
             let mut html_nodes = VList::new();
-            if let Some(event) = events.first() {
-                html_nodes.add_child(self.get_event(None, event));
+            let mut prev_message: Option<&AnySyncMessageEvent> = None;
+            let mut membership_run: Vec<AnySyncStateEvent> = Vec::new();
+            for event in events {
+                if let AnySyncRoomEvent::State(state_event) = event {
+                    if is_join_or_leave(state_event) {
+                        membership_run.push(state_event.clone());
+                        continue;
+                    }
+                }
+                if !membership_run.is_empty() {
+                    html_nodes.add_child(self.get_membership_run(membership_run.split_off(0)));
+                    prev_message = None;
+                }
+
+                match event {
+                    AnySyncRoomEvent::Message(message)
+                        if self.state.redacted.contains(message.event_id()) =>
+                    {
+                        let new_user = message.is_new_user(prev_message);
+                        html_nodes.add_child(self.get_redacted(message.sender(), new_user));
+                        if new_user {
+                            prev_message = None;
+                        }
+                    }
+                    AnySyncRoomEvent::Message(message) => {
+                        html_nodes.add_child(
+                            self.wrap_event(message, self.get_event(prev_message, message)),
+                        );
+                        prev_message = Some(message);
+                    }
+                    AnySyncRoomEvent::RedactedMessage(message) => {
+                        let new_user = prev_message
+                            .map(|prev| prev.sender() != message.sender())
+                            .unwrap_or(true);
+                        html_nodes.add_child(self.get_redacted(message.sender(), new_user));
+                        if new_user {
+                            prev_message = None;
+                        }
+                    }
+                    AnySyncRoomEvent::State(state_event) => {
+                        html_nodes.add_child(html! {
+                            <StateEvent event=state_event.clone() room=self.props.current_room.clone() />
+                        });
+                        prev_message = None;
+                    }
+                    _ => {}
+                }
+            }
+            if !membership_run.is_empty() {
+                html_nodes.add_child(self.get_membership_run(membership_run));
             }
-            html_nodes.add_children(
-                events
-                    .windows(2)
-                    .map(|e| self.get_event(Some(&e[0]), &e[1])),
-            );
 
             html_nodes.into()
         } else {
             html! {}
         };
 
+        let edit_history = match self.edit_history_versions() {
+            Some(versions) => html! {
+                <EditHistory
+                    versions=versions
+                    on_close=self.link.callback(|_| Msg::ShowEditHistory(None))
+                />
+            },
+            None => html! {},
+        };
+
+        let member_list = if self.state.show_members {
+            html! { <MemberList account=self.props.account.clone() room=self.props.current_room.clone() /> }
+        } else {
+            html! {}
+        };
+
         html! {
             <div class="event-list">
-                <div class="room-title"><div><h1>{ self.props.current_room.display_name() }</h1></div></div>
-                <div class="scrollable message-scrollarea">
-                    <div class="message-container">
-                        { events }
-                        <div id="anchor"></div>
+                <div class="room-title">
+                    <div>
+                        <h1>{ self.props.current_room.display_name() }{ self.room_shield() }</h1>
+                        <a class="members-toggle" onclick=self.link.callback(|_| Msg::ToggleMembers)>
+                            <span class="material-icons">{"people"}</span>
+                        </a>
+                        <a class="threads-toggle" onclick=self.link.callback(|_| Msg::ToggleThreads)>
+                            <span class="material-icons">{"forum"}</span>
+                        </a>
+                    </div>
+                </div>
+                <div class="uk-flex message-scrollarea">
+                    <div class="scrollable uk-width-expand" onscroll=self.link.callback(|_| Msg::Scrolled)>
+                        <div class="message-container">
+                            { events }
+                            <div id="anchor"></div>
+                        </div>
                     </div>
+                    { member_list }
+                    { self.view_threads() }
                 </div>
-                <Input on_submit=&self.on_submit/>
+                { self.view_typing() }
+                <Input
+                    room_id=self.props.current_room.room_id.clone()
+                    on_submit=&self.on_submit
+                    encrypted=self.props.current_room.is_encrypted()
+                    editing=self.editing_body()
+                    replying_to=self.reply_preview()
+                    on_edit_last=self.link.callback(|_| Msg::EditLast)
+                    on_cancel_edit=self.link.callback(|_| Msg::CancelEdit)
+                    on_cancel_reply=self.link.callback(|_| Msg::CancelReply)
+                    on_typing=self.link.callback(Msg::Typing)
+                />
+                { edit_history }
             </div>
         }
     }
 }
 
 impl EventList {
+    fn room_events(&self) -> &[AnySyncRoomEvent] {
+        self.state
+            .events
+            .get(&self.props.current_room.room_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn find_message(&self, event_id: &EventId) -> Option<&AnySyncMessageEvent> {
+        self.room_events().iter().find_map(|event| match event {
+            AnySyncRoomEvent::Message(message) if message.event_id() == event_id => Some(message),
+            _ => None,
+        })
+    }
+
+    /// Only own text messages can be edited
+    fn can_edit(&self, event: &AnySyncMessageEvent) -> bool {
+        match event {
+            AnySyncMessageEvent::RoomMessage(message) => {
+                message.sender == self.props.current_room.own_user_id
+                    && !self.state.redacted.contains(&message.event_id)
+                    && matches!(message.content, MessageEventContent::Text(_))
+            }
+            _ => false,
+        }
+    }
+
+    fn editing_body(&self) -> Option<String> {
+        let event_id = self.state.editing.as_ref()?;
+        match self.find_message(event_id)? {
+            AnySyncMessageEvent::RoomMessage(message) => {
+                Some(message_body(&message.content).to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// A message from the timeline or one that was fetched on its own
+    fn lookup_message(&self, event_id: &EventId) -> Option<AnySyncMessageEvent> {
+        self.find_message(event_id)
+            .or_else(|| self.state.fetched_events.get(event_id))
+            .cloned()
+    }
+
+    /// Fetches the parents of replies which aren't loaded, so their quote can be shown
+    fn fetch_reply_parents(&mut self, room_id: &RoomId) {
+        let missing: Vec<EventId> = match self.state.events.get(room_id) {
+            Some(events) => events
+                .iter()
+                .filter_map(|event| match event {
+                    AnySyncRoomEvent::Message(message) => reply_parent_id(message),
+                    _ => None,
+                })
+                .filter(|parent| {
+                    !self.state.fetched_events.contains_key(parent)
+                        && !self.state.requested_events.contains(parent)
+                        && !events.iter().any(|event| event.event_id() == *parent)
+                })
+                .cloned()
+                .collect(),
+            None => return,
+        };
+        for event_id in missing {
+            self.state.requested_events.insert(event_id.clone());
+            self.matrix_agent
+                .send(Request::GetEvent((room_id.clone(), event_id)));
+        }
+    }
+
+    fn reply_to(&self, event_id: &EventId) -> Option<ReplyTo> {
+        match self.find_message(event_id)? {
+            AnySyncMessageEvent::RoomMessage(message) => Some(ReplyTo {
+                event_id: message.event_id.clone(),
+                sender: message.sender.clone(),
+                body: message_body(&message.content).to_string(),
+                formatted_body: match &message.content {
+                    MessageEventContent::Text(text) => text
+                        .formatted
+                        .as_ref()
+                        .map(|formatted| formatted.body.clone()),
+                    MessageEventContent::Notice(notice) => notice
+                        .formatted
+                        .as_ref()
+                        .map(|formatted| formatted.body.clone()),
+                    _ => None,
+                },
+            }),
+            _ => None,
+        }
+    }
+
+    /// "Alice: the message" for quotes and previews
+    fn message_preview(&self, event: &AnySyncMessageEvent) -> String {
+        let body = match event {
+            AnySyncMessageEvent::RoomMessage(message) => {
+                strip_reply_fallback(message_body(&message.content))
+            }
+            _ => "",
+        };
+        format!(
+            "{}: {}",
+            self.props.current_room.get_sender_displayname(event),
+            body
+        )
+    }
+
+    fn reply_preview(&self) -> Option<String> {
+        let event = self.find_message(self.state.replying_to.as_ref()?)?;
+        Some(self.message_preview(event))
+    }
+
+    fn room_threads(&self) -> Option<&HashMap<EventId, Vec<AnySyncMessageEvent>>> {
+        self.state.threads.get(&self.props.current_room.room_id)
+    }
+
+    /// Roots outside of the loaded timeline are fetched for the thread list
+    fn fetch_thread_root(&mut self, room_id: &RoomId, root: EventId) {
+        let loaded = self.state.events.get(room_id).map_or(false, |events| {
+            events.iter().any(|event| *event.event_id() == root)
+        });
+        if loaded
+            || self.state.fetched_events.contains_key(&root)
+            || self.state.requested_events.contains(&root)
+        {
+            return;
+        }
+        self.state.requested_events.insert(root.clone());
+        self.matrix_agent
+            .send(Request::GetEvent((room_id.clone(), root)));
+    }
+
+    fn thread_summary(&self, root: &EventId) -> Option<ThreadSummary> {
+        let thread = self.room_threads()?.get(root)?;
+        let latest = thread.last()?;
+        let root_preview = match self.lookup_message(root) {
+            Some(event) => self.message_preview(&event),
+            None if self.state.unavailable_events.contains(root) => tr!(
+                // Shown instead of the root of a thread if it can't be loaded
+                "Original message unavailable"
+            ),
+            None => tr!(
+                // Shown instead of the root of a thread while it is loaded
+                "Loading…"
+            ),
+        };
+        Some(ThreadSummary {
+            root: root.clone(),
+            root_preview,
+            reply_count: thread.len(),
+            latest_preview: self.message_preview(latest),
+        })
+    }
+
+    //noinspection RsTypeCheck
+    fn view_typing(&self) -> Html {
+        let room = &self.props.current_room;
+        let names: Vec<&str> = match self.state.typing.get(&room.room_id) {
+            Some(user_ids) => user_ids
+                .iter()
+                .filter(|user_id| **user_id != room.own_user_id)
+                .map(|user_id| room.get_user_displayname(user_id))
+                .collect(),
+            None => Vec::new(),
+        };
+        let text = match names.as_slice() {
+            [] => return html! {},
+            [name] => tr!(
+                // Shown above the input, {0} is a display name
+                "{0} is typing…",
+                name
+            ),
+            [first, second] => tr!(
+                // Shown above the input, {0} and {1} are display names
+                "{0} and {1} are typing…",
+                first,
+                second
+            ),
+            [first, second, rest @ ..] => tr!(
+                // Shown above the input, {0} and {1} are display names, {2} is a number
+                "{0}, {1} and {2} others are typing…",
+                first,
+                second,
+                rest.len()
+            ),
+        };
+
+        html! {
+            <div class="typing-notice">{ text }</div>
+        }
+    }
+
+    //noinspection RsTypeCheck
+    fn view_threads(&self) -> Html {
+        if let Some(root) = &self.state.open_thread {
+            let replies = self
+                .room_threads()
+                .and_then(|threads| threads.get(root))
+                .cloned()
+                .unwrap_or_default();
+            return html! {
+                <ThreadPanel
+                    root=self.lookup_message(root)
+                    replies=replies
+                    room=self.props.current_room.clone()
+                    on_send=self.link.callback(Msg::SendThreadMessage)
+                    on_close=self.link.callback(|_| Msg::CloseThread)
+                />
+            };
+        }
+        if !self.state.show_threads {
+            return html! {};
+        }
+
+        let mut threads: Vec<(SystemTime, ThreadSummary)> = self
+            .room_threads()
+            .map(|threads| {
+                threads
+                    .iter()
+                    .filter_map(|(root, thread)| {
+                        let latest = *thread.last()?.origin_server_ts();
+                        Some((latest, self.thread_summary(root)?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        threads.sort_by(|a, b| b.0.cmp(&a.0));
+
+        html! {
+            <ThreadList
+                threads=threads.into_iter().map(|(_, summary)| summary).collect::<Vec<_>>()
+                on_open=self.link.callback(Msg::OpenThread)
+                on_close=self.link.callback(|_| Msg::ToggleThreads)
+            />
+        }
+    }
+
+    /// Shows the latest edit of every edited message in the room
+    fn apply_edits(&mut self, room_id: &RoomId) {
+        let events = match self.state.events.get_mut(room_id) {
+            Some(events) => events,
+            None => return,
+        };
+        for event in events.iter_mut() {
+            if let AnySyncRoomEvent::Message(AnySyncMessageEvent::RoomMessage(message)) = event {
+                if let Some(edits) = self.state.edits.get_mut(&message.event_id) {
+                    // Only the original sender may edit a message
+                    edits.edits.retain(|edit| edit.sender == message.sender);
+                    if edits.original.is_none() {
+                        edits.original = Some((message.origin_server_ts, message.content.clone()));
+                    }
+                    // Falls back to the original once every edit was redacted
+                    let content = match edits.edits.last().map(|edit| &edit.relation) {
+                        Some(Relation::Replace(_, content)) => Some(content),
+                        _ => edits.original.as_ref().map(|(_, content)| content),
+                    };
+                    if let Some(content) = content {
+                        message.content = content.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    /// The newest message of the open room, by time as older pages are appended
+    fn newest_message(&self) -> Option<&AnySyncMessageEvent> {
+        self.room_events()
+            .iter()
+            .filter_map(|event| match event {
+                AnySyncRoomEvent::Message(message) => Some(message),
+                _ => None,
+            })
+            .max_by_key(|message| *message.origin_server_ts())
+    }
+
+    /// Marks the newest message as read once it is on screen and the tab is visible
+    fn send_read_receipt(&mut self) {
+        let room_id = self.props.current_room.room_id.clone();
+        let event_id = match self.newest_message() {
+            Some(event) => event.event_id().clone(),
+            None => return,
+        };
+        if self.state.sent_receipts.get(&room_id) == Some(&event_id) || !is_anchor_visible() {
+            return;
+        }
+        self.matrix_agent.send(Request::SendReadReceipt((
+            room_id.clone(),
+            event_id.clone(),
+        )));
+        // Recorded right away so scrolling doesn't send it again while the request runs,
+        // `ReadReceiptFailed` clears it again
+        self.state.sent_receipts.insert(room_id, event_id);
+    }
+
+    /// Everybody else whose latest read receipt points at the event
+    fn readers(&self, event_id: &EventId) -> Vec<Reader> {
+        let receipts = match self.state.receipts.get(&self.props.current_room.room_id) {
+            Some(receipts) => receipts,
+            None => return Vec::new(),
+        };
+        let mut readers: Vec<&ReadReceipt> = receipts
+            .values()
+            .filter(|receipt| {
+                receipt.event_id == *event_id
+                    && receipt.user_id != self.props.current_room.own_user_id
+            })
+            .collect();
+        readers.sort_by_key(|receipt| receipt.ts);
+        readers
+            .into_iter()
+            .map(|receipt| Reader {
+                name: self
+                    .props
+                    .current_room
+                    .get_user_displayname(&receipt.user_id)
+                    .to_string(),
+                ts: receipt.ts,
+            })
+            .collect()
+    }
+
+    fn is_edited(&self, event_id: &EventId) -> bool {
+        self.state
+            .edits
+            .get(event_id)
+            .map(|edits| edits.original.is_some() && !edits.edits.is_empty())
+            .unwrap_or(false)
+    }
+
+    fn edit_history_versions(&self) -> Option<Vec<(SystemTime, String)>> {
+        let edits = self
+            .state
+            .edits
+            .get(self.state.show_edit_history.as_ref()?)?;
+        let original = edits.original.as_ref()?;
+        let versions = std::iter::once((original.0, message_body(&original.1).to_string()))
+            .chain(edits.edits.iter().filter_map(|edit| match &edit.relation {
+                Relation::Replace(_, content) => {
+                    Some((edit.origin_server_ts, message_body(content).to_string()))
+                }
+                _ => None,
+            }))
+            .collect();
+        Some(versions)
+    }
+
+    fn sender_trust(&self, event: &AnySyncMessageEvent) -> Option<TrustLevel> {
+        self.state
+            .trust
+            .get(&self.props.current_room.room_id)?
+            .users
+            .get(&event.sender())
+            .copied()
+    }
+
+    //noinspection RsTypeCheck
+    fn room_shield(&self) -> Html {
+        match self.state.trust.get(&self.props.current_room.room_id) {
+            Some(trust) if trust.has_unverified_devices() => html! {
+                <span class="material-icons shield unverified">{"gpp_bad"}</span>
+            },
+            Some(_) => html! {
+                <span class="material-icons shield verified">{"verified_user"}</span>
+            },
+            None => html! {},
+        }
+    }
+
+    /// Remembers the target of a redaction, returns whether the event was one.
+    ///
+    /// Edits and thread messages aren't part of the timeline, so they are dropped right away.
+    fn apply_redaction(&mut self, event: &AnySyncRoomEvent) -> bool {
+        if let AnySyncRoomEvent::Message(AnySyncMessageEvent::RoomRedaction(redaction)) = event {
+            let redacts = &redaction.redacts;
+            self.state.redacted.insert(redacts.clone());
+            for edits in self.state.edits.values_mut() {
+                edits.edits.retain(|edit| edit.event_id != *redacts);
+            }
+            for threads in self.state.threads.values_mut() {
+                for thread in threads.values_mut() {
+                    thread.retain(|message| message.event_id() != redacts);
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    //noinspection RsTypeCheck
+    fn get_redacted(&self, sender: &UserId, new_user: bool) -> Html {
+        html! {
+            <Redacted sender=sender.clone() new_user=new_user room=self.props.current_room.clone() />
+        }
+    }
+
+    /// Groups the reactions of a message by emoji, in the order they were first used
+    fn reaction_groups(&self, event_id: &EventId) -> Vec<ReactionGroup> {
+        let mut groups: Vec<ReactionGroup> = Vec::new();
+        let reactions = match self.state.reactions.get(event_id) {
+            Some(reactions) => reactions,
+            None => return groups,
+        };
+        let own_user_id = &self.props.current_room.own_user_id;
+
+        for reaction in reactions {
+            let key = match &reaction.relation {
+                Relation::Annotation(_, key) => key,
+                _ => continue,
+            };
+            if self.state.redacted.contains(&reaction.event_id) {
+                continue;
+            }
+
+            let index = match groups.iter().position(|group| group.key == *key) {
+                Some(index) => index,
+                None => {
+                    groups.push(ReactionGroup {
+                        key: key.clone(),
+                        senders: Vec::new(),
+                        own_reaction: None,
+                    });
+                    groups.len() - 1
+                }
+            };
+            let group = &mut groups[index];
+            // Every user counts once per emoji
+            if group.senders.contains(&reaction.sender) {
+                continue;
+            }
+            group.senders.push(reaction.sender.clone());
+            if reaction.sender == *own_user_id {
+                group.own_reaction = Some(reaction.event_id.clone());
+            }
+        }
+        groups
+    }
+
+    /// Adds the actions menu, the edited marker and the reactions to a rendered message
+    //noinspection RsTypeCheck
+    fn wrap_event(&self, event: &AnySyncMessageEvent, content: Html) -> Html {
+        let edited = if self.is_edited(event.event_id()) {
+            let event_id = event.event_id().clone();
+            html! {
+                <a
+                    class="edited-marker"
+                    onclick=self.link.callback(move |_| Msg::ShowEditHistory(Some(event_id.clone())))
+                >
+                    {
+                        tr!(
+                            // Marker behind a message which was edited, opens the edit history
+                            "(edited)"
+                        )
+                    }
+                </a>
+            }
+        } else {
+            html! {}
+        };
+        let groups = self.reaction_groups(event.event_id());
+        let reactions = if groups.is_empty() {
+            html! {}
+        } else {
+            let event_id = event.event_id().clone();
+            html! {
+                <Reactions
+                    groups=groups
+                    room=self.props.current_room.clone()
+                    on_react=self.link.callback(move |key| Msg::React((event_id.clone(), key)))
+                    on_redact=self.link.callback(|event_id| Msg::Redact((event_id, None)))
+                />
+            }
+        };
+        let classes = if self.state.editing.as_ref() == Some(event.event_id())
+            || self.state.replying_to.as_ref() == Some(event.event_id())
+        {
+            "event selected"
+        } else {
+            "event"
+        };
+
+        let thread = match self.thread_summary(event.event_id()) {
+            Some(summary) => {
+                let root = summary.root.clone();
+                html! {
+                    <a class="thread-summary" onclick=self.link.callback(move |_| Msg::OpenThread(root.clone()))>
+                        <span class="material-icons">{"forum"}</span>
+                        {
+                            tr!(
+                                // {0} is the number of replies in a thread, {1} "Sender: message" of the latest one
+                                "{0} replies · {1}",
+                                summary.reply_count,
+                                summary.latest_preview
+                            )
+                        }
+                    </a>
+                }
+            }
+            None => html! {},
+        };
+
+        let readers = self.readers(event.event_id());
+        let read_receipts = if readers.is_empty() {
+            html! {}
+        } else {
+            html! { <ReadReceipts readers=readers /> }
+        };
+
+        html! {
+            <div class=classes id=event_element_id(event.event_id())>
+                { content }
+                { edited }
+                { reactions }
+                { thread }
+                { read_receipts }
+                <EventMenu
+                    event_id=event.event_id().clone()
+                    can_edit=self.can_edit(event)
+                    on_edit=self.on_edit.clone()
+                    on_reply=self.on_reply.clone()
+                    on_react=self.on_react.clone()
+                    on_thread=self.on_thread.clone()
+                    can_redact=self.props.current_room.can_redact(event.sender())
+                    on_redact=self.on_redact.clone()
+                />
+            </div>
+        }
+    }
+
+    /// Runs of joins and leaves get collapsed into a summary line
+    //noinspection RsTypeCheck
+    fn get_membership_run(&self, mut events: Vec<AnySyncStateEvent>) -> Html {
+        if events.len() == 1 {
+            html! {
+                <StateEvent event=events.remove(0) room=self.props.current_room.clone() />
+            }
+        } else {
+            html! {
+                <MembershipSummary events=events room=self.props.current_room.clone() />
+            }
+        }
+    }
+
     // Typeinspection of IDEA breaks with this :D
     //noinspection RsTypeCheck
     fn get_event(
@@ -184,8 +1163,6 @@ impl EventList {
         prev_event: Option<&AnySyncMessageEvent>,
         event: &AnySyncMessageEvent,
     ) -> Html {
-        // TODO make encryption supported
-
         match &event.content() {
             AnyMessageEventContent::RoomMessage(room_message) => match room_message {
                 MessageEventContent::Text(text_event) => {
@@ -195,6 +1172,9 @@ impl EventList {
                             event=event.clone()
                             room=self.props.current_room.clone()
                             text_event=text_event.clone()
+                            trust=self.sender_trust(event)
+                            reply_parent=reply_parent_id(event).and_then(|id| self.lookup_message(id))
+                            reply_parent_unavailable=reply_parent_id(event).map_or(false, |id| self.state.unavailable_events.contains(id))
                         />
                     }
                 }
@@ -205,6 +1185,9 @@ impl EventList {
                             event=event.clone()
                             room=self.props.current_room.clone()
                             notice_event=notice_event.clone()
+                            trust=self.sender_trust(event)
+                            reply_parent=reply_parent_id(event).and_then(|id| self.lookup_message(id))
+                            reply_parent_unavailable=reply_parent_id(event).map_or(false, |id| self.state.unavailable_events.contains(id))
                         />
                     }
                 }
@@ -215,6 +1198,7 @@ impl EventList {
                             event=event.clone()
                             room=self.props.current_room.clone()
                             image_event=image_event.clone()
+                            trust=self.sender_trust(event)
                         />
                     }
                 }
@@ -225,12 +1209,52 @@ impl EventList {
                             event=event.clone()
                             room=self.props.current_room.clone()
                             video_event=video_event.clone()
+                            trust=self.sender_trust(event)
                         />
                     }
                 }
                 _ => html! {},
             },
+            AnyMessageEventContent::RoomEncrypted(_) => {
+                html! {
+                    <Encrypted
+                        prev_event=prev_event.cloned()
+                        event=event.clone()
+                        room=self.props.current_room.clone()
+                    />
+                }
+            }
             _ => html! {},
         }
     }
 }
+
+/// The event a text message or notice replies to
+fn reply_parent_id(event: &AnySyncMessageEvent) -> Option<&EventId> {
+    let relates_to = match event {
+        AnySyncMessageEvent::RoomMessage(message) => match &message.content {
+            MessageEventContent::Text(text) => text.relates_to.as_ref(),
+            MessageEventContent::Notice(notice) => notice.relates_to.as_ref(),
+            _ => None,
+        },
+        _ => None,
+    }?;
+    Some(&relates_to.in_reply_to.event_id)
+}
+
+/// Whether the end of the timeline is scrolled into view in a visible tab
+fn is_anchor_visible() -> bool {
+    let document = yew::utils::document();
+    if document.hidden() {
+        return false;
+    }
+    let window_height = yew::utils::window()
+        .inner_height()
+        .ok()
+        .and_then(|height| height.as_f64())
+        .unwrap_or_default();
+    match document.get_element_by_id("anchor") {
+        Some(anchor) => anchor.get_bounding_client_rect().top() <= window_height,
+        None => false,
+    }
+}
@@ -0,0 +1,276 @@
+use matrix_sdk::identifiers::EventId;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+use crate::app::components::reaction_picker::ReactionPicker;
+
+/// The actions of a single message which show up when hovering it
+pub(crate) struct EventMenu {
+    props: Props,
+    link: ComponentLink<Self>,
+    state: State,
+}
+
+#[derive(Default)]
+pub struct State {
+    open: bool,
+    picking_reaction: bool,
+    confirm_redact: bool,
+    reason: String,
+}
+
+pub enum Msg {
+    ToggleMenu,
+    PickReaction,
+    React(String),
+    Reply,
+    Thread,
+    Edit,
+    AskRedact,
+    SetReason(String),
+    Redact,
+    Cancel,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    pub event_id: EventId,
+    #[prop_or_default]
+    pub on_react: Callback<(EventId, String)>,
+    #[prop_or_default]
+    pub on_reply: Callback<EventId>,
+    #[prop_or_default]
+    pub on_thread: Callback<EventId>,
+    #[prop_or_default]
+    pub can_edit: bool,
+    #[prop_or_default]
+    pub on_edit: Callback<EventId>,
+    #[prop_or_default]
+    pub can_redact: bool,
+    #[prop_or_default]
+    pub on_redact: Callback<(EventId, Option<String>)>,
+}
+
+impl Component for EventMenu {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        EventMenu {
+            props,
+            link,
+            state: Default::default(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ToggleMenu => {
+                self.state.open = !self.state.open;
+                self.state.picking_reaction = false;
+                self.state.confirm_redact = false;
+            }
+            Msg::PickReaction => {
+                self.state.picking_reaction = true;
+            }
+            Msg::React(key) => {
+                self.props.on_react.emit((self.props.event_id.clone(), key));
+                self.state = Default::default();
+            }
+            Msg::Reply => {
+                self.props.on_reply.emit(self.props.event_id.clone());
+                self.state = Default::default();
+            }
+            Msg::Thread => {
+                self.props.on_thread.emit(self.props.event_id.clone());
+                self.state = Default::default();
+            }
+            Msg::Edit => {
+                self.props.on_edit.emit(self.props.event_id.clone());
+                self.state = Default::default();
+            }
+            Msg::AskRedact => {
+                self.state.confirm_redact = true;
+            }
+            Msg::SetReason(reason) => {
+                self.state.reason = reason;
+            }
+            Msg::Redact => {
+                let reason = Some(self.state.reason.trim().to_string()).filter(|r| !r.is_empty());
+                self.props
+                    .on_redact
+                    .emit((self.props.event_id.clone(), reason));
+                self.state = Default::default();
+            }
+            Msg::Cancel => {
+                self.state = Default::default();
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        // Keep the menu visible while it is used, even when the mouse leaves the message
+        let classes = if self.state.open {
+            "event-menu open"
+        } else {
+            "event-menu"
+        };
+        let dropdown = if self.state.confirm_redact {
+            self.view_confirm_redact()
+        } else if self.state.picking_reaction {
+            html! {
+                <div class="event-menu-dropdown">
+                    <ReactionPicker on_pick=self.link.callback(Msg::React) />
+                </div>
+            }
+        } else if self.state.open {
+            self.view_actions()
+        } else {
+            html! {}
+        };
+
+        html! {
+            <div class=classes>
+                <a
+                    class="event-menu-toggle"
+                    title={
+                        tr!(
+                            // Tooltip of the button which opens the actions of a message
+                            "More"
+                        )
+                    }
+                    onclick=self.link.callback(|_| Msg::ToggleMenu)
+                >
+                    <span class="material-icons">{"more_horiz"}</span>
+                </a>
+                { dropdown }
+            </div>
+        }
+    }
+}
+
+impl EventMenu {
+    //noinspection RsTypeCheck
+    fn view_actions(&self) -> Html {
+        let edit = if self.props.can_edit {
+            html! {
+                <li>
+                    <a onclick=self.link.callback(|_| Msg::Edit)>
+                        <span class="material-icons">{"edit"}</span>
+                        {
+                            tr!(
+                                // Action of a message to edit it
+                                "Edit"
+                            )
+                        }
+                    </a>
+                </li>
+            }
+        } else {
+            html! {}
+        };
+        let redact = if self.props.can_redact {
+            html! {
+                <li>
+                    <a onclick=self.link.callback(|_| Msg::AskRedact)>
+                        <span class="material-icons">{"delete"}</span>
+                        {
+                            tr!(
+                                // Action of a message to redact it
+                                "Delete"
+                            )
+                        }
+                    </a>
+                </li>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <ul class="event-menu-dropdown uk-nav uk-dropdown-nav">
+                <li>
+                    <a onclick=self.link.callback(|_| Msg::PickReaction)>
+                        <span class="material-icons">{"add_reaction"}</span>
+                        {
+                            tr!(
+                                // Action of a message to add an emoji reaction
+                                "React"
+                            )
+                        }
+                    </a>
+                </li>
+                <li>
+                    <a onclick=self.link.callback(|_| Msg::Reply)>
+                        <span class="material-icons">{"reply"}</span>
+                        {
+                            tr!(
+                                // Action of a message to reply to it
+                                "Reply"
+                            )
+                        }
+                    </a>
+                </li>
+                <li>
+                    <a onclick=self.link.callback(|_| Msg::Thread)>
+                        <span class="material-icons">{"forum"}</span>
+                        {
+                            tr!(
+                                // Action of a message to open its thread or start one
+                                "Reply in thread"
+                            )
+                        }
+                    </a>
+                </li>
+                { edit }
+                { redact }
+            </ul>
+        }
+    }
+
+    //noinspection RsTypeCheck
+    fn view_confirm_redact(&self) -> Html {
+        html! {
+            <form
+                class="event-menu-dropdown"
+                onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::Redact })
+            >
+                <p>
+                    {
+                        tr!(
+                            // Question before a message gets redacted
+                            "Delete this message?"
+                        )
+                    }
+                </p>
+                <input
+                    class="uk-input"
+                    placeholder={
+                        tr!(
+                            // The optional reason given when deleting a message
+                            "Reason (optional)"
+                        )
+                    }
+                    value=&self.state.reason
+                    oninput=self.link.callback(|e: InputData| Msg::SetReason(e.value))
+                />
+                <div class="event-menu-buttons">
+                    <button type="button" class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Cancel)>
+                        { tr!("Cancel") }
+                    </button>
+                    <button class="uk-button uk-button-danger">
+                        { tr!("Delete") }
+                    </button>
+                </div>
+            </form>
+        }
+    }
+}
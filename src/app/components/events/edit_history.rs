@@ -0,0 +1,84 @@
+use std::time::SystemTime;
+
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+use crate::utils::time::format_timestamp;
+
+/// Lists all versions of an edited message with the latest one on top
+pub(crate) struct EditHistory {
+    props: Props,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    Close,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    pub versions: Vec<(SystemTime, String)>,
+    #[prop_or_default]
+    pub on_close: Callback<()>,
+}
+
+impl Component for EditHistory {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        EditHistory { props, link }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Close => self.props.on_close.emit(()),
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let versions: Html = self
+            .props
+            .versions
+            .iter()
+            .rev()
+            .map(|(timestamp, body)| {
+                html! {
+                    <li>
+                        <span class="uk-text-meta">{format_timestamp(*timestamp)}</span>
+                        <p>{body}</p>
+                    </li>
+                }
+            })
+            .collect();
+
+        html! {
+            <div class="uk-modal uk-open edit-history-modal" style="display: block;">
+                <div class="uk-modal-dialog uk-modal-body">
+                    <h2 class="uk-modal-title">
+                        {
+                            tr!(
+                                // Title of the dialog which lists all versions of an edited message
+                                "Message edits"
+                            )
+                        }
+                    </h2>
+                    <ul class="uk-list uk-list-divider">{versions}</ul>
+                    <p class="uk-text-right">
+                        <button class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Close)>
+                            { tr!("Close") }
+                        </button>
+                    </p>
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::rc::Rc;
+
+use crate::app::components::events::{EventExt, RoomExt};
+use matrix_sdk::{events::AnySyncMessageEvent, Room};
+use yew::prelude::*;
+
+use tr::tr;
+
+/// Placeholder for Megolm events we don't have the keys for (yet)
+pub(crate) struct Encrypted {
+    props: Props,
+}
+
+#[derive(Clone, Properties, Debug)]
+pub struct Props {
+    #[prop_or_default]
+    pub prev_event: Option<AnySyncMessageEvent>,
+    pub event: AnySyncMessageEvent,
+    pub room: Rc<Room>,
+}
+
+impl Component for Encrypted {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Encrypted { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> bool {
+        false
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> bool {
+        // TODO fix the PartialEq hack
+        true
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let new_user = self.props.event.is_new_user(self.props.prev_event.as_ref());
+        let placeholder = tr!(
+            // Shown instead of a message we don't have the decryption keys for
+            "Unable to decrypt this message."
+        );
+
+        if new_user {
+            let sender_displayname = self.props.room.get_sender_displayname(&self.props.event);
+            html! {
+                <p class="encrypted-event">
+                    <displayname>{sender_displayname}{": "}</displayname>
+                    <span class="material-icons">{"lock"}</span>
+                    <em>{placeholder}</em>
+                </p>
+            }
+        } else {
+            html! {
+                <p class="encrypted-event">
+                    <span class="material-icons">{"lock"}</span>
+                    <em>{placeholder}</em>
+                </p>
+            }
+        }
+    }
+}
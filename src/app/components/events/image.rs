@@ -1,6 +1,7 @@
 use std::rc::Rc;
 
-use crate::app::components::events::{EventExt, RoomExt};
+use crate::app::components::events::{trust_shield_html, EventExt, RoomExt};
+use crate::app::matrix::trust::TrustLevel;
 use matrix_sdk::{
     events::{room::message::ImageMessageEventContent, AnySyncMessageEvent},
     Room,
@@ -19,6 +20,8 @@ pub struct Props {
     pub event: AnySyncMessageEvent,
     pub image_event: ImageMessageEventContent,
     pub room: Rc<Room>,
+    #[prop_or_default]
+    pub trust: Option<TrustLevel>,
 }
 
 impl Component for Image {
@@ -70,7 +73,7 @@ impl Component for Image {
             if new_user {
                 html! {
                     <div>
-                        <p><displayname>{sender_displayname}{": "}</displayname></p>
+                        <p><displayname>{sender_displayname}{trust_shield_html(self.props.trust)}{": "}</displayname></p>
                         <a href=lightbox_href_full><div class="thumbnail-container"><img src=thumbnail/></div></a>
                         <div class="lightbox short-animate" id=lightbox_id_full>
                             <img class="long-animate" src=image_url/>
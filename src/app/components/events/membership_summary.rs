@@ -0,0 +1,138 @@
+use std::rc::Rc;
+
+use matrix_sdk::{
+    events::{room::member::MembershipState, AnySyncStateEvent},
+    Room,
+};
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::components::events::state::{member_displayname, StateEvent};
+
+/// How many names are listed before the rest is only counted
+const MAX_NAMES: usize = 3;
+
+/// Collapses a run of joins and leaves into a single line which can be expanded
+pub(crate) struct MembershipSummary {
+    props: Props,
+    link: ComponentLink<Self>,
+    expanded: bool,
+}
+
+pub enum Msg {
+    Toggle,
+}
+
+#[derive(Clone, Properties, Debug)]
+pub struct Props {
+    pub events: Vec<AnySyncStateEvent>,
+    pub room: Rc<Room>,
+}
+
+impl Component for MembershipSummary {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        MembershipSummary {
+            props,
+            link,
+            expanded: false,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Toggle => {
+                self.expanded = !self.expanded;
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        // TODO fix the PartialEq hack
+        self.props = props;
+        true
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let mut joined: Vec<String> = Vec::new();
+        let mut left: Vec<String> = Vec::new();
+        for event in &self.props.events {
+            if let AnySyncStateEvent::RoomMember(event) = event {
+                let name = member_displayname(&self.props.room, event);
+                let names = match event.content.membership {
+                    MembershipState::Join => &mut joined,
+                    _ => &mut left,
+                };
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        let mut parts = Vec::new();
+        if !joined.is_empty() {
+            parts.push(tr!(
+                // {0} is a list of users like "Alice, Bob and 2 others"
+                "{0} joined",
+                name_list(&joined)
+            ));
+        }
+        if !left.is_empty() {
+            parts.push(tr!(
+                // {0} is a list of users like "Alice, Bob and 2 others"
+                "{0} left",
+                name_list(&left)
+            ));
+        }
+
+        let details = if self.expanded {
+            self.props
+                .events
+                .iter()
+                .map(|event| html! { <StateEvent event=event.clone() room=self.props.room.clone() /> })
+                .collect::<Html>()
+        } else {
+            html! {}
+        };
+        let icon = if self.expanded {
+            "expand_less"
+        } else {
+            "expand_more"
+        };
+
+        html! {
+            <div class="membership-summary">
+                <p class="state-event" onclick=self.link.callback(|_| Msg::Toggle)>
+                    <span class="material-icons">{"people"}</span>
+                    { parts.join("; ") }
+                    <span class="material-icons">{icon}</span>
+                </p>
+                { details }
+            </div>
+        }
+    }
+}
+
+fn name_list(names: &[String]) -> String {
+    match names.len() {
+        0 => String::new(),
+        1 => names[0].clone(),
+        len if len <= MAX_NAMES => tr!(
+            // {0} is a comma separated list of users, {1} the last user
+            "{0} and {1}",
+            names[..len - 1].join(", "),
+            names[len - 1]
+        ),
+        len => tr!(
+            // {0} is a comma separated list of users, {1} how many more there are
+            "{0} and {1} others",
+            names[..MAX_NAMES].join(", "),
+            len - MAX_NAMES
+        ),
+    }
+}
@@ -1,11 +1,25 @@
-use matrix_sdk::events::AnySyncMessageEvent;
+use matrix_sdk::events::{room::message::MessageEventContent, AnySyncMessageEvent};
+use matrix_sdk::identifiers::UserId;
+use matrix_sdk::js_int::Int;
+use matrix_sdk::presence::PresenceState;
 use matrix_sdk::Room;
 use url::Url;
+use yew::prelude::*;
 
+use crate::app::matrix::presence::PresenceInfo;
+use crate::app::matrix::trust::TrustLevel;
 use crate::app::matrix::types::get_media_download_url;
 
+pub mod edit_history;
+pub mod encrypted;
 pub mod image;
+pub mod membership_summary;
 pub mod notice;
+pub mod reactions;
+pub mod read_receipts;
+pub mod redacted;
+pub mod reply_quote;
+pub mod state;
 pub mod text;
 pub mod video;
 
@@ -24,21 +38,29 @@ impl EventExt for AnySyncMessageEvent {
 }
 
 pub trait RoomExt {
+    fn get_user_displayname<'a>(&'a self, user_id: &'a UserId) -> &'a str;
     fn get_sender_displayname<'a>(&'a self, event: &'a AnySyncMessageEvent) -> &'a str;
     fn get_sender_avatar<'a>(
         &'a self,
         homeserver_url: &'a Url,
         event: &'a AnySyncMessageEvent,
     ) -> Option<Url>;
+    fn get_user_power_level(&self, user_id: &UserId) -> Int;
+    fn can_redact(&self, sender: &UserId) -> bool;
+    fn get_user_presence(&self, user_id: &UserId) -> Option<PresenceInfo>;
 }
 
 impl RoomExt for Room {
-    fn get_sender_displayname<'a>(&'a self, event: &'a AnySyncMessageEvent) -> &'a str {
+    fn get_user_displayname<'a>(&'a self, user_id: &'a UserId) -> &'a str {
         self.joined_members
-            .get(&event.sender())
-            .or_else(|| self.invited_members.get(&event.sender()))
+            .get(user_id)
+            .or_else(|| self.invited_members.get(user_id))
             .and_then(|member| member.display_name.as_deref())
-            .unwrap_or_else(|| event.sender().as_str())
+            .unwrap_or_else(|| user_id.as_str())
+    }
+
+    fn get_sender_displayname<'a>(&'a self, event: &'a AnySyncMessageEvent) -> &'a str {
+        self.get_user_displayname(event.sender())
     }
 
     fn get_sender_avatar<'a>(
@@ -56,4 +78,88 @@ impl RoomExt for Room {
             member.avatar_url.as_deref()?,
         ))
     }
+
+    fn get_user_power_level(&self, user_id: &UserId) -> Int {
+        self.joined_members
+            .get(user_id)
+            .and_then(|member| member.power_level)
+            .or_else(|| {
+                self.power_levels
+                    .as_ref()
+                    .map(|levels| levels.users_default)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Own messages can always be deleted, others only with the `redact` power level
+    fn can_redact(&self, sender: &UserId) -> bool {
+        if *sender == self.own_user_id {
+            return true;
+        }
+        let required = self
+            .power_levels
+            .as_ref()
+            .map(|levels| levels.redact)
+            .unwrap_or_else(|| Int::from(50));
+        self.get_user_power_level(&self.own_user_id) >= required
+    }
+
+    fn get_user_presence(&self, user_id: &UserId) -> Option<PresenceInfo> {
+        self.joined_members
+            .get(user_id)
+            .and_then(PresenceInfo::from_member)
+    }
+}
+
+/// The plain text body of a message, e.g. for edits or quotes
+pub fn message_body(content: &MessageEventContent) -> &str {
+    match content {
+        MessageEventContent::Text(content) => &content.body,
+        MessageEventContent::Notice(content) => &content.body,
+        MessageEventContent::Emote(content) => &content.body,
+        MessageEventContent::Image(content) => &content.body,
+        MessageEventContent::Video(content) => &content.body,
+        MessageEventContent::Audio(content) => &content.body,
+        MessageEventContent::File(content) => &content.body,
+        MessageEventContent::Location(content) => &content.body,
+        _ => "",
+    }
+}
+
+/// The css classes and material icon of the shield shown next to a sender
+fn trust_shield(trust: Option<TrustLevel>) -> Option<(&'static str, &'static str)> {
+    match trust? {
+        TrustLevel::Verified => Some(("material-icons shield verified", "verified_user")),
+        TrustLevel::UnverifiedDevices => Some(("material-icons shield warning", "gpp_maybe")),
+        TrustLevel::Unverified => Some(("material-icons shield unverified", "shield")),
+    }
+}
+
+pub fn trust_shield_html(trust: Option<TrustLevel>) -> Html {
+    match trust_shield(trust) {
+        Some((classes, icon)) => html! { <span class=classes>{icon}</span> },
+        None => html! {},
+    }
+}
+
+/// Same as `trust_shield_html` for events that get rendered from raw html
+pub fn trust_shield_raw_html(trust: Option<TrustLevel>) -> String {
+    match trust_shield(trust) {
+        Some((classes, icon)) => format!("<span class=\"{}\">{}</span>", classes, icon),
+        None => String::new(),
+    }
+}
+
+/// The dot on an avatar telling whether the user is online, with the status as tooltip
+pub fn presence_dot_html(presence: Option<&PresenceInfo>) -> Html {
+    let presence = match presence {
+        Some(presence) => presence,
+        None => return html! {},
+    };
+    let classes = match presence.presence {
+        PresenceState::Online => "presence-dot online",
+        PresenceState::Unavailable => "presence-dot unavailable",
+        _ => "presence-dot offline",
+    };
+    html! { <span class=classes title=presence.status_msg.as_deref().unwrap_or("")></span> }
 }
@@ -9,7 +9,11 @@ use web_sys::Node;
 use yew::prelude::*;
 use yew::virtual_dom::VNode;
 
-use crate::app::components::events::{EventExt, RoomExt};
+use crate::app::components::events::{
+    reply_quote::ReplyQuote, trust_shield_raw_html, EventExt, RoomExt,
+};
+use crate::app::matrix::relations::{reply_fallback, strip_reply_fallback};
+use crate::app::matrix::trust::TrustLevel;
 
 pub(crate) struct Notice {
     props: Props,
@@ -22,6 +26,14 @@ pub struct Props {
     pub event: AnySyncMessageEvent,
     pub notice_event: NoticeMessageEventContent,
     pub room: Rc<Room>,
+    #[prop_or_default]
+    pub trust: Option<TrustLevel>,
+    /// The event this one replies to, if it is loaded
+    #[prop_or_default]
+    pub reply_parent: Option<AnySyncMessageEvent>,
+    /// Set when the event this one replies to couldn't be fetched
+    #[prop_or_default]
+    pub reply_parent_unavailable: bool,
 }
 
 impl Component for Notice {
@@ -36,19 +48,35 @@ impl Component for Notice {
         false
     }
 
-    fn change(&mut self, _props: Self::Properties) -> bool {
+    fn change(&mut self, props: Self::Properties) -> bool {
         // TODO fix the PartialEq hack
-        /*if format!("{:?}", self.props) != format!("{:?}", props) {
-            self.props = props;
-            true
-        } else {
-            false
-        }*/
+        // Edits and late reply parents arrive as new props
+        self.props = props;
         true
     }
 
     //noinspection RsTypeCheck
     fn view(&self) -> Html {
+        match &self.props.notice_event.relates_to {
+            Some(relates_to) => html! {
+                <>
+                    <ReplyQuote
+                        event_id=relates_to.in_reply_to.event_id.clone()
+                        parent=self.props.reply_parent.clone()
+                        unavailable=self.props.reply_parent_unavailable
+                        fallback=reply_fallback(&self.props.notice_event.body)
+                        room=self.props.room.clone()
+                    />
+                    { self.view_content() }
+                </>
+            },
+            None => self.view_content(),
+        }
+    }
+}
+
+impl Notice {
+    fn view_content(&self) -> Html {
         let new_user = self.props.event.is_new_user(self.props.prev_event.as_ref());
         let sender_displayname = if new_user {
             self.props.room.get_sender_displayname(&self.props.event)
@@ -56,7 +84,12 @@ impl Component for Notice {
             ""
         };
 
-        let mut pure_content = self.props.notice_event.body.clone();
+        // Replies carry a quote of the parent for clients without reply support
+        let mut pure_content = if self.props.notice_event.relates_to.is_some() {
+            strip_reply_fallback(&self.props.notice_event.body).to_string()
+        } else {
+            self.props.notice_event.body.clone()
+        };
         let finder = LinkFinder::new();
         let pure_content_clone = pure_content.clone();
         let links: Vec<_> = finder.links(&pure_content_clone).collect();
@@ -70,8 +103,10 @@ impl Component for Notice {
 
         if new_user {
             let full_html = format!(
-                "<p style=\"opacity: .6;\"><displayname>{}: </displayname>{}</p>",
-                sender_displayname, pure_content
+                "<p style=\"opacity: .6;\"><displayname>{}{}: </displayname>{}</p>",
+                sender_displayname,
+                trust_shield_raw_html(self.props.trust),
+                pure_content
             );
             let js_text_event = {
                 let div = web_sys::window()
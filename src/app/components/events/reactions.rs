@@ -0,0 +1,98 @@
+use std::rc::Rc;
+
+use matrix_sdk::{
+    identifiers::{EventId, UserId},
+    Room,
+};
+use yew::prelude::*;
+
+use crate::app::components::events::RoomExt;
+
+/// All reactions of a message with the same emoji
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReactionGroup {
+    pub key: String,
+    pub senders: Vec<UserId>,
+    /// Our own reaction, clicking the pill redacts it
+    pub own_reaction: Option<EventId>,
+}
+
+/// The reaction pills below a message
+pub(crate) struct Reactions {
+    props: Props,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    Toggle(usize),
+}
+
+#[derive(Clone, Properties, Debug)]
+pub struct Props {
+    pub groups: Vec<ReactionGroup>,
+    pub room: Rc<Room>,
+    #[prop_or_default]
+    pub on_react: Callback<String>,
+    #[prop_or_default]
+    pub on_redact: Callback<EventId>,
+}
+
+impl Component for Reactions {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Reactions { props, link }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Toggle(index) => {
+                let group = &self.props.groups[index];
+                match &group.own_reaction {
+                    Some(event_id) => self.props.on_redact.emit(event_id.clone()),
+                    None => self.props.on_react.emit(group.key.clone()),
+                }
+            }
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        // TODO fix the PartialEq hack
+        self.props = props;
+        true
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let pills: Html = self
+            .props
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| {
+                let class = if group.own_reaction.is_some() {
+                    "reaction own"
+                } else {
+                    "reaction"
+                };
+                let senders: Vec<&str> = group
+                    .senders
+                    .iter()
+                    .map(|sender| self.props.room.get_user_displayname(sender))
+                    .collect();
+                html! {
+                    <a class=class title=senders.join(", ") onclick=self.link.callback(move |_| Msg::Toggle(index))>
+                        <span class="reaction-key">{&group.key}</span>
+                        <span class="reaction-count">{group.senders.len()}</span>
+                    </a>
+                }
+            })
+            .collect();
+
+        html! {
+            <div class="reactions">{ pills }</div>
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::SystemTime;
+
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use crate::utils::time::format_timestamp;
+
+/// How many avatars are stacked before the rest is only counted
+const MAX_AVATARS: usize = 5;
+
+/// A member who has read up to a message
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reader {
+    pub name: String,
+    pub ts: Option<SystemTime>,
+}
+
+/// The avatars of the members whose latest read message this is
+pub(crate) struct ReadReceipts {
+    props: Props,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    pub readers: Vec<Reader>,
+}
+
+impl Component for ReadReceipts {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        ReadReceipts { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> bool {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let avatars: Html = self
+            .props
+            .readers
+            .iter()
+            .take(MAX_AVATARS)
+            .map(
+                |reader| html! { <span class="read-receipt-avatar">{initial(&reader.name)}</span> },
+            )
+            .collect();
+        let more = if self.props.readers.len() > MAX_AVATARS {
+            html! { <span class="read-receipt-more">{format!("+{}", self.props.readers.len() - MAX_AVATARS)}</span> }
+        } else {
+            html! {}
+        };
+        let names: Html = self
+            .props
+            .readers
+            .iter()
+            .map(|reader| {
+                let time = reader.ts.map(format_timestamp).unwrap_or_default();
+                html! {
+                    <li>
+                        <span>{&reader.name}</span>
+                        <span class="uk-text-meta">{time}</span>
+                    </li>
+                }
+            })
+            .collect();
+
+        html! {
+            <div class="read-receipts">
+                { avatars }
+                { more }
+                <ul class="read-receipts-list">{ names }</ul>
+            </div>
+        }
+    }
+}
+
+/// "Alice" becomes "A", user ids lose their "@"
+fn initial(name: &str) -> String {
+    name.trim_start_matches('@')
+        .chars()
+        .next()
+        .map(|c| c.to_uppercase().to_string())
+        .unwrap_or_default()
+}
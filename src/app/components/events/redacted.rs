@@ -0,0 +1,64 @@
+use std::rc::Rc;
+
+use matrix_sdk::{identifiers::UserId, Room};
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::components::events::RoomExt;
+
+/// Placeholder for a message that got deleted
+pub(crate) struct Redacted {
+    props: Props,
+}
+
+#[derive(Clone, Properties, Debug)]
+pub struct Props {
+    pub sender: UserId,
+    pub new_user: bool,
+    pub room: Rc<Room>,
+}
+
+impl Component for Redacted {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Redacted { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> bool {
+        false
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> bool {
+        // TODO fix the PartialEq hack
+        true
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let placeholder = tr!(
+            // Shown instead of a message that was deleted
+            "Message deleted"
+        );
+
+        if self.props.new_user {
+            let sender_displayname = self.props.room.get_user_displayname(&self.props.sender);
+            html! {
+                <p class="redacted-event">
+                    <displayname>{sender_displayname}{": "}</displayname>
+                    <span class="material-icons">{"delete"}</span>
+                    <em>{placeholder}</em>
+                </p>
+            }
+        } else {
+            html! {
+                <p class="redacted-event">
+                    <span class="material-icons">{"delete"}</span>
+                    <em>{placeholder}</em>
+                </p>
+            }
+        }
+    }
+}
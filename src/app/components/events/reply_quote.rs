@@ -0,0 +1,128 @@
+use std::rc::Rc;
+
+use matrix_sdk::{
+    events::{AnyMessageEventContent, AnySyncMessageEvent},
+    identifiers::EventId,
+    Room,
+};
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::components::events::{message_body, RoomExt};
+use crate::app::matrix::relations::strip_reply_fallback;
+
+/// The quote of the parent event above a reply, clicking it jumps to the parent
+pub(crate) struct ReplyQuote {
+    props: Props,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    ScrollToParent,
+}
+
+#[derive(Clone, Properties, Debug)]
+pub struct Props {
+    pub event_id: EventId,
+    /// `None` while the parent is fetched
+    #[prop_or_default]
+    pub parent: Option<AnySyncMessageEvent>,
+    /// Set when the parent couldn't be fetched
+    #[prop_or_default]
+    pub unavailable: bool,
+    /// The quote the reply carries for clients without reply support
+    #[prop_or_default]
+    pub fallback: Option<String>,
+    pub room: Rc<Room>,
+}
+
+impl Component for ReplyQuote {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        ReplyQuote { props, link }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ScrollToParent => {
+                let element = web_sys::window()
+                    .unwrap()
+                    .document()
+                    .unwrap()
+                    .get_element_by_id(&event_element_id(&self.props.event_id));
+                // Parents which are only fetched for the quote aren't in the timeline
+                if let Some(element) = element {
+                    element.scroll_into_view();
+                }
+            }
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        // TODO fix the PartialEq hack
+        self.props = props;
+        true
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let content = match &self.props.parent {
+            Some(parent) => {
+                let body = match parent.content() {
+                    AnyMessageEventContent::RoomMessage(content) => {
+                        strip_reply_fallback(message_body(&content)).to_string()
+                    }
+                    AnyMessageEventContent::RoomEncrypted(_) => tr!(
+                        // Shown instead of a message we don't have the decryption keys for
+                        "Unable to decrypt this message."
+                    ),
+                    _ => String::new(),
+                };
+                html! {
+                    <>
+                        <displayname>{self.props.room.get_sender_displayname(parent)}</displayname>
+                        <p>{body}</p>
+                    </>
+                }
+            }
+            None if self.props.unavailable => match &self.props.fallback {
+                Some(fallback) => html! { <p>{fallback}</p> },
+                None => html! {
+                    <p>
+                        {
+                            tr!(
+                                // Shown in the quote of a reply if the original message can't be loaded
+                                "Original message unavailable"
+                            )
+                        }
+                    </p>
+                },
+            },
+            None => html! {
+                <p>
+                    {
+                        tr!(
+                            // Shown in the quote of a reply while the original message is loaded
+                            "Loading the original message…"
+                        )
+                    }
+                </p>
+            },
+        };
+
+        html! {
+            <blockquote class="reply-quote" onclick=self.link.callback(|_| Msg::ScrollToParent)>
+                { content }
+            </blockquote>
+        }
+    }
+}
+
+/// The html id of the element of an event in the timeline
+pub fn event_element_id(event_id: &EventId) -> String {
+    format!("event-{}", event_id)
+}
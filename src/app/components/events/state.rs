@@ -0,0 +1,285 @@
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use matrix_sdk::{
+    events::{
+        room::member::{MemberEventContent, MembershipState},
+        AnySyncStateEvent, SyncStateEvent,
+    },
+    identifiers::UserId,
+    Room,
+};
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::components::events::RoomExt;
+
+/// A compact line for a change of the room state, e.g. a join or a new topic
+pub(crate) struct StateEvent {
+    props: Props,
+}
+
+#[derive(Clone, Properties, Debug)]
+pub struct Props {
+    pub event: AnySyncStateEvent,
+    pub room: Rc<Room>,
+}
+
+impl Component for StateEvent {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        StateEvent { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> bool {
+        false
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> bool {
+        // TODO fix the PartialEq hack
+        true
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        match describe_state_event(&self.props.room, &self.props.event) {
+            Some(description) => html! {
+                <p class="state-event">
+                    <span class="material-icons">{state_event_icon(&self.props.event)}</span>
+                    {description}
+                </p>
+            },
+            None => html! {},
+        }
+    }
+}
+
+/// Whether the event is a plain join or leave which gets collapsed with its neighbours
+pub fn is_join_or_leave(event: &AnySyncStateEvent) -> bool {
+    match event {
+        AnySyncStateEvent::RoomMember(event) => match event.content.membership {
+            // Profile changes are joins as well but should stay visible
+            MembershipState::Join => !is_profile_change(event),
+            MembershipState::Leave => event.sender.as_str() == event.state_key,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn is_profile_change(event: &SyncStateEvent<MemberEventContent>) -> bool {
+    event
+        .prev_content
+        .as_ref()
+        .map(|prev| prev.membership == MembershipState::Join)
+        .unwrap_or(false)
+}
+
+/// The name of the user a membership event is about
+pub fn member_displayname(room: &Room, event: &SyncStateEvent<MemberEventContent>) -> String {
+    event
+        .content
+        .displayname
+        .clone()
+        .or_else(|| {
+            event
+                .prev_content
+                .as_ref()
+                .and_then(|prev| prev.displayname.clone())
+        })
+        .unwrap_or_else(|| match UserId::try_from(event.state_key.as_str()) {
+            Ok(user_id) => room.get_user_displayname(&user_id).to_string(),
+            Err(_) => event.state_key.clone(),
+        })
+}
+
+fn state_event_icon(event: &AnySyncStateEvent) -> &'static str {
+    match event {
+        AnySyncStateEvent::RoomMember(_) => "person",
+        AnySyncStateEvent::RoomName(_) | AnySyncStateEvent::RoomTopic(_) => "edit",
+        AnySyncStateEvent::RoomAvatar(_) => "image",
+        AnySyncStateEvent::RoomPowerLevels(_) => "admin_panel_settings",
+        AnySyncStateEvent::RoomEncryption(_) => "lock",
+        _ => "info",
+    }
+}
+
+/// A human readable sentence for the state change or `None` if we don't show the event
+pub fn describe_state_event(room: &Room, event: &AnySyncStateEvent) -> Option<String> {
+    let sender = room.get_user_displayname(event.sender());
+
+    let description = match event {
+        AnySyncStateEvent::RoomMember(event) => describe_membership(room, sender, event),
+        AnySyncStateEvent::RoomCreate(_) => tr!(
+            // {0} is the user who created the room
+            "{0} created the room",
+            sender
+        ),
+        AnySyncStateEvent::RoomName(event) => match event.content.name() {
+            Some(name) => tr!(
+                // {0} is the user, {1} the new name of the room
+                "{0} changed the room name to {1}",
+                sender,
+                name
+            ),
+            None => tr!(
+                // {0} is the user who removed the room name
+                "{0} removed the room name",
+                sender
+            ),
+        },
+        AnySyncStateEvent::RoomTopic(event) if event.content.topic.is_empty() => tr!(
+            // {0} is the user who removed the topic
+            "{0} removed the topic",
+            sender
+        ),
+        AnySyncStateEvent::RoomTopic(event) => tr!(
+            // {0} is the user, {1} the new topic of the room
+            "{0} changed the topic to \"{1}\"",
+            sender,
+            event.content.topic
+        ),
+        AnySyncStateEvent::RoomAvatar(_) => tr!(
+            // {0} is the user who changed the avatar of the room
+            "{0} changed the room avatar",
+            sender
+        ),
+        AnySyncStateEvent::RoomEncryption(_) => tr!(
+            // {0} is the user who enabled encryption in the room
+            "{0} enabled end-to-end encryption",
+            sender
+        ),
+        AnySyncStateEvent::RoomPowerLevels(event) => {
+            let prev_users = event.prev_content.as_ref().map(|prev| &prev.users);
+            let changes: Vec<String> = event
+                .content
+                .users
+                .iter()
+                .filter(|(user_id, level)| {
+                    prev_users.and_then(|users| users.get(*user_id)) != Some(level)
+                })
+                .map(|(user_id, level)| {
+                    tr!(
+                        // {0} is a user, {1} the new power level of them
+                        "{0} to {1}",
+                        room.get_user_displayname(user_id),
+                        level
+                    )
+                })
+                .collect();
+            if changes.is_empty() {
+                tr!(
+                    // {0} is the user who changed the power levels of the room
+                    "{0} changed the power levels of the room",
+                    sender
+                )
+            } else {
+                tr!(
+                    // {0} is the user, {1} a list like "Alice to 100, Bob to 50"
+                    "{0} changed the power level of {1}",
+                    sender,
+                    changes.join(", ")
+                )
+            }
+        }
+        _ => return None,
+    };
+    Some(description)
+}
+
+fn describe_membership(
+    room: &Room,
+    sender: &str,
+    event: &SyncStateEvent<MemberEventContent>,
+) -> String {
+    let target = member_displayname(room, event);
+    let prev_membership = event.prev_content.as_ref().map(|prev| &prev.membership);
+    let own_event = event.sender.as_str() == event.state_key;
+
+    match (&event.content.membership, prev_membership) {
+        (MembershipState::Join, Some(MembershipState::Join)) => {
+            let prev_displayname = event
+                .prev_content
+                .as_ref()
+                .and_then(|prev| prev.displayname.clone());
+            match (prev_displayname, &event.content.displayname) {
+                (Some(prev), Some(new)) if prev != *new => tr!(
+                    // {0} is the old, {1} the new display name
+                    "{0} changed their display name to {1}",
+                    prev,
+                    new
+                ),
+                (None, Some(new)) => tr!(
+                    // {0} is the user id, {1} the new display name
+                    "{0} set their display name to {1}",
+                    event.state_key,
+                    new
+                ),
+                (Some(prev), None) => tr!(
+                    // {0} is the display name that got removed
+                    "{0} removed their display name",
+                    prev
+                ),
+                // Nothing else is shown about a member
+                _ => tr!(
+                    // {0} is the user who changed their avatar
+                    "{0} changed their avatar",
+                    target
+                ),
+            }
+        }
+        (MembershipState::Join, _) => tr!(
+            // {0} is the user who joined
+            "{0} joined the room",
+            target
+        ),
+        (MembershipState::Invite, _) => tr!(
+            // {0} is the inviting, {1} the invited user
+            "{0} invited {1}",
+            sender,
+            target
+        ),
+        (MembershipState::Leave, Some(MembershipState::Invite)) if own_event => tr!(
+            // {0} is the user who rejected the invite
+            "{0} rejected the invite",
+            target
+        ),
+        (MembershipState::Leave, Some(MembershipState::Invite)) => tr!(
+            // {0} is the user who withdrew the invite, {1} the invited user
+            "{0} withdrew the invite for {1}",
+            sender,
+            target
+        ),
+        (MembershipState::Leave, Some(MembershipState::Ban)) => tr!(
+            // {0} is the user who unbanned, {1} the unbanned user
+            "{0} unbanned {1}",
+            sender,
+            target
+        ),
+        (MembershipState::Leave, _) if own_event => tr!(
+            // {0} is the user who left
+            "{0} left the room",
+            target
+        ),
+        (MembershipState::Leave, _) => tr!(
+            // {0} is the user who kicked, {1} the kicked user
+            "{0} kicked {1}",
+            sender,
+            target
+        ),
+        (MembershipState::Ban, _) => tr!(
+            // {0} is the user who banned, {1} the banned user
+            "{0} banned {1}",
+            sender,
+            target
+        ),
+        _ => tr!(
+            // {0} is the user whose membership changed
+            "The membership of {0} changed",
+            target
+        ),
+    }
+}
@@ -1,6 +1,12 @@
 use std::rc::Rc;
 
-use crate::app::components::events::{EventExt, RoomExt};
+use crate::app::components::events::{
+    reply_quote::ReplyQuote, trust_shield_raw_html, EventExt, RoomExt,
+};
+use crate::app::matrix::relations::{
+    reply_fallback, strip_reply_fallback, strip_reply_fallback_html,
+};
+use crate::app::matrix::trust::TrustLevel;
 use linkify::LinkFinder;
 use matrix_sdk::{
     events::{room::message::TextMessageEventContent, AnySyncMessageEvent},
@@ -21,6 +27,14 @@ pub struct Props {
     pub event: AnySyncMessageEvent,
     pub text_event: TextMessageEventContent,
     pub room: Rc<Room>,
+    #[prop_or_default]
+    pub trust: Option<TrustLevel>,
+    /// The event this one replies to, if it is loaded
+    #[prop_or_default]
+    pub reply_parent: Option<AnySyncMessageEvent>,
+    /// Set when the event this one replies to couldn't be fetched
+    #[prop_or_default]
+    pub reply_parent_unavailable: bool,
 }
 
 impl Component for Text {
@@ -35,19 +49,37 @@ impl Component for Text {
         false
     }
 
-    fn change(&mut self, _props: Self::Properties) -> bool {
+    fn change(&mut self, props: Self::Properties) -> bool {
         // TODO fix the PartialEq hack
-        /*if format!("{:?}", self.props) != format!("{:?}", props) {
-            self.props = props;
-            true
-        } else {
-            false
-        }*/
+        // Edits and late reply parents arrive as new props
+        self.props = props;
         true
     }
 
     //noinspection RsTypeCheck
     fn view(&self) -> Html {
+        match &self.props.text_event.relates_to {
+            Some(relates_to) => html! {
+                <>
+                    <ReplyQuote
+                        event_id=relates_to.in_reply_to.event_id.clone()
+                        parent=self.props.reply_parent.clone()
+                        unavailable=self.props.reply_parent_unavailable
+                        fallback=reply_fallback(&self.props.text_event.body)
+                        room=self.props.room.clone()
+                    />
+                    { self.view_content() }
+                </>
+            },
+            None => self.view_content(),
+        }
+    }
+}
+
+impl Text {
+    fn view_content(&self) -> Html {
+        // Replies carry a quote of the parent for clients without reply support
+        let is_reply = self.props.text_event.relates_to.is_some();
         let new_user = self.props.event.is_new_user(self.props.prev_event.as_ref());
         let sender_displayname = if new_user {
             self.props.room.get_sender_displayname(&self.props.event)
@@ -55,7 +87,11 @@ impl Component for Text {
             ""
         };
 
-        let mut pure_content = self.props.text_event.body.clone();
+        let mut pure_content = if is_reply {
+            strip_reply_fallback(&self.props.text_event.body).to_string()
+        } else {
+            self.props.text_event.body.clone()
+        };
         let finder = LinkFinder::new();
         let pure_content_clone = pure_content.clone();
         let links: Vec<_> = finder.links(&pure_content_clone).collect();
@@ -71,15 +107,22 @@ impl Component for Text {
         };
 
         if let Some(formatted) = &self.props.text_event.formatted {
+            let formatted_body = if is_reply {
+                strip_reply_fallback_html(&formatted.body)
+            } else {
+                &formatted.body
+            };
             let format_slot;
             let message = if new_user {
                 format_slot = format!(
-                    "<displayname>{}:</displayname> {}",
-                    sender_displayname, formatted.body
+                    "<displayname>{}{}:</displayname> {}",
+                    sender_displayname,
+                    trust_shield_raw_html(self.props.trust),
+                    formatted_body
                 );
-                &format_slot
+                format_slot.as_str()
             } else {
-                &formatted.body
+                formatted_body
             };
             let js_text_event = {
                 let div = web_sys::window()
@@ -88,15 +131,17 @@ impl Component for Text {
                     .unwrap()
                     .create_element("p")
                     .unwrap();
-                div.set_inner_html(message.as_str());
+                div.set_inner_html(message);
                 div
             };
             let node = Node::from(js_text_event);
             VNode::VRef(node)
         } else if new_user {
             let full_html = format!(
-                "<p><displayname>{}: </displayname>{}</p>",
-                sender_displayname, content
+                "<p><displayname>{}{}: </displayname>{}</p>",
+                sender_displayname,
+                trust_shield_raw_html(self.props.trust),
+                content
             );
             let js_text_event = {
                 let div = web_sys::window()
@@ -1,6 +1,7 @@
 use std::rc::Rc;
 
-use crate::app::components::events::{EventExt, RoomExt};
+use crate::app::components::events::{trust_shield_html, EventExt, RoomExt};
+use crate::app::matrix::trust::TrustLevel;
 use matrix_sdk::{
     events::{room::message::VideoMessageEventContent, AnySyncMessageEvent},
     Room,
@@ -19,6 +20,8 @@ pub struct Props {
     pub event: AnySyncMessageEvent,
     pub video_event: VideoMessageEventContent,
     pub room: Rc<Room>,
+    #[prop_or_default]
+    pub trust: Option<TrustLevel>,
 }
 
 impl Component for Video {
@@ -72,7 +75,7 @@ impl Component for Video {
             if new_user {
                 html! {
                     <div>
-                        <p><displayname>{sender_displayname}{": "}</displayname></p>
+                        <p><displayname>{sender_displayname}{trust_shield_html(self.props.trust)}{": "}</displayname></p>
                         <a href={lightbox_href_full}><img src=thumbnail/></a>
                         <div class="lightbox short-animate" id={lightbox_id_full}>
                             <video class="long-animate" controls=true>
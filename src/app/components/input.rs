@@ -1,17 +1,50 @@
+use std::time::Duration;
+
+use matrix_sdk::identifiers::RoomId;
 use yew::prelude::*;
 
+use tr::tr;
+
 #[derive(Debug, PartialEq, Clone, Properties)]
 pub struct InputProps {
+    pub room_id: RoomId,
     pub on_submit: Callback<String>,
+    #[prop_or_default]
+    pub encrypted: bool,
+    /// The body of the message which is currently edited
+    #[prop_or_default]
+    pub editing: Option<String>,
+    /// Up arrow in an empty input edits the last own message
+    #[prop_or_default]
+    pub on_edit_last: Callback<()>,
+    #[prop_or_default]
+    pub on_cancel_edit: Callback<()>,
+    /// Preview of the message which is replied to
+    #[prop_or_default]
+    pub replying_to: Option<String>,
+    #[prop_or_default]
+    pub on_cancel_reply: Callback<()>,
+    /// `Some(timeout)` while composing, `None` once the message is sent or cleared
+    #[prop_or_default]
+    pub on_typing: Callback<(RoomId, Option<Duration>)>,
 }
 
+/// How long the server shows us as typing without hearing from us again
+const TYPING_TIMEOUT: Duration = Duration::from_secs(30);
+/// Typing notices are renewed this often while still composing
+const TYPING_RENEW_MS: f64 = 20_000.0;
+
 pub struct InputState {
     value: Option<String>,
+    /// When the last typing notice was sent, in ms since the epoch
+    typing_since: Option<f64>,
 }
 
 pub struct Input {
     on_input: Callback<InputData>,
     on_submit: Callback<KeyboardEvent>,
+    on_keydown: Callback<KeyboardEvent>,
+    link: ComponentLink<Self>,
     state: InputState,
     props: InputProps,
 }
@@ -20,6 +53,9 @@ pub struct Input {
 pub enum Msg {
     ValueChange(InputData),
     ValueSubmit(KeyboardEvent),
+    KeyDown(KeyboardEvent),
+    CancelEdit,
+    CancelReply,
 }
 
 impl Component for Input {
@@ -27,11 +63,16 @@ impl Component for Input {
     type Properties = InputProps;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let state = InputState { value: None };
+        let state = InputState {
+            value: None,
+            typing_since: None,
+        };
         Self {
             props,
             on_input: link.callback(Msg::ValueChange),
             on_submit: link.callback(Msg::ValueSubmit),
+            on_keydown: link.callback(Msg::KeyDown),
+            link,
             state,
         }
     }
@@ -39,6 +80,11 @@ impl Component for Input {
     fn update(&mut self, msg: Self::Message) -> bool {
         match msg {
             Msg::ValueChange(data) => {
+                if data.value.is_empty() {
+                    self.stop_typing();
+                } else {
+                    self.start_typing();
+                }
                 self.state.value = Some(data.value);
                 true
             }
@@ -48,30 +94,143 @@ impl Component for Input {
                         .on_submit
                         .emit(self.state.value.as_deref().unwrap_or("").to_owned());
                     self.state.value = None;
+                    self.stop_typing();
                     return true;
                 }
                 false
             }
+            Msg::KeyDown(data) => match data.key().as_str() {
+                "ArrowUp" if self.state.value.as_deref().unwrap_or("").is_empty() => {
+                    data.prevent_default();
+                    self.props.on_edit_last.emit(());
+                    false
+                }
+                "Escape" if self.props.editing.is_some() => {
+                    self.link.send_message(Msg::CancelEdit);
+                    false
+                }
+                "Escape" if self.props.replying_to.is_some() => {
+                    self.link.send_message(Msg::CancelReply);
+                    false
+                }
+                _ => false,
+            },
+            Msg::CancelEdit => {
+                self.state.value = None;
+                self.stop_typing();
+                self.props.on_cancel_edit.emit(());
+                true
+            }
+            Msg::CancelReply => {
+                self.props.on_cancel_reply.emit(());
+                false
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        if self.props == props {
+            return false;
         }
+        // The next keystroke starts typing in the new room
+        if props.room_id != self.props.room_id {
+            self.stop_typing();
+        }
+        // Starting an edit puts the old message into the input
+        if props.editing.is_some() && props.editing != self.props.editing {
+            self.state.value = props.editing.clone();
+        }
+        self.props = props;
+        true
     }
 
-    fn change(&mut self, _props: Self::Properties) -> bool {
-        false
+    fn destroy(&mut self) {
+        self.stop_typing();
     }
 
     fn view(&self) -> Html {
+        let (encryption_classes, encryption_icon) = if self.props.encrypted {
+            ("encryption-bg encrypted", "lock")
+        } else {
+            ("encryption-bg", "lock_open")
+        };
+        let context = if let Some(preview) = &self.props.replying_to {
+            html! {
+                <div class="input-context">
+                    <span class="material-icons">{"reply"}</span>
+                    <span class="input-context-preview">
+                        {
+                            tr!(
+                                // Shown above the input while replying, {0} is "Sender: message"
+                                "Replying to {0}",
+                                preview
+                            )
+                        }
+                    </span>
+                    <a class="input-context-close" onclick=self.link.callback(|_| Msg::CancelReply)>
+                        <span class="material-icons">{"close"}</span>
+                    </a>
+                </div>
+            }
+        } else if self.props.editing.is_some() {
+            html! {
+                <div class="input-context">
+                    <span class="material-icons">{"edit"}</span>
+                    {
+                        tr!(
+                            // Shown above the input while a message is edited
+                            "Editing message"
+                        )
+                    }
+                    <a class="input-context-close" onclick=self.link.callback(|_| Msg::CancelEdit)>
+                        <span class="material-icons">{"close"}</span>
+                    </a>
+                </div>
+            }
+        } else {
+            html! {}
+        };
+
         html! {
+            <>
+            { context }
             <div class="message-input">
-                <div class="encryption-bg">
-                    <span class="material-icons">{"lock_open"}</span>
+                <div class=encryption_classes>
+                    <span class="material-icons">{encryption_icon}</span>
                 </div>
                 <textarea autofocus=true
                     placeholder={ "Input Text..." }
                     value=&self.state.value.as_deref().unwrap_or("")
                     oninput=&self.on_input
                     onkeypress=&self.on_submit
+                    onkeydown=&self.on_keydown
                 />
             </div>
+            </>
+        }
+    }
+}
+
+impl Input {
+    fn start_typing(&mut self) {
+        let now = js_sys::Date::now();
+        let renew = match self.state.typing_since {
+            Some(since) => now - since >= TYPING_RENEW_MS,
+            None => true,
+        };
+        if renew {
+            self.state.typing_since = Some(now);
+            self.props
+                .on_typing
+                .emit((self.props.room_id.clone(), Some(TYPING_TIMEOUT)));
+        }
+    }
+
+    fn stop_typing(&mut self) {
+        if self.state.typing_since.take().is_some() {
+            self.props
+                .on_typing
+                .emit((self.props.room_id.clone(), None));
         }
     }
 }
@@ -0,0 +1,175 @@
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    Request, Response,
+};
+
+/// Modal that offers to restore the room keys from the key backup after logging in
+pub struct KeyRestoreModal {
+    link: ComponentLink<Self>,
+    state: State,
+    matrix_agent: AccountBridge,
+}
+
+#[derive(Default)]
+pub struct State {
+    can_restore: bool,
+    loading: bool,
+    recovery_key: String,
+    error: Option<String>,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum Msg {
+    NewMessage(Response),
+    SetRecoveryKey(String),
+    Restore,
+    Dismiss,
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+}
+
+impl Component for KeyRestoreModal {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let matrix_callback = link.callback(Msg::NewMessage);
+        let mut matrix_agent = AccountBridge::new(props.account, matrix_callback);
+        if cfg!(feature = "encryption") {
+            matrix_agent.send(Request::GetKeyBackupStatus);
+        }
+        KeyRestoreModal {
+            link,
+            state: Default::default(),
+            matrix_agent,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::NewMessage(Response::KeyBackupStatus(status)) => {
+                self.state.can_restore = status.can_restore;
+                true
+            }
+            Msg::NewMessage(Response::KeyBackupRestored(_)) => {
+                self.state.loading = false;
+                self.state.recovery_key = "".to_string();
+                true
+            }
+            Msg::NewMessage(Response::Error(error)) => {
+                if self.state.loading {
+                    self.state.loading = false;
+                    self.state.error = Some(error.to_string());
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::NewMessage(_) => false,
+            Msg::SetRecoveryKey(recovery_key) => {
+                self.state.recovery_key = recovery_key;
+                true
+            }
+            Msg::Restore => {
+                if self.state.recovery_key.trim().is_empty() {
+                    return false;
+                }
+                self.state.loading = true;
+                self.state.error = None;
+                self.matrix_agent
+                    .send(Request::RestoreKeyBackup(self.state.recovery_key.clone()));
+                true
+            }
+            Msg::Dismiss => {
+                self.matrix_agent.send(Request::DismissKeyBackupRestore);
+                false
+            }
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> bool {
+        false
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        if !self.state.can_restore {
+            return html! {};
+        }
+
+        let error = match &self.state.error {
+            Some(error) => html! {
+                <div class="uk-alert-danger" uk-alert="">
+                    <p>{error}</p>
+                </div>
+            },
+            None => html! {},
+        };
+
+        html! {
+            <div class="uk-modal uk-open" style="display: block;">
+                <div class="uk-modal-dialog uk-modal-body">
+                    <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::Restore })>
+                        <h2 class="uk-modal-title">
+                            {
+                                tr!(
+                                    // Title of the dialog offering to restore keys after logging in
+                                    "Restore your keys"
+                                )
+                            }
+                        </h2>
+                        <p>
+                            {
+                                tr!(
+                                    // Explanation in the dialog offering to restore keys after logging in
+                                    "Your keys are backed up on your homeserver. Enter your recovery key to read your encrypted messages in this session."
+                                )
+                            }
+                        </p>
+                        { error }
+                        <input
+                            class="uk-input"
+                            type="password"
+                            required=true
+                            disabled=self.state.loading
+                            placeholder=
+                            {
+                                tr!(
+                                    // Placeholder of the recovery key field in the key restore dialog
+                                    "Recovery Key"
+                                )
+                            }
+                            value=&self.state.recovery_key
+                            oninput=self.link.callback(|e: InputData| Msg::SetRecoveryKey(e.value))
+                        />
+                        <p class="uk-text-right">
+                            <button type="button" class="uk-button uk-button-default" disabled=self.state.loading onclick=self.link.callback(|_| Msg::Dismiss)>
+                                {
+                                    tr!(
+                                        // Button to skip restoring keys after logging in
+                                        "Not now"
+                                    )
+                                }
+                            </button>
+                            <button class="uk-button uk-button-primary" disabled=self.state.loading>
+                                {
+                                    tr!(
+                                        // Button to restore keys with the recovery key
+                                        "Restore"
+                                    )
+                                }
+                            </button>
+                        </p>
+                    </form>
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::{collections::HashMap, rc::Rc};
+
+use matrix_sdk::{identifiers::UserId, Room};
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+use crate::app::components::events::{presence_dot_html, RoomExt};
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    presence::PresenceInfo,
+    verification::DeviceInfo,
+    Request, Response,
+};
+
+/// Lists the joined members of a room and lets the user verify their devices
+pub struct MemberList {
+    link: ComponentLink<Self>,
+    state: State,
+    matrix_agent: AccountBridge,
+    props: Props,
+}
+
+#[derive(Default)]
+pub struct State {
+    selected_user: Option<UserId>,
+    devices: HashMap<UserId, Vec<DeviceInfo>>,
+    /// Presence updates since the room was loaded
+    presence: HashMap<UserId, PresenceInfo>,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum Msg {
+    NewMessage(Response),
+    SelectUser(UserId),
+    Back,
+    Verify(UserId, String),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+    pub room: Rc<Room>,
+}
+
+impl Component for MemberList {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let matrix_callback = link.callback(Msg::NewMessage);
+        let matrix_agent = AccountBridge::new(props.account.clone(), matrix_callback);
+        MemberList {
+            link,
+            state: Default::default(),
+            matrix_agent,
+            props,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::NewMessage(Response::UserDevices((user_id, devices))) => {
+                self.state.devices.insert(user_id, devices);
+                true
+            }
+            Msg::NewMessage(Response::Presence(presence)) => {
+                let is_member = self
+                    .props
+                    .room
+                    .joined_members
+                    .contains_key(&presence.user_id);
+                self.state
+                    .presence
+                    .insert(presence.user_id.clone(), presence);
+                is_member
+            }
+            Msg::NewMessage(_) => false,
+            Msg::SelectUser(user_id) => {
+                if cfg!(feature = "encryption") {
+                    self.matrix_agent
+                        .send(Request::GetUserDevices(user_id.clone()));
+                }
+                self.state.selected_user = Some(user_id);
+                true
+            }
+            Msg::Back => {
+                self.state.selected_user = None;
+                true
+            }
+            Msg::Verify(user_id, device_id) => {
+                self.matrix_agent
+                    .send(Request::StartVerification((user_id, device_id)));
+                false
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        match &self.state.selected_user {
+            Some(user_id) => self.view_profile(user_id),
+            None => {
+                let members: Html = self
+                    .props
+                    .room
+                    .joined_members
+                    .values()
+                    .map(|member| {
+                        let user_id = member.user_id.clone();
+                        let display_name = member
+                            .display_name
+                            .clone()
+                            .unwrap_or_else(|| member.user_id.to_string());
+                        let presence = self.presence(&member.user_id);
+                        html! {
+                            <li>
+                                <a onclick=self.link.callback(move |_| Msg::SelectUser(user_id.clone()))>
+                                    { presence_dot_html(presence.as_ref()) }
+                                    {display_name}
+                                </a>
+                            </li>
+                        }
+                    })
+                    .collect();
+
+                html! {
+                    <div class="member-list scrollable">
+                        <h4>
+                            {
+                                tr!(
+                                    // Title of the member list of a room
+                                    "Members"
+                                )
+                            }
+                        </h4>
+                        <ul class="uk-list">{members}</ul>
+                    </div>
+                }
+            }
+        }
+    }
+}
+
+impl MemberList {
+    /// The latest presence, falling back to the one stored with the room
+    fn presence(&self, user_id: &UserId) -> Option<PresenceInfo> {
+        self.state
+            .presence
+            .get(user_id)
+            .cloned()
+            .or_else(|| self.props.room.get_user_presence(user_id))
+    }
+
+    //noinspection RsTypeCheck
+    fn view_profile(&self, user_id: &UserId) -> Html {
+        let display_name = self
+            .props
+            .room
+            .joined_members
+            .get(user_id)
+            .and_then(|member| member.display_name.clone())
+            .unwrap_or_else(|| user_id.to_string());
+
+        let presence = self.presence(user_id);
+        let status_msg = match presence.as_ref().and_then(|p| p.status_msg.as_deref()) {
+            Some(status_msg) => html! { <p class="status-msg">{status_msg}</p> },
+            None => html! {},
+        };
+
+        let device_list: Html = match self.state.devices.get(user_id) {
+            Some(devices) => devices
+                .iter()
+                .map(|device| {
+                    let verify = if device.verified {
+                        html! { <span class="material-icons verified">{"verified_user"}</span> }
+                    } else {
+                        let user_id = user_id.clone();
+                        let device_id = device.device_id.clone();
+                        html! {
+                            <button
+                                class="uk-button uk-button-small uk-button-primary"
+                                onclick=self.link.callback(move |_| Msg::Verify(user_id.clone(), device_id.clone()))
+                            >
+                                {
+                                    tr!(
+                                        // Button to start verifying a device of another user
+                                        "Verify"
+                                    )
+                                }
+                            </button>
+                        }
+                    };
+                    html! {
+                        <li class="device">
+                            <span>
+                                { device.display_name.as_deref().unwrap_or(&device.device_id) }
+                                <small>{" ("}{&device.device_id}{")"}</small>
+                            </span>
+                            { verify }
+                        </li>
+                    }
+                })
+                .collect(),
+            None => html! { <li>{ tr!("Loading devices…") }</li> },
+        };
+        // The devices only matter for verification
+        let devices = if cfg!(feature = "encryption") {
+            html! {
+                <>
+                    <h5>
+                        {
+                            tr!(
+                                // Title of the device list in a member profile
+                                "Sessions"
+                            )
+                        }
+                    </h5>
+                    <ul class="uk-list">{device_list}</ul>
+                </>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <div class="member-list scrollable">
+                <a onclick=self.link.callback(|_| Msg::Back)>
+                    <span class="material-icons">{"arrow_back"}</span>
+                </a>
+                <h4>{ presence_dot_html(presence.as_ref()) }{display_name}</h4>
+                <p class="user-id">{user_id.to_string()}</p>
+                { status_msg }
+                { devices }
+            </div>
+        }
+    }
+}
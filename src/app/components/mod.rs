@@ -1,5 +1,13 @@
+pub mod create_room;
 pub mod event_list;
+pub mod event_menu;
 pub mod events;
 pub mod input;
+pub mod key_restore;
+pub mod member_list;
 pub mod raw_html;
+pub mod reaction_picker;
 pub mod room_list;
+pub mod thread_list;
+pub mod thread_panel;
+pub mod verification;
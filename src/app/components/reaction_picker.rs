@@ -0,0 +1,119 @@
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+/// The reactions offered without typing a shortcode, same as on GitHub
+const QUICK_REACTIONS: [&str; 8] = ["👍", "👎", "😄", "🎉", "😕", "❤️", "🚀", "👀"];
+
+/// Picks an emoji either from a few common ones or by its `:shortcode:`
+pub(crate) struct ReactionPicker {
+    props: Props,
+    link: ComponentLink<Self>,
+    shortcode: String,
+    invalid: bool,
+}
+
+pub enum Msg {
+    Pick(String),
+    SetShortcode(String),
+    SubmitShortcode,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    #[prop_or_default]
+    pub on_pick: Callback<String>,
+}
+
+impl Component for ReactionPicker {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        ReactionPicker {
+            props,
+            link,
+            shortcode: String::new(),
+            invalid: false,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Pick(emoji) => {
+                self.props.on_pick.emit(emoji);
+                false
+            }
+            Msg::SetShortcode(shortcode) => {
+                self.shortcode = shortcode;
+                self.invalid = false;
+                true
+            }
+            Msg::SubmitShortcode => {
+                match emoji_for_shortcode(&self.shortcode) {
+                    Some(emoji) => {
+                        self.shortcode.clear();
+                        self.props.on_pick.emit(emoji);
+                    }
+                    None => self.invalid = true,
+                }
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let quick_reactions: Html = QUICK_REACTIONS
+            .iter()
+            .map(|emoji| {
+                html! {
+                    <a class="reaction-picker-emoji" onclick=self.link.callback(move |_| Msg::Pick(emoji.to_string()))>
+                        {emoji}
+                    </a>
+                }
+            })
+            .collect();
+        let input_class = if self.invalid {
+            "uk-input uk-form-small uk-form-danger"
+        } else {
+            "uk-input uk-form-small"
+        };
+
+        html! {
+            <div class="reaction-picker">
+                <div>{ quick_reactions }</div>
+                <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::SubmitShortcode })>
+                    <input
+                        class=input_class
+                        placeholder={
+                            tr!(
+                                // Placeholder of the field to react with an emoji shortcode
+                                ":shortcode:"
+                            )
+                        }
+                        value=&self.shortcode
+                        oninput=self.link.callback(|e: InputData| Msg::SetShortcode(e.value))
+                    />
+                </form>
+            </div>
+        }
+    }
+}
+
+/// Uses the same shortcodes as messages, e.g. `:tada:` or just `tada`
+fn emoji_for_shortcode(shortcode: &str) -> Option<String> {
+    let shortcode = format!(":{}:", shortcode.trim().trim_matches(':'));
+    let replacer = gh_emoji::Replacer::new();
+    let emoji = replacer.replace_all(&shortcode);
+    if emoji == shortcode {
+        None
+    } else {
+        Some(emoji.to_string())
+    }
+}
@@ -0,0 +1,112 @@
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+use crate::app::matrix::account::{AccountId, AccountSummary};
+
+/// Shows the logged in accounts and lets the user switch between them
+pub(crate) struct AccountSwitcher {
+    props: Props,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    SwitchAccount(AccountId),
+    AddAccount,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    pub active: AccountId,
+    pub accounts: Vec<AccountSummary>,
+
+    #[prop_or_default]
+    pub switch_account: Callback<AccountId>,
+    #[prop_or_default]
+    pub add_account: Callback<()>,
+}
+
+impl Component for AccountSwitcher {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        AccountSwitcher { props, link }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SwitchAccount(account) => {
+                if account != self.props.active {
+                    self.props.switch_account.emit(account);
+                }
+            }
+            Msg::AddAccount => self.props.add_account.emit(()),
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let accounts: Html = self
+            .props
+            .accounts
+            .iter()
+            .map(|account| self.view_account(account))
+            .collect();
+
+        html! {
+            <div class="account-switcher">
+                { accounts }
+                <a
+                    class="account add-account"
+                    title={
+                        tr!(
+                            // Tooltip of the button to log in another account
+                            "Add account"
+                        )
+                    }
+                    onclick=self.link.callback(|_| Msg::AddAccount)
+                >
+                    <span class="material-icons">{"person_add"}</span>
+                </a>
+            </div>
+        }
+    }
+}
+
+impl AccountSwitcher {
+    fn view_account(&self, account: &AccountSummary) -> Html {
+        let id = account.id.clone();
+        let active = account.id == self.props.active;
+        let class = if active { "account active" } else { "account" };
+
+        // The active account is read right now, so only the others get a badge
+        let badge = if !active && account.unread > 0 {
+            html! { <span class="uk-badge">{account.unread}</span> }
+        } else {
+            html! {}
+        };
+
+        // "@alice:example.org" becomes "A"
+        let initial = account
+            .user_id
+            .trim_start_matches('@')
+            .chars()
+            .next()
+            .map(|c| c.to_uppercase().to_string())
+            .unwrap_or_default();
+
+        html! {
+            <a class=class title=&account.user_id onclick=self.link.callback(move |_| Msg::SwitchAccount(id.clone()))>
+                <span class="account-initial">{initial}</span>
+                { badge }
+            </a>
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use matrix_sdk::identifiers::RoomId;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+use crate::app::matrix::invites::Invite;
+
+/// A pending invite with a preview of the room and buttons to answer it
+pub(crate) struct InviteItem {
+    props: Props,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    Accept,
+    Decline,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    pub invite: Invite,
+    /// Buttons are disabled while the answer is sent
+    #[prop_or_default]
+    pub answering: bool,
+
+    #[prop_or_default]
+    pub on_accept: Callback<RoomId>,
+    #[prop_or_default]
+    pub on_decline: Callback<RoomId>,
+}
+
+impl Component for InviteItem {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        InviteItem { props, link }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        let room_id = self.props.invite.room_id.clone();
+        match msg {
+            Msg::Accept => self.props.on_accept.emit(room_id),
+            Msg::Decline => self.props.on_decline.emit(room_id),
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let invite = &self.props.invite;
+        let inviter = invite
+            .inviter_name
+            .clone()
+            .or_else(|| invite.inviter.as_ref().map(ToString::to_string));
+        let invited_by = match inviter {
+            Some(inviter) if invite.is_direct => tr!(
+                // Shown under an invite to a direct chat, {0} is the inviter
+                "{0} wants to chat",
+                inviter
+            ),
+            Some(inviter) => tr!(
+                // Shown under an invite to a room, {0} is the inviter
+                "Invited by {0}",
+                inviter
+            ),
+            None => String::new(),
+        };
+        let topic = match &invite.topic {
+            Some(topic) => html! { <p class="invite-topic">{topic}</p> },
+            None => html! {},
+        };
+        let alias = match &invite.canonical_alias {
+            Some(alias) if invite.room_name.is_some() => {
+                html! { <p class="invite-alias">{alias.to_string()}</p> }
+            }
+            _ => html! {},
+        };
+
+        html! {
+            <div class="room-list-item invite">
+                <div class="content">
+                    <div>
+                        <h5 class="name">{invite.display_name()}</h5>
+                        { alias }
+                        <p class="latest-msg">{invited_by}</p>
+                        { topic }
+                        <div class="invite-buttons">
+                            <button
+                                class="uk-button uk-button-primary uk-button-small"
+                                disabled=self.props.answering
+                                onclick=self.link.callback(|_| Msg::Accept)
+                            >
+                                {
+                                    tr!(
+                                        // Button to accept an invite and join the room
+                                        "Accept"
+                                    )
+                                }
+                            </button>
+                            <button
+                                class="uk-button uk-button-default uk-button-small"
+                                disabled=self.props.answering
+                                onclick=self.link.callback(|_| Msg::Decline)
+                            >
+                                {
+                                    tr!(
+                                        // Button to reject an invite
+                                        "Decline"
+                                    )
+                                }
+                            </button>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
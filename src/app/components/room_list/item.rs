@@ -11,6 +11,13 @@ use matrix_sdk::{
 use yew::prelude::*;
 use yewtil::NeqAssign;
 
+use tr::tr;
+
+use crate::app::components::events::presence_dot_html;
+use crate::app::components::room_list::room_menu::RoomMenu;
+use crate::app::matrix::presence::PresenceInfo;
+use crate::app::matrix::receipts::UnreadCounts;
+
 pub(crate) struct RoomItem {
     props: Props,
     link: ComponentLink<Self>,
@@ -18,14 +25,29 @@ pub(crate) struct RoomItem {
 
 pub enum Msg {
     ChangeRoom(Rc<Room>),
+    ToggleFavourite,
+    Leave(bool),
 }
 
 #[derive(Clone, Properties, Debug, PartialEq)]
 pub struct Props {
     pub room: Rc<Room>,
+    /// The presence of the other user if the room is a direct chat
+    #[prop_or_default]
+    pub presence: Option<PresenceInfo>,
+    #[prop_or_default]
+    pub unread: UnreadCounts,
+
+    #[prop_or_default]
+    pub favourite: bool,
 
     #[prop_or_default]
     pub change_room_callback: Callback<RoomId>,
+    #[prop_or_default]
+    pub toggle_favourite: Callback<RoomId>,
+    /// Called with `true` if the room should be forgotten as well
+    #[prop_or_default]
+    pub leave_room: Callback<(RoomId, bool)>,
 }
 
 impl Component for RoomItem {
@@ -41,6 +63,16 @@ impl Component for RoomItem {
             Msg::ChangeRoom(room) => {
                 self.props.change_room_callback.emit(room.room_id.clone());
             }
+            Msg::ToggleFavourite => {
+                self.props
+                    .toggle_favourite
+                    .emit(self.props.room.room_id.clone());
+            }
+            Msg::Leave(forget) => {
+                self.props
+                    .leave_room
+                    .emit((self.props.room.room_id.clone(), forget));
+            }
         }
         false
     }
@@ -53,32 +85,84 @@ impl Component for RoomItem {
     fn view(&self) -> Html {
         let room = self.props.room.clone();
 
-        // TODO placeholder for encrypted rooms
         let last_message = match room.messages.iter().last() {
             Some(AnyPossiblyRedactedSyncMessageEvent::Regular(
                 AnySyncMessageEvent::RoomMessage(SyncMessageEvent {
                     content: MessageEventContent::Text(text_event),
                     ..
                 }),
-            )) => &text_event.body,
-            _ => "",
+            )) => text_event.body.clone(),
+            Some(AnyPossiblyRedactedSyncMessageEvent::Regular(
+                AnySyncMessageEvent::RoomEncrypted(_),
+            )) => tr!(
+                // Placeholder for the latest message of a room if it is encrypted
+                "Encrypted message"
+            ),
+            _ => "".to_string(),
+        };
+
+        let encryption_icon = if room.is_encrypted() {
+            html! { <span class="material-icons encryption-icon">{"lock"}</span> }
+        } else {
+            html! {}
+        };
+
+        let unread = self.props.unread;
+        let badge = if unread.highlights > 0 {
+            html! { <span class="uk-badge unread-badge highlight">{unread.notifications.max(unread.highlights)}</span> }
+        } else if unread.notifications > 0 {
+            html! { <span class="uk-badge unread-badge">{unread.notifications}</span> }
+        } else {
+            html! {}
+        };
+        let classes = if unread.notifications > 0 || unread.highlights > 0 {
+            "room-list-item unread"
+        } else {
+            "room-list-item"
+        };
+
+        let (favourite_classes, favourite_title) = if self.props.favourite {
+            (
+                "material-icons favourite-toggle active",
+                tr!(
+                    // Tooltip of the star of a favourite room
+                    "Remove from favourites"
+                ),
+            )
+        } else {
+            (
+                "material-icons favourite-toggle",
+                tr!(
+                    // Tooltip of the star of a room which isn't a favourite
+                    "Add to favourites"
+                ),
+            )
         };
 
         let room = room.clone();
         let display_name = room.display_name();
+        let room_name = display_name.clone();
 
         html! {
-            <div class="room-list-item">
+            <div class=classes>
                 <a onclick=self.link.callback(move |e: MouseEvent| Msg::ChangeRoom(room.clone()))>
                     <div class="content">
                         // TODO remove placeholder
-                        <img class="avatar" src="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAACgAAAAoCAYAAACM/rhtAAAARUlEQVRYhe3OMQ0AIADAMBKUowmBoIKMo0f/jrnX+dmoA4KCdUBQsA4ICtYBQcE6IChYBwQF64CgYB0QFKwDgoJ1QPC1C8gY0kSgNLTWAAAAAElFTkSuQmCC"/>
+                        <div class="avatar-wrapper">
+                            <img class="avatar" src="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAACgAAAAoCAYAAACM/rhtAAAARUlEQVRYhe3OMQ0AIADAMBKUowmBoIKMo0f/jrnX+dmoA4KCdUBQsA4ICtYBQcE6IChYBwQF64CgYB0QFKwDgoJ1QPC1C8gY0kSgNLTWAAAAAElFTkSuQmCC"/>
+                            { presence_dot_html(self.props.presence.as_ref()) }
+                        </div>
                         <div>
-                            <h5 class="name">{display_name}</h5>
+                            <h5 class="name">{encryption_icon}{display_name}</h5>
                             <p class="latest-msg">{last_message}</p>
                         </div>
+                        { badge }
                     </div>
                 </a>
+                <a class=favourite_classes title=favourite_title onclick=self.link.callback(|_| Msg::ToggleFavourite)>
+                    {"star"}
+                </a>
+                <RoomMenu room_name=room_name on_leave=self.link.callback(Msg::Leave) />
             </div>
         }
     }
@@ -1,28 +1,63 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
 
 use log::*;
-use matrix_sdk::{identifiers::RoomId, Room};
+use matrix_sdk::{
+    events::AnyPossiblyRedactedSyncMessageEvent,
+    identifiers::{RoomId, UserId},
+    presence::PresenceState,
+    Room,
+};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::JsCast;
 use web_sys::HtmlElement;
 use yew::prelude::*;
+use yew::services::interval::{IntervalService, IntervalTask};
 use yew::utils::document;
-use yew::{Bridge, Bridged, Component, ComponentLink, Html};
+use yew::{Component, ComponentLink, Html};
+use yew_router::components::RouterAnchor;
 use yewtil::NeqAssign;
 
 use tr::tr;
 
+use crate::app::components::create_room::CreateRoomModal;
+use crate::app::components::events::RoomExt;
 use crate::app::components::raw_html::RawHTML;
-use crate::app::components::room_list::item::RoomItem;
-use crate::app::matrix::{MatrixAgent, Request, Response};
+use crate::app::components::room_list::{
+    account_switcher::AccountSwitcher, invite::InviteItem, item::RoomItem, status::StatusMessage,
+};
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId, AccountSummary},
+    invites::Invite,
+    presence::PresenceInfo,
+    receipts::UnreadCounts,
+    tags::{FAVOURITE, LOW_PRIORITY},
+    Request, Response,
+};
+use crate::app::AppRoute;
+use crate::utils::activity::ActivityTracker;
+use crate::utils::ruma::AnySyncRoomEventExt;
 
+mod account_switcher;
+mod invite;
 mod item;
+mod room_menu;
+mod status;
+
+/// Without any input for this long we show up as away
+const IDLE_MS: f64 = 5.0 * 60.0 * 1000.0;
 
 pub struct RoomList {
     link: ComponentLink<Self>,
     state: State,
-    matrix_agent: Box<dyn Bridge<MatrixAgent>>,
+    matrix_agent: AccountBridge,
     props: Props,
+    activity: ActivityTracker,
+    _idle_check: IntervalTask,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -31,6 +66,54 @@ pub enum Msg {
     ChangeRoom(RoomId),
     SetFilter(String),
     ToggleTheme,
+    UpdatePresence,
+    GoOffline,
+    SetStatus(Option<String>),
+    ToggleSection(RoomSection),
+    ToggleFavourite(RoomId),
+    LeaveRoom((RoomId, bool)),
+    ShowCreateRoom(bool),
+    AcceptInvite(RoomId),
+    DeclineInvite(RoomId),
+}
+
+/// The groups the room list is split into, in the order they are shown
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RoomSection {
+    Favourites,
+    DirectMessages,
+    Rooms,
+    LowPriority,
+}
+
+impl RoomSection {
+    const ALL: [RoomSection; 4] = [
+        RoomSection::Favourites,
+        RoomSection::DirectMessages,
+        RoomSection::Rooms,
+        RoomSection::LowPriority,
+    ];
+
+    fn title(self) -> String {
+        match self {
+            RoomSection::Favourites => tr!(
+                // Header of the room list section with the favourite rooms
+                "Favourites"
+            ),
+            RoomSection::DirectMessages => tr!(
+                // Header of the room list section with the direct chats
+                "Direct Messages"
+            ),
+            RoomSection::Rooms => tr!(
+                // Header of the room list section with all other rooms
+                "Rooms"
+            ),
+            RoomSection::LowPriority => tr!(
+                // Header of the room list section with the rooms tagged as low priority
+                "Low Priority"
+            ),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -40,12 +123,40 @@ pub struct State {
     loading: bool,
     search_query: Option<String>,
     dark_theme: bool,
+    /// The latest presence of everyone we share a room with, including ourselves
+    presence: HashMap<UserId, PresenceInfo>,
+    /// The presence we last sent to the server
+    own_presence: Option<PresenceState>,
+    status_msg: Option<String>,
+    /// Counts from syncs after the room was loaded
+    unread: HashMap<RoomId, UnreadCounts>,
+    /// The `m.tag` tags of each room
+    tags: HashMap<RoomId, Vec<String>>,
+    /// When the newest event from a sync after the room was loaded was sent
+    latest_activity: HashMap<RoomId, SystemTime>,
+    collapsed: HashSet<RoomSection>,
+    invites: HashMap<RoomId, Invite>,
+    /// Invites we sent an answer for and wait for the server
+    answering_invites: HashSet<RoomId>,
+    /// A room we joined which is opened as soon as the sync delivers it
+    open_when_joined: Option<RoomId>,
+    show_create_room: bool,
 }
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct Props {
+    pub account: AccountId,
+    #[prop_or_default]
+    pub accounts: Vec<AccountSummary>,
     #[prop_or_default]
     pub change_room_callback: Callback<Rc<Room>>,
+    /// Called when the open room is left
+    #[prop_or_default]
+    pub close_room_callback: Callback<()>,
+    #[prop_or_default]
+    pub switch_account: Callback<AccountId>,
+    #[prop_or_default]
+    pub add_account: Callback<()>,
 }
 
 impl Component for RoomList {
@@ -54,20 +165,45 @@ impl Component for RoomList {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let matrix_callback = link.callback(Msg::NewMessage);
-        let matrix_agent = MatrixAgent::bridge(matrix_callback);
+        let mut matrix_agent = AccountBridge::new(props.account.clone(), matrix_callback);
+        // The sync might have been running for a while already
+        matrix_agent.send(Request::GetJoinedRooms);
         let state = State {
             rooms: Default::default(),
             current_room: None,
             loading: true,
             search_query: None,
             dark_theme: false,
+            presence: Default::default(),
+            own_presence: None,
+            status_msg: None,
+            unread: Default::default(),
+            tags: Default::default(),
+            latest_activity: Default::default(),
+            collapsed: Default::default(),
+            invites: Default::default(),
+            answering_invites: Default::default(),
+            open_when_joined: None,
+            show_create_room: false,
         };
 
+        let activity = ActivityTracker::new(
+            link.callback(|_| Msg::UpdatePresence),
+            link.callback(|_| Msg::GoOffline),
+        );
+        let idle_check = IntervalService::spawn(
+            Duration::from_secs(60),
+            link.callback(|_| Msg::UpdatePresence),
+        );
+        link.send_message(Msg::UpdatePresence);
+
         RoomList {
             props,
             link,
             matrix_agent,
             state,
+            activity,
+            _idle_check: idle_check,
         }
     }
 
@@ -84,12 +220,87 @@ impl Component for RoomList {
                 }
                 Response::JoinedRoom((room_id, room)) => {
                     info!("Got JoinedRoom");
+                    self.state.invites.remove(&room_id);
+                    if self.state.open_when_joined.as_ref() == Some(&room_id) {
+                        self.state.open_when_joined = None;
+                        self.link.send_message(Msg::ChangeRoom(room_id.clone()));
+                    }
                     self.state.rooms.insert(room_id, Rc::new(room));
                     if self.state.loading {
                         self.state.loading = false;
                     }
                     true
                 }
+                Response::Invite(invite) => {
+                    self.state.invites.insert(invite.room_id.clone(), invite);
+                    true
+                }
+                Response::RoomCreated(room_id) => {
+                    self.state.show_create_room = false;
+                    self.open_when_joined(room_id);
+                    true
+                }
+                Response::RoomJoined(room_id) => {
+                    self.state.invites.remove(&room_id);
+                    self.state.answering_invites.remove(&room_id);
+                    self.open_when_joined(room_id);
+                    true
+                }
+                Response::LeftRoom(room_id) => {
+                    self.state.rooms.remove(&room_id);
+                    self.state.invites.remove(&room_id);
+                    self.state.answering_invites.remove(&room_id);
+                    self.state.unread.remove(&room_id);
+                    self.state.tags.remove(&room_id);
+                    self.state.latest_activity.remove(&room_id);
+                    if self.state.current_room.as_ref() == Some(&room_id) {
+                        self.state.current_room = None;
+                        self.props.close_room_callback.emit(());
+                    }
+                    true
+                }
+                Response::InviteRejected(room_id) => {
+                    self.state.invites.remove(&room_id);
+                    self.state.answering_invites.remove(&room_id);
+                    true
+                }
+                // Lets the user try answering again
+                Response::InviteFailed(room_id) => self.state.answering_invites.remove(&room_id),
+                Response::Sync((room_id, raw_event)) => {
+                    let ts = match raw_event.deserialize() {
+                        Ok(event) => *event.origin_server_ts(),
+                        Err(_) => return false,
+                    };
+                    let position = self.room_position(&room_id);
+                    let latest = self
+                        .state
+                        .latest_activity
+                        .entry(room_id.clone())
+                        .or_insert(ts);
+                    if ts > *latest {
+                        *latest = ts;
+                    }
+                    // Most events don't move the room, no need to render the whole list for them
+                    self.room_position(&room_id) != position
+                }
+                Response::RoomTags((room_id, tags)) => {
+                    self.state.tags.insert(room_id, tags);
+                    true
+                }
+                Response::UnreadCounts((room_id, counts)) => {
+                    self.state.unread.insert(room_id, counts);
+                    true
+                }
+                Response::Presence(presence) => {
+                    // Picks up the status message set by other sessions of the account
+                    if Some(presence.user_id.as_str()) == self.own_user_id() {
+                        self.state.status_msg = presence.status_msg.clone();
+                    }
+                    self.state
+                        .presence
+                        .insert(presence.user_id.clone(), presence);
+                    true
+                }
                 _ => false,
             },
             Msg::ChangeRoom(room_id) => {
@@ -101,6 +312,10 @@ impl Component for RoomList {
 
                 let room = self.state.rooms[&room_id].clone();
                 self.props.change_room_callback.emit(room);
+                // Opening the room reads it, the read receipt follows once it is rendered
+                self.state
+                    .unread
+                    .insert(room_id.clone(), UnreadCounts::default());
                 self.state.current_room = Some(room_id);
                 true
             }
@@ -125,6 +340,66 @@ impl Component for RoomList {
                     .unwrap();
                 true
             }
+            Msg::UpdatePresence => {
+                let presence = if self.activity.is_hidden() || self.activity.idle_ms() >= IDLE_MS {
+                    PresenceState::Unavailable
+                } else {
+                    PresenceState::Online
+                };
+                if self.state.own_presence != Some(presence) {
+                    self.set_presence(presence);
+                }
+                false
+            }
+            Msg::GoOffline => {
+                self.set_presence(PresenceState::Offline);
+                false
+            }
+            Msg::ToggleSection(section) => {
+                if !self.state.collapsed.remove(&section) {
+                    self.state.collapsed.insert(section);
+                }
+                true
+            }
+            Msg::ToggleFavourite(room_id) => {
+                let favourite = !self.has_tag(&room_id, FAVOURITE);
+                self.matrix_agent.send(Request::SetRoomTag((
+                    room_id,
+                    FAVOURITE.to_string(),
+                    favourite,
+                )));
+                false
+            }
+            Msg::ShowCreateRoom(show) => {
+                self.state.show_create_room = show;
+                true
+            }
+            Msg::LeaveRoom((room_id, forget)) => {
+                let request = if forget {
+                    Request::ForgetRoom(room_id)
+                } else {
+                    Request::LeaveRoom(room_id)
+                };
+                self.matrix_agent.send(request);
+                false
+            }
+            Msg::AcceptInvite(room_id) => {
+                self.state.answering_invites.insert(room_id.clone());
+                self.matrix_agent
+                    .send(Request::JoinRoom((room_id.into(), Vec::new())));
+                true
+            }
+            Msg::DeclineInvite(room_id) => {
+                self.state.answering_invites.insert(room_id.clone());
+                self.matrix_agent.send(Request::RejectInvite(room_id));
+                true
+            }
+            Msg::SetStatus(status_msg) => {
+                self.state.status_msg = status_msg;
+                let presence = self.state.own_presence.unwrap_or(PresenceState::Online);
+                self.set_presence(presence);
+                false
+            }
         }
     }
 
@@ -143,30 +418,59 @@ impl Component for RoomList {
                 </div>
             }
         } else {
-            let rooms: Html = match self.state.search_query.as_deref() {
-                None | Some("") => self
-                    .state
-                    .rooms
-                    .iter()
-                    .map(|(_, room)| self.get_room(room))
-                    .collect(),
-                _ => self
-                    .state
-                    .rooms
-                    .iter()
-                    .filter(|(_, room)| {
-                        room.display_name()
-                            .to_lowercase()
-                            .contains(&self.state.search_query.as_ref().unwrap().to_lowercase())
-                    })
-                    .map(|(_, room)| self.get_room(room))
-                    .collect(),
+            let query = self
+                .state
+                .search_query
+                .as_deref()
+                .unwrap_or("")
+                .to_lowercase();
+            let mut rooms: Vec<&Rc<Room>> = self
+                .state
+                .rooms
+                .values()
+                .filter(|room| {
+                    query.is_empty() || room.display_name().to_lowercase().contains(&query)
+                })
+                .collect();
+            rooms.sort_by(|a, b| self.compare_rooms(a, b));
+
+            let invites = self.view_invites(&query);
+            let create_room = if self.state.show_create_room {
+                html! {
+                    <CreateRoomModal
+                        account=self.props.account.clone()
+                        on_close=self.link.callback(|_| Msg::ShowCreateRoom(false))
+                    />
+                }
+            } else {
+                html! {}
             };
+            let rooms: Html = RoomSection::ALL
+                .iter()
+                .map(|section| {
+                    let section_rooms: Vec<&Rc<Room>> = rooms
+                        .iter()
+                        .copied()
+                        .filter(|room| self.room_section(room) == *section)
+                        .collect();
+                    self.view_section(*section, &section_rooms)
+                })
+                .collect();
 
             html! {
                 <div class="roomlist" style="height: 100%">
                     <div class="top-bar">
                         <div class="userdata">
+                            <AccountSwitcher
+                                active=self.props.account.clone()
+                                accounts=self.props.accounts.clone()
+                                switch_account=self.props.switch_account.clone()
+                                add_account=self.props.add_account.clone()
+                            />
+                            <StatusMessage
+                                presence=self.own_presence()
+                                on_change=self.link.callback(Msg::SetStatus)
+                            />
                         </div>
                         <div class="search">
                             <div>
@@ -183,9 +487,35 @@ impl Component for RoomList {
                                     value=self.state.search_query.as_deref().unwrap_or("")
                                     oninput=self.link.callback(|e: InputData| Msg::SetFilter(e.value)) />
                             </div>
+                            <a
+                                class="new-room"
+                                title={
+                                    tr!(
+                                        // Tooltip of the button which opens the dialog to create a room
+                                        "New room"
+                                    )
+                                }
+                                onclick=self.link.callback(|_| Msg::ShowCreateRoom(true))
+                            >
+                                <span class="material-icons">{"add"}</span>
+                            </a>
+                            <div
+                                class="explore-link"
+                                title={
+                                    tr!(
+                                        // Tooltip of the button which opens the public room directory
+                                        "Explore rooms"
+                                    )
+                                }
+                            >
+                                <RouterAnchor<AppRoute> route=AppRoute::Explore>
+                                    <span class="material-icons">{"explore"}</span>
+                                </RouterAnchor<AppRoute>>
+                            </div>
                         </div>
                     </div>
-                    <div class="scrollable list">{rooms}</div>
+                    <div class="scrollable list">{invites}{rooms}</div>
+                    { create_room }
                     <div class="bottom-bar">
                         <div class="toggleWrapper">
                             <div>
@@ -205,6 +535,11 @@ impl Component for RoomList {
                                 </label>
                             </div>
                         </div>
+                        <div class="settings-link">
+                            <RouterAnchor<AppRoute> route=AppRoute::Settings>
+                                <span class="material-icons">{"settings"}</span>
+                            </RouterAnchor<AppRoute>>
+                        </div>
                     </div>
                 </div>
             }
@@ -213,10 +548,200 @@ impl Component for RoomList {
 }
 
 impl RoomList {
+    //noinspection RsTypeCheck
+    fn view_invites(&self, query: &str) -> Html {
+        let mut invites: Vec<&Invite> = self
+            .state
+            .invites
+            .values()
+            .filter(|invite| {
+                query.is_empty() || invite.display_name().to_lowercase().contains(query)
+            })
+            .collect();
+        if invites.is_empty() {
+            return html! {};
+        }
+        invites.sort_by_key(|invite| invite.display_name().to_lowercase());
+
+        let items: Html = invites
+            .into_iter()
+            .map(|invite| {
+                html! {
+                    <InviteItem
+                        invite=invite.clone()
+                        answering=self.state.answering_invites.contains(&invite.room_id)
+                        on_accept=self.link.callback(Msg::AcceptInvite)
+                        on_decline=self.link.callback(Msg::DeclineInvite)
+                    />
+                }
+            })
+            .collect();
+
+        html! {
+            <div class="room-section">
+                <div class="room-section-header">
+                    {
+                        tr!(
+                            // Header of the room list section with pending invites
+                            "Invites"
+                        )
+                    }
+                </div>
+                { items }
+            </div>
+        }
+    }
+
+    //noinspection RsTypeCheck
+    fn view_section(&self, section: RoomSection, rooms: &[&Rc<Room>]) -> Html {
+        if rooms.is_empty() {
+            return html! {};
+        }
+        let collapsed = self.state.collapsed.contains(&section);
+        let icon = if collapsed {
+            "chevron_right"
+        } else {
+            "expand_more"
+        };
+        let items: Html = if collapsed {
+            html! {}
+        } else {
+            rooms.iter().map(|room| self.get_room(room)).collect()
+        };
+
+        html! {
+            <div class="room-section">
+                <a class="room-section-header" onclick=self.link.callback(move |_| Msg::ToggleSection(section))>
+                    <span class="material-icons">{icon}</span>
+                    { section.title() }
+                </a>
+                { items }
+            </div>
+        }
+    }
+
+    fn room_section(&self, room: &Room) -> RoomSection {
+        if self.has_tag(&room.room_id, FAVOURITE) {
+            RoomSection::Favourites
+        } else if self.has_tag(&room.room_id, LOW_PRIORITY) {
+            RoomSection::LowPriority
+        } else if room.direct_target.is_some() {
+            RoomSection::DirectMessages
+        } else {
+            RoomSection::Rooms
+        }
+    }
+
+    /// The section of the room and how many rooms are shown above it
+    fn room_position(&self, room_id: &RoomId) -> Option<(RoomSection, usize)> {
+        let room = self.state.rooms.get(room_id)?;
+        let section = self.room_section(room);
+        let above = self
+            .state
+            .rooms
+            .values()
+            .filter(|other| {
+                self.room_section(other) == section
+                    && self.compare_rooms(other, room) == Ordering::Less
+            })
+            .count();
+        Some((section, above))
+    }
+
+    /// Most recent activity first, rooms with the same activity by name so the order is stable
+    fn compare_rooms(&self, a: &Room, b: &Room) -> Ordering {
+        self.latest_activity(b)
+            .cmp(&self.latest_activity(a))
+            .then_with(|| {
+                a.display_name()
+                    .to_lowercase()
+                    .cmp(&b.display_name().to_lowercase())
+            })
+            .then_with(|| a.room_id.cmp(&b.room_id))
+    }
+
+    fn has_tag(&self, room_id: &RoomId, tag: &str) -> bool {
+        self.state
+            .tags
+            .get(room_id)
+            .map(|tags| tags.iter().any(|t| t == tag))
+            .unwrap_or(false)
+    }
+
+    /// The newest event of the room, rooms without any known events sort last
+    fn latest_activity(&self, room: &Room) -> Option<SystemTime> {
+        let latest_message = room.messages.iter().last().map(|message| match message {
+            AnyPossiblyRedactedSyncMessageEvent::Regular(event) => *event.origin_server_ts(),
+            AnyPossiblyRedactedSyncMessageEvent::Redacted(event) => *event.origin_server_ts(),
+        });
+        let latest_sync = self.state.latest_activity.get(&room.room_id).copied();
+        latest_message.max(latest_sync)
+    }
+
     fn get_room(&self, matrix_room: &Rc<Room>) -> Html {
         let room = matrix_room.clone();
         html! {
-            <RoomItem change_room_callback=self.link.callback(Msg::ChangeRoom) room=room.clone() />
+            <RoomItem
+                change_room_callback=self.link.callback(Msg::ChangeRoom)
+                toggle_favourite=self.link.callback(Msg::ToggleFavourite)
+                leave_room=self.link.callback(Msg::LeaveRoom)
+                favourite=self.has_tag(&room.room_id, FAVOURITE)
+                room=room.clone()
+                presence=self.direct_presence(&room)
+                unread=self.unread_counts(&room)
+            />
+        }
+    }
+
+    /// Opens the room right away if it is known, otherwise once the sync delivers it
+    fn open_when_joined(&mut self, room_id: RoomId) {
+        if self.state.rooms.contains_key(&room_id) {
+            self.link.send_message(Msg::ChangeRoom(room_id));
+        } else {
+            self.state.open_when_joined = Some(room_id);
         }
     }
+
+    fn unread_counts(&self, room: &Room) -> UnreadCounts {
+        self.state
+            .unread
+            .get(&room.room_id)
+            .copied()
+            .unwrap_or_else(|| UnreadCounts::from_room(room))
+    }
+
+    /// The presence of the other user in a direct chat
+    fn direct_presence(&self, room: &Room) -> Option<PresenceInfo> {
+        let user_id = room.direct_target.as_ref()?;
+        self.state
+            .presence
+            .get(user_id)
+            .cloned()
+            .or_else(|| room.get_user_presence(user_id))
+    }
+
+    fn own_user_id(&self) -> Option<&str> {
+        self.props
+            .accounts
+            .iter()
+            .find(|account| account.id == self.props.account)
+            .map(|account| account.user_id.as_str())
+    }
+
+    fn own_presence(&self) -> Option<PresenceInfo> {
+        let own_user_id = self.own_user_id()?;
+        self.state
+            .presence
+            .values()
+            .find(|presence| presence.user_id.as_str() == own_user_id)
+            .cloned()
+    }
+
+    fn set_presence(&mut self, presence: PresenceState) {
+        self.state.own_presence = Some(presence);
+        self.matrix_agent.send(Request::SetPresence((
+            presence,
+            self.state.status_msg.clone(),
+        )));
+    }
 }
@@ -0,0 +1,174 @@
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+/// The actions of a room in the room list, leaving asks for confirmation first
+pub(crate) struct RoomMenu {
+    props: Props,
+    link: ComponentLink<Self>,
+    state: State,
+}
+
+#[derive(Default)]
+pub struct State {
+    open: bool,
+    confirm_leave: bool,
+    forget: bool,
+}
+
+pub enum Msg {
+    ToggleMenu,
+    AskLeave,
+    ToggleForget,
+    Leave,
+    Cancel,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    pub room_name: String,
+    /// Called with `true` if the room should be forgotten as well
+    #[prop_or_default]
+    pub on_leave: Callback<bool>,
+}
+
+impl Component for RoomMenu {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        RoomMenu {
+            props,
+            link,
+            state: Default::default(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ToggleMenu => {
+                self.state = State {
+                    open: !self.state.open,
+                    ..Default::default()
+                };
+            }
+            Msg::AskLeave => {
+                self.state.confirm_leave = true;
+            }
+            Msg::ToggleForget => {
+                self.state.forget = !self.state.forget;
+            }
+            Msg::Leave => {
+                self.props.on_leave.emit(self.state.forget);
+                self.state = Default::default();
+            }
+            Msg::Cancel => {
+                self.state = Default::default();
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let classes = if self.state.open {
+            "room-menu open"
+        } else {
+            "room-menu"
+        };
+        let dropdown = if self.state.confirm_leave {
+            self.view_confirm_leave()
+        } else if self.state.open {
+            html! {
+                <ul class="room-menu-dropdown uk-nav uk-dropdown-nav">
+                    <li>
+                        <a onclick=self.link.callback(|_| Msg::AskLeave)>
+                            <span class="material-icons">{"logout"}</span>
+                            {
+                                tr!(
+                                    // Action of a room to leave it
+                                    "Leave room"
+                                )
+                            }
+                        </a>
+                    </li>
+                </ul>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <div class=classes>
+                <a
+                    class="room-menu-toggle"
+                    title={
+                        tr!(
+                            // Tooltip of the button which opens the actions of a room
+                            "Room options"
+                        )
+                    }
+                    onclick=self.link.callback(|_| Msg::ToggleMenu)
+                >
+                    <span class="material-icons">{"more_vert"}</span>
+                </a>
+                { dropdown }
+            </div>
+        }
+    }
+}
+
+impl RoomMenu {
+    //noinspection RsTypeCheck
+    fn view_confirm_leave(&self) -> Html {
+        html! {
+            <form
+                class="room-menu-dropdown"
+                onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::Leave })
+            >
+                <p>
+                    {
+                        tr!(
+                            // Question before leaving a room, {0} is the room name
+                            "Leave {0}?",
+                            self.props.room_name
+                        )
+                    }
+                </p>
+                <label>
+                    <input
+                        class="uk-checkbox"
+                        type="checkbox"
+                        checked=self.state.forget
+                        onclick=self.link.callback(|_| Msg::ToggleForget)
+                    />
+                    { " " }
+                    {
+                        tr!(
+                            // Option when leaving a room to also remove it from the history
+                            "Forget this room"
+                        )
+                    }
+                </label>
+                <div class="room-menu-buttons">
+                    <button type="button" class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Cancel)>
+                        { tr!("Cancel") }
+                    </button>
+                    <button class="uk-button uk-button-danger">
+                        {
+                            tr!(
+                                // Button to confirm leaving a room
+                                "Leave"
+                            )
+                        }
+                    </button>
+                </div>
+            </form>
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+use crate::app::components::events::presence_dot_html;
+use crate::app::matrix::presence::PresenceInfo;
+
+/// Our own presence and status message, which can be edited in place
+pub(crate) struct StatusMessage {
+    props: Props,
+    link: ComponentLink<Self>,
+    state: State,
+}
+
+#[derive(Default)]
+pub struct State {
+    /// The new status message while editing
+    editing: Option<String>,
+}
+
+pub enum Msg {
+    Edit,
+    SetValue(String),
+    Save,
+    Cancel,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    #[prop_or_default]
+    pub presence: Option<PresenceInfo>,
+    /// `None` clears the status message
+    #[prop_or_default]
+    pub on_change: Callback<Option<String>>,
+}
+
+impl Component for StatusMessage {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        StatusMessage {
+            props,
+            link,
+            state: Default::default(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Edit => {
+                let current = self
+                    .props
+                    .presence
+                    .as_ref()
+                    .and_then(|presence| presence.status_msg.clone());
+                self.state.editing = Some(current.unwrap_or_default());
+            }
+            Msg::SetValue(value) => {
+                self.state.editing = Some(value);
+            }
+            Msg::Save => {
+                if let Some(value) = self.state.editing.take() {
+                    let value = value.trim().to_string();
+                    self.props
+                        .on_change
+                        .emit(Some(value).filter(|value| !value.is_empty()));
+                }
+            }
+            Msg::Cancel => {
+                self.state.editing = None;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let presence = self.props.presence.as_ref();
+        if let Some(value) = &self.state.editing {
+            return html! {
+                <form
+                    class="status-message editing"
+                    onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::Save })
+                >
+                    { presence_dot_html(presence) }
+                    <input
+                        class="uk-input uk-form-small"
+                        placeholder={
+                            tr!(
+                                // Placeholder of the input for the own status message
+                                "What's happening?"
+                            )
+                        }
+                        value=value
+                        oninput=self.link.callback(|e: InputData| Msg::SetValue(e.value))
+                        onkeydown=self.link.batch_callback(|e: KeyboardEvent| {
+                            if e.key() == "Escape" { vec![Msg::Cancel] } else { vec![] }
+                        })
+                    />
+                </form>
+            };
+        }
+
+        let status_msg = presence
+            .and_then(|presence| presence.status_msg.clone())
+            .unwrap_or_else(|| {
+                tr!(
+                    // Shown in the top bar when no status message is set
+                    "Set a status"
+                )
+            });
+        html! {
+            <a class="status-message" onclick=self.link.callback(|_| Msg::Edit)>
+                { presence_dot_html(presence) }
+                <span>{ status_msg }</span>
+            </a>
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use matrix_sdk::identifiers::EventId;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use tr::tr;
+
+/// A thread as shown below its root and in the thread list
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThreadSummary {
+    pub root: EventId,
+    /// "Sender: message" of the root
+    pub root_preview: String,
+    pub reply_count: usize,
+    /// "Sender: message" of the latest reply
+    pub latest_preview: String,
+}
+
+/// Lists the threads of a room, the most recently active first
+pub(crate) struct ThreadList {
+    props: Props,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    Open(EventId),
+    Close,
+}
+
+#[derive(Clone, Properties, Debug, PartialEq)]
+pub struct Props {
+    pub threads: Vec<ThreadSummary>,
+    #[prop_or_default]
+    pub on_open: Callback<EventId>,
+    #[prop_or_default]
+    pub on_close: Callback<()>,
+}
+
+impl Component for ThreadList {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        ThreadList { props, link }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Open(root) => self.props.on_open.emit(root),
+            Msg::Close => self.props.on_close.emit(()),
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let threads: Html = if self.props.threads.is_empty() {
+            html! {
+                <li>
+                    {
+                        tr!(
+                            // Shown in the thread list of a room without threads
+                            "No threads yet"
+                        )
+                    }
+                </li>
+            }
+        } else {
+            self.props
+                .threads
+                .iter()
+                .map(|thread| {
+                    let root = thread.root.clone();
+                    html! {
+                        <li>
+                            <a class="thread-list-item" onclick=self.link.callback(move |_| Msg::Open(root.clone()))>
+                                <p class="thread-root-preview">{&thread.root_preview}</p>
+                                <span class="uk-text-meta">
+                                    {
+                                        tr!(
+                                            // {0} is the number of replies, {1} "Sender: message" of the latest one
+                                            "{0} replies · {1}",
+                                            thread.reply_count,
+                                            thread.latest_preview
+                                        )
+                                    }
+                                </span>
+                            </a>
+                        </li>
+                    }
+                })
+                .collect()
+        };
+
+        html! {
+            <div class="side-panel scrollable">
+                <div class="side-panel-header">
+                    <h4>
+                        {
+                            tr!(
+                                // Title of the list of threads in a room
+                                "Threads"
+                            )
+                        }
+                    </h4>
+                    <a onclick=self.link.callback(|_| Msg::Close)>
+                        <span class="material-icons">{"close"}</span>
+                    </a>
+                </div>
+                <ul class="uk-list uk-list-divider">{threads}</ul>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::rc::Rc;
+
+use matrix_sdk::{
+    events::{room::message::MessageEventContent, AnyMessageEventContent, AnySyncMessageEvent},
+    Room,
+};
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::components::{
+    events::{message_body, text::Text, EventExt, RoomExt},
+    input::Input,
+};
+
+/// The timeline of a single thread with its own composer
+pub(crate) struct ThreadPanel {
+    props: Props,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    Close,
+}
+
+#[derive(Clone, Properties, Debug)]
+pub struct Props {
+    /// `None` if the root isn't loaded
+    #[prop_or_default]
+    pub root: Option<AnySyncMessageEvent>,
+    pub replies: Vec<AnySyncMessageEvent>,
+    pub room: Rc<Room>,
+    #[prop_or_default]
+    pub on_send: Callback<String>,
+    #[prop_or_default]
+    pub on_close: Callback<()>,
+}
+
+impl Component for ThreadPanel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        ThreadPanel { props, link }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Close => self.props.on_close.emit(()),
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        // TODO fix the PartialEq hack
+        self.props = props;
+        true
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let mut prev_event: Option<&AnySyncMessageEvent> = None;
+        let mut messages = Vec::new();
+        for event in self.props.root.iter().chain(self.props.replies.iter()) {
+            messages.push(self.view_message(prev_event, event));
+            prev_event = Some(event);
+        }
+
+        html! {
+            <div class="side-panel thread-panel">
+                <div class="side-panel-header">
+                    <h4>
+                        {
+                            tr!(
+                                // Title of the side panel showing a single thread
+                                "Thread"
+                            )
+                        }
+                    </h4>
+                    <a onclick=self.link.callback(|_| Msg::Close)>
+                        <span class="material-icons">{"close"}</span>
+                    </a>
+                </div>
+                <div class="scrollable">
+                    <div class="message-container">
+                        { messages.into_iter().collect::<Html>() }
+                    </div>
+                </div>
+                <Input
+                    room_id=self.props.room.room_id.clone()
+                    on_submit=&self.props.on_send
+                    encrypted=self.props.room.is_encrypted()
+                />
+            </div>
+        }
+    }
+}
+
+impl ThreadPanel {
+    //noinspection RsTypeCheck
+    fn view_message(
+        &self,
+        prev_event: Option<&AnySyncMessageEvent>,
+        event: &AnySyncMessageEvent,
+    ) -> Html {
+        match event.content() {
+            AnyMessageEventContent::RoomMessage(MessageEventContent::Text(mut text_event)) => {
+                // The reply to the latest message is only a fallback for clients without threads
+                text_event.relates_to = None;
+                html! {
+                    <Text
+                        prev_event=prev_event.cloned()
+                        event=event.clone()
+                        room=self.props.room.clone()
+                        text_event=text_event
+                    />
+                }
+            }
+            // Everything else is shown as plain text to keep the panel simple
+            AnyMessageEventContent::RoomMessage(content) => {
+                let sender = if event.is_new_user(prev_event) {
+                    html! {
+                        <displayname>{self.props.room.get_sender_displayname(event)}{": "}</displayname>
+                    }
+                } else {
+                    html! {}
+                };
+                html! {
+                    <p>{ sender }{ message_body(&content) }</p>
+                }
+            }
+            _ => html! {},
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use yew::prelude::*;
+
+use tr::tr;
+
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    verification::{VerificationInfo, VerificationState},
+    Request, Response,
+};
+
+/// Modal that guides through incoming and outgoing SAS verification flows
+pub struct VerificationModal {
+    link: ComponentLink<Self>,
+    state: State,
+    matrix_agent: AccountBridge,
+}
+
+#[derive(Default)]
+pub struct State {
+    verification: Option<VerificationInfo>,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum Msg {
+    NewMessage(Response),
+    Accept,
+    Confirm,
+    Cancel,
+    Close,
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+}
+
+impl Component for VerificationModal {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let matrix_callback = link.callback(Msg::NewMessage);
+        let matrix_agent = AccountBridge::new(props.account, matrix_callback);
+        VerificationModal {
+            link,
+            state: Default::default(),
+            matrix_agent,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            Msg::NewMessage(Response::Verification(info)) => {
+                // Only show one flow at a time
+                if let Some(current) = &self.state.verification {
+                    if current.flow_id != info.flow_id
+                        && current.state != VerificationState::Done
+                        && current.state != VerificationState::Cancelled
+                    {
+                        self.matrix_agent
+                            .send(Request::CancelVerification(info.flow_id));
+                        return false;
+                    }
+                }
+                self.state.verification = Some(info);
+                true
+            }
+            Msg::NewMessage(_) => false,
+            Msg::Accept => {
+                if let Some(verification) = &self.state.verification {
+                    self.matrix_agent
+                        .send(Request::AcceptVerification(verification.flow_id.clone()));
+                }
+                false
+            }
+            Msg::Confirm => {
+                if let Some(verification) = &self.state.verification {
+                    self.matrix_agent
+                        .send(Request::ConfirmVerification(verification.flow_id.clone()));
+                }
+                false
+            }
+            Msg::Cancel => {
+                if let Some(verification) = self.state.verification.take() {
+                    self.matrix_agent
+                        .send(Request::CancelVerification(verification.flow_id));
+                }
+                true
+            }
+            Msg::Close => {
+                self.state.verification = None;
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> bool {
+        false
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let verification = match &self.state.verification {
+            Some(verification) => verification,
+            None => return html! {},
+        };
+
+        let other = format!(
+            "{} ({})",
+            verification.other_user_id, verification.other_device_id
+        );
+
+        let (content, buttons) = match &verification.state {
+            VerificationState::Requested => (
+                html! {
+                    <p>
+                        {
+                            tr!(
+                                // {0} is the user and device that wants to verify
+                                "{0} wants to verify this session.",
+                                other
+                            )
+                        }
+                    </p>
+                },
+                html! {
+                    <>
+                        <button class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Cancel)>
+                            { tr!("Decline") }
+                        </button>
+                        <button class="uk-button uk-button-primary" onclick=self.link.callback(|_| Msg::Accept)>
+                            { tr!("Accept") }
+                        </button>
+                    </>
+                },
+            ),
+            VerificationState::Started => (
+                html! {
+                    <p>
+                        {
+                            tr!(
+                                // {0} is the user and device we want to verify
+                                "Waiting for {0} to accept the verification…",
+                                other
+                            )
+                        }
+                    </p>
+                },
+                html! {
+                    <button class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Cancel)>
+                        { tr!("Cancel") }
+                    </button>
+                },
+            ),
+            VerificationState::Emoji(emoji) => (
+                html! {
+                    <>
+                        <p>
+                            {
+                                tr!(
+                                    // Explanation shown above the SAS emoji
+                                    "Confirm that the following emoji appear on the other device in the same order:"
+                                )
+                            }
+                        </p>
+                        <div class="verification-emoji">
+                            {
+                                for emoji.iter().map(|(emoji, description)| html! {
+                                    <div class="emoji">
+                                        <span class="symbol">{emoji}</span>
+                                        <span class="description">{description}</span>
+                                    </div>
+                                })
+                            }
+                        </div>
+                    </>
+                },
+                html! {
+                    <>
+                        <button class="uk-button uk-button-danger" onclick=self.link.callback(|_| Msg::Cancel)>
+                            { tr!("They don't match") }
+                        </button>
+                        <button class="uk-button uk-button-primary" onclick=self.link.callback(|_| Msg::Confirm)>
+                            { tr!("They match") }
+                        </button>
+                    </>
+                },
+            ),
+            VerificationState::Confirmed => (
+                html! {
+                    <p>
+                        {
+                            tr!(
+                                // {0} is the user and device we are verifying
+                                "Waiting for {0} to confirm…",
+                                other
+                            )
+                        }
+                    </p>
+                },
+                html! {
+                    <button class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Cancel)>
+                        { tr!("Cancel") }
+                    </button>
+                },
+            ),
+            VerificationState::Done => (
+                html! {
+                    <p>
+                        {
+                            tr!(
+                                // {0} is the user and device we verified
+                                "{0} is now verified.",
+                                other
+                            )
+                        }
+                    </p>
+                },
+                html! {
+                    <button class="uk-button uk-button-primary" onclick=self.link.callback(|_| Msg::Close)>
+                        { tr!("Close") }
+                    </button>
+                },
+            ),
+            VerificationState::Cancelled => (
+                html! {
+                    <p>{ tr!("The verification was cancelled.") }</p>
+                },
+                html! {
+                    <button class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::Close)>
+                        { tr!("Close") }
+                    </button>
+                },
+            ),
+        };
+
+        html! {
+            <div class="uk-modal uk-open verification-modal" style="display: block;">
+                <div class="uk-modal-dialog uk-modal-body">
+                    <h2 class="uk-modal-title">
+                        {
+                            tr!(
+                                // Title of the device verification dialog
+                                "Verify session"
+                            )
+                        }
+                    </h2>
+                    { content }
+                    <p class="uk-text-right">{ buttons }</p>
+                </div>
+            </div>
+        }
+    }
+}
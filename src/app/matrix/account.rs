@@ -0,0 +1,45 @@
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use yew::{Bridge, Bridged, Callback};
+
+use crate::app::matrix::{MatrixAgent, Request, Response};
+
+/// Identifies one of the accounts the `MatrixAgent` runs a client for
+pub type AccountId = String;
+
+/// Creates the id for an account that is about to log in
+pub fn new_account_id() -> AccountId {
+    thread_rng().sample_iter(&Alphanumeric).take(16).collect()
+}
+
+/// What the account switcher shows for an account
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountSummary {
+    pub id: AccountId,
+    pub user_id: String,
+    pub unread: usize,
+}
+
+/// A bridge to the `MatrixAgent` which only talks about a single account
+pub struct AccountBridge {
+    account: AccountId,
+    bridge: Box<dyn Bridge<MatrixAgent>>,
+}
+
+impl AccountBridge {
+    pub fn new(account: AccountId, callback: Callback<Response>) -> Self {
+        let own_account = account.clone();
+        let bridge = MatrixAgent::bridge(Callback::from(
+            move |(account, response): (AccountId, Response)| {
+                if account == own_account {
+                    callback.emit(response);
+                }
+            },
+        ));
+        AccountBridge { account, bridge }
+    }
+
+    pub fn send(&mut self, request: Request) {
+        self.bridge.send((self.account.clone(), request));
+    }
+}
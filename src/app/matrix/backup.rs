@@ -0,0 +1,557 @@
+use std::collections::HashSet;
+#[cfg(feature = "encryption")]
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "encryption")]
+use log::*;
+#[cfg(feature = "encryption")]
+use matrix_sdk::{
+    api::{
+        error::{ErrorKind, FromHttpResponseError, ServerError},
+        r0::backup::{
+            add_backup_keys, create_backup_version, get_backup_keys, get_latest_backup_info,
+            BackupAlgorithm, EncryptedSessionData, KeyBackupData, RoomKeyBackup,
+        },
+    },
+    identifiers::{DeviceKeyAlgorithm, RoomId, UserId},
+    js_int::UInt,
+    Client, ExportedRoomKey,
+};
+#[cfg(feature = "encryption")]
+use olm_rs::{
+    inbound_group_session::OlmInboundGroupSession,
+    pk::{OlmPkDecryption, OlmPkEncryption, PkMessage},
+    utility::OlmUtility,
+};
+#[cfg(feature = "encryption")]
+use rand::random;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "encryption")]
+use serde_json::{json, Value};
+
+#[cfg(feature = "encryption")]
+use crate::errors::MatrixError;
+
+#[cfg(feature = "encryption")]
+const RECOVERY_KEY_PREFIX: [u8; 2] = [0x8B, 0x01];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BackupStatus {
+    pub version: Option<String>,
+    pub backed_up_keys: usize,
+    pub total_keys: usize,
+    /// This session logged in freshly and can restore the room keys from the backup
+    pub can_restore: bool,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+struct BackupState {
+    version: Option<String>,
+    public_key: Option<String>,
+    uploaded_sessions: HashSet<String>,
+    total_keys: usize,
+    /// Set when we might have received or created room keys since the last upload
+    has_new_keys: bool,
+    can_restore: bool,
+}
+
+/// Keeps track of the `m.megolm_backup.v1.curve25519-aes-sha2` backup the room keys get uploaded to
+#[derive(Clone, Debug, Default)]
+pub struct KeyBackup {
+    state: Arc<Mutex<BackupState>>,
+}
+
+impl KeyBackup {
+    pub fn status(&self) -> BackupStatus {
+        let state = self.state.lock().unwrap();
+        BackupStatus {
+            version: state.version.clone(),
+            backed_up_keys: state.uploaded_sessions.len(),
+            total_keys: state.total_keys,
+            can_restore: state.can_restore,
+        }
+    }
+
+    /// Makes the next `upload_new_keys` look for keys that aren't in the backup yet
+    pub fn keys_changed(&self) {
+        self.state.lock().unwrap().has_new_keys = true;
+    }
+
+    pub fn has_new_keys(&self) -> bool {
+        self.state.lock().unwrap().has_new_keys
+    }
+
+    /// Stops offering to restore the room keys from the backup
+    pub fn dismiss_restore(&self) {
+        self.state.lock().unwrap().can_restore = false;
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl KeyBackup {
+    /// Looks up the latest backup version on the server
+    pub async fn load(&self, client: &Client) -> Result<(), MatrixError> {
+        let response = client
+            .send(get_latest_backup_info::Request {})
+            .await
+            .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+        if let BackupAlgorithm::MegolmBackupV1Curve25519AesSha2 {
+            public_key,
+            signatures,
+        } = response.algorithm
+        {
+            // Otherwise the homeserver could swap in its own key and read every key we upload.
+            // A key we created or restored from is ours even if no verified session signed it.
+            let known_key = self.state.lock().unwrap().public_key.as_ref() == Some(&public_key);
+            if !known_key && !is_signed_by_us(client, &public_key, &signatures).await {
+                let mut state = self.state.lock().unwrap();
+                state.version = None;
+                state.public_key = None;
+                state.uploaded_sessions.clear();
+                return Err(MatrixError::UntrustedKeyBackup);
+            }
+
+            let known_version = self.state.lock().unwrap().version.clone();
+            if known_version.as_ref() != Some(&response.version) {
+                // Only the sessions the server doesn't have yet get uploaded
+                let uploaded_sessions = backed_up_sessions(client, &response.version).await?;
+                let mut state = self.state.lock().unwrap();
+                state.uploaded_sessions = uploaded_sessions;
+                state.version = Some(response.version);
+                state.public_key = Some(public_key);
+                state.has_new_keys = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Offers to restore the room keys after a fresh login if there is a backup
+    pub async fn check_restore(&self, client: &Client) -> Result<(), MatrixError> {
+        match client.send(get_latest_backup_info::Request {}).await {
+            Ok(_) => {
+                self.state.lock().unwrap().can_restore = true;
+                Ok(())
+            }
+            Err(matrix_sdk::Error::RumaResponse(FromHttpResponseError::Http(
+                ServerError::Known(e),
+            ))) if e.kind == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(MatrixError::SDKError(e.to_string())),
+        }
+    }
+
+    /// Downloads the latest backup and imports all keys that can be decrypted with the recovery key
+    pub async fn restore(&self, client: &Client, recovery_key: &str) -> Result<usize, MatrixError> {
+        let private_key = decode_recovery_key(recovery_key)?;
+        let decryption = OlmPkDecryption::from_bytes(&private_key)
+            .map_err(|_| MatrixError::InvalidRecoveryKey)?;
+
+        let backup_info = client
+            .send(get_latest_backup_info::Request {})
+            .await
+            .map_err(|_| MatrixError::MissingKeyBackup)?;
+        let public_key = match backup_info.algorithm {
+            BackupAlgorithm::MegolmBackupV1Curve25519AesSha2 { public_key, .. }
+                if public_key == decryption.public_key() =>
+            {
+                public_key
+            }
+            _ => return Err(MatrixError::InvalidRecoveryKey),
+        };
+
+        let response = client
+            .send(get_backup_keys::Request {
+                version: backup_info.version.clone(),
+            })
+            .await
+            .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+
+        let mut keys: Vec<ExportedRoomKey> = Vec::new();
+        let mut session_ids = HashSet::new();
+        for (room_id, room_backup) in response.rooms {
+            for (session_id, backup_data) in room_backup.sessions {
+                session_ids.insert(session_id.clone());
+                let message = PkMessage::new(
+                    backup_data.session_data.ephemeral,
+                    backup_data.session_data.mac,
+                    backup_data.session_data.ciphertext,
+                );
+                let decrypted = match decryption.decrypt(message) {
+                    Ok(decrypted) => decrypted,
+                    Err(e) => {
+                        warn!("Failed to decrypt backed up session {}: {}", session_id, e);
+                        continue;
+                    }
+                };
+                let mut session_data: Value = match serde_json::from_str(&decrypted) {
+                    Ok(session_data) => session_data,
+                    Err(e) => {
+                        warn!("Invalid backed up session {}: {}", session_id, e);
+                        continue;
+                    }
+                };
+                session_data["room_id"] = json!(room_id);
+                session_data["session_id"] = json!(session_id);
+                match serde_json::from_value(session_data) {
+                    Ok(key) => keys.push(key),
+                    Err(e) => warn!("Invalid backed up session {}: {}", session_id, e),
+                }
+            }
+        }
+
+        let count = client
+            .import_room_keys(keys)
+            .await
+            .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+        info!(
+            "Restored {} keys from backup {}",
+            count, backup_info.version
+        );
+
+        // Holding the private key proves that the backup is ours, so we keep it up to date
+        let mut state = self.state.lock().unwrap();
+        state.version = Some(backup_info.version);
+        state.public_key = Some(public_key);
+        state.uploaded_sessions = session_ids;
+        state.has_new_keys = true;
+        state.can_restore = false;
+        Ok(count)
+    }
+
+    /// Creates a new backup version and returns the recovery key for it
+    pub async fn create(&self, client: &Client) -> Result<String, MatrixError> {
+        let private_key: [u8; 32] = random();
+        let decryption = OlmPkDecryption::from_bytes(&private_key)
+            .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+        let public_key = decryption.public_key().to_string();
+
+        // Signed with the device key and, once cross-signing is set up, the master key
+        let signatures = client
+            .sign(&auth_data_json(&public_key))
+            .await
+            .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+        let response = client
+            .send(create_backup_version::Request {
+                algorithm: BackupAlgorithm::MegolmBackupV1Curve25519AesSha2 {
+                    public_key: public_key.clone(),
+                    signatures,
+                },
+            })
+            .await
+            .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+
+        {
+            let mut state = self.state.lock().unwrap();
+            state.version = Some(response.version);
+            state.public_key = Some(public_key);
+            state.uploaded_sessions.clear();
+            state.has_new_keys = true;
+            state.can_restore = false;
+        }
+
+        Ok(encode_recovery_key(&private_key))
+    }
+
+    /// Uploads all room keys that are not in the backup yet
+    pub async fn upload_new_keys(&self, client: &Client) -> Result<BackupStatus, MatrixError> {
+        let (version, public_key, uploaded_sessions) = {
+            let mut state = self.state.lock().unwrap();
+            let keys = match (&state.version, &state.public_key) {
+                (Some(version), Some(public_key)) => (
+                    version.clone(),
+                    public_key.clone(),
+                    state.uploaded_sessions.clone(),
+                ),
+                _ => return Err(MatrixError::MissingKeyBackup),
+            };
+            // Keys arriving while we upload are picked up the next time
+            state.has_new_keys = false;
+            keys
+        };
+
+        let result = self
+            .upload_keys(client, version, public_key, uploaded_sessions)
+            .await;
+        if result.is_err() {
+            self.keys_changed();
+        }
+        result
+    }
+
+    async fn upload_keys(
+        &self,
+        client: &Client,
+        version: String,
+        public_key: String,
+        uploaded_sessions: HashSet<String>,
+    ) -> Result<BackupStatus, MatrixError> {
+        let keys = client
+            .export_room_keys(|session| !uploaded_sessions.contains(session.session_id()))
+            .await
+            .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+        let total_keys = uploaded_sessions.len() + keys.len();
+
+        if !keys.is_empty() {
+            let encryption = OlmPkEncryption::new(&public_key);
+            let mut rooms: BTreeMap<RoomId, RoomKeyBackup> = BTreeMap::new();
+            let mut session_ids = Vec::with_capacity(keys.len());
+            let mut verified_keys: HashMap<RoomId, HashSet<String>> = HashMap::new();
+            for key in keys {
+                // The server keeps the copy which can decrypt the most messages
+                let first_message_index = match OlmInboundGroupSession::import(&key.session_key) {
+                    Ok(session) => session.first_known_index(),
+                    Err(e) => {
+                        warn!("Not backing up invalid session {}: {}", key.session_id, e);
+                        continue;
+                    }
+                };
+                if !verified_keys.contains_key(&key.room_id) {
+                    let room_keys = verified_device_keys(client, &key.room_id).await;
+                    verified_keys.insert(key.room_id.clone(), room_keys);
+                }
+                // Forwarded keys can't be traced back to the device that created them
+                let is_verified = key.forwarding_curve25519_key_chain.is_empty()
+                    && key
+                        .sender_claimed_keys
+                        .get(&DeviceKeyAlgorithm::Ed25519)
+                        .map_or(false, |sender_key| {
+                            verified_keys[&key.room_id].contains(sender_key)
+                        });
+
+                let session_data = json!({
+                    "algorithm": key.algorithm,
+                    "sender_key": key.sender_key,
+                    "session_key": key.session_key,
+                    "sender_claimed_keys": key.sender_claimed_keys,
+                    "forwarding_curve25519_key_chain": key.forwarding_curve25519_key_chain,
+                });
+                let message = encryption.encrypt(&session_data.to_string());
+                let backup_data = KeyBackupData {
+                    first_message_index: first_message_index.into(),
+                    forwarded_count: UInt::new(key.forwarding_curve25519_key_chain.len() as u64)
+                        .unwrap_or_default(),
+                    is_verified,
+                    session_data: EncryptedSessionData {
+                        ephemeral: message.ephemeral_key,
+                        ciphertext: message.ciphertext,
+                        mac: message.mac,
+                    },
+                };
+                session_ids.push(key.session_id.clone());
+                rooms
+                    .entry(key.room_id)
+                    .or_insert_with(|| RoomKeyBackup {
+                        sessions: BTreeMap::new(),
+                    })
+                    .sessions
+                    .insert(key.session_id, backup_data);
+            }
+
+            client
+                .send(add_backup_keys::Request {
+                    version: version.clone(),
+                    rooms,
+                })
+                .await
+                .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+            info!("Uploaded {} keys to backup {}", session_ids.len(), version);
+
+            let mut state = self.state.lock().unwrap();
+            // The backup might have been replaced while we were uploading
+            if state.version.as_ref() == Some(&version) {
+                state.uploaded_sessions.extend(session_ids);
+            }
+        }
+
+        self.state.lock().unwrap().total_keys = total_keys;
+        Ok(self.status())
+    }
+}
+
+/// The ids of the sessions which are already in the backup
+#[cfg(feature = "encryption")]
+async fn backed_up_sessions(
+    client: &Client,
+    version: &str,
+) -> Result<HashSet<String>, MatrixError> {
+    let response = client
+        .send(get_backup_keys::Request {
+            version: version.to_string(),
+        })
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+    Ok(response
+        .rooms
+        .into_iter()
+        .flat_map(|(_, room_backup)| room_backup.sessions.into_iter().map(|(id, _)| id))
+        .collect())
+}
+
+/// The ed25519 keys of the verified devices in the room, to tell which sessions come from them
+#[cfg(feature = "encryption")]
+async fn verified_device_keys(client: &Client, room_id: &RoomId) -> HashSet<String> {
+    let members: Vec<UserId> = match client.get_joined_room(room_id).await {
+        Some(room) => room.read().await.joined_members.keys().cloned().collect(),
+        None => return HashSet::new(),
+    };
+    let mut keys = HashSet::new();
+    for user_id in members {
+        if let Ok(devices) = client.get_user_devices(&user_id).await {
+            keys.extend(
+                devices
+                    .devices()
+                    .filter(|device| device.is_trusted())
+                    .filter_map(|device| device.ed25519_key().map(str::to_string)),
+            );
+        }
+    }
+    keys
+}
+
+/// The canonical json of the `auth_data` that gets signed, which is everything but the signatures
+#[cfg(feature = "encryption")]
+fn auth_data_json(public_key: &str) -> String {
+    json!({ "public_key": public_key }).to_string()
+}
+
+/// Checks that our master key or one of our verified sessions signed the backup key
+#[cfg(feature = "encryption")]
+async fn is_signed_by_us(
+    client: &Client,
+    public_key: &str,
+    signatures: &BTreeMap<UserId, BTreeMap<String, String>>,
+) -> bool {
+    let user_id = match client.user_id().await {
+        Some(user_id) => user_id,
+        None => return false,
+    };
+    let own_signatures = match signatures.get(&user_id) {
+        Some(own_signatures) => own_signatures,
+        None => return false,
+    };
+    let own_device_id = client.device_id().await;
+    let master_key = match client.get_user_identity(&user_id).await {
+        Some(identity) if identity.is_verified() => {
+            identity.master_key().get_first_key().map(str::to_string)
+        }
+        _ => None,
+    };
+
+    let message = auth_data_json(public_key);
+    let utility = OlmUtility::new();
+    for (key_id, signature) in own_signatures {
+        let key_name = match key_id.strip_prefix("ed25519:") {
+            Some(key_name) => key_name,
+            None => continue,
+        };
+        let signing_key = if master_key.as_deref() == Some(key_name) {
+            master_key.clone()
+        } else {
+            match client.get_device(&user_id, key_name.into()).await {
+                Some(device)
+                    if device.is_trusted()
+                        || own_device_id.as_deref() == Some(device.device_id()) =>
+                {
+                    device.ed25519_key().map(str::to_string)
+                }
+                _ => None,
+            }
+        };
+        if let Some(signing_key) = signing_key {
+            if let Ok(true) = utility.ed25519_verify(&signing_key, &message, signature.clone()) {
+                return true;
+            }
+        }
+    }
+    warn!("The key backup isn't signed by a verified session");
+    false
+}
+
+/// Encodes the backup private key as a recovery key as described in the spec
+#[cfg(feature = "encryption")]
+fn encode_recovery_key(private_key: &[u8]) -> String {
+    let mut bytes = RECOVERY_KEY_PREFIX.to_vec();
+    bytes.extend_from_slice(private_key);
+    let parity = bytes.iter().fold(0, |parity, byte| parity ^ byte);
+    bytes.push(parity);
+
+    // Groups of four characters are easier to write down
+    let encoded: Vec<char> = bs58::encode(bytes).into_string().chars().collect();
+    encoded
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(feature = "encryption")]
+fn decode_recovery_key(recovery_key: &str) -> Result<Vec<u8>, MatrixError> {
+    let recovery_key: String = recovery_key.split_whitespace().collect();
+    let bytes = bs58::decode(recovery_key)
+        .into_vec()
+        .map_err(|_| MatrixError::InvalidRecoveryKey)?;
+
+    let parity = bytes.iter().fold(0, |parity, byte| parity ^ byte);
+    if bytes.len() != 35 || bytes[..2] != RECOVERY_KEY_PREFIX || parity != 0 {
+        return Err(MatrixError::InvalidRecoveryKey);
+    }
+    Ok(bytes[2..34].to_vec())
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+
+    const PRIVATE_KEY: [u8; 32] = [7; 32];
+
+    /// Encodes the bytes like a recovery key, but without checking them
+    fn encode_bytes(mut bytes: Vec<u8>, parity_offset: u8) -> String {
+        let parity = bytes.iter().fold(0, |parity, byte| parity ^ byte);
+        bytes.push(parity ^ parity_offset);
+        bs58::encode(bytes).into_string()
+    }
+
+    #[test]
+    fn recovery_key_round_trip() {
+        let recovery_key = encode_recovery_key(&PRIVATE_KEY);
+        assert!(recovery_key.contains(' '));
+        assert_eq!(
+            decode_recovery_key(&recovery_key).unwrap(),
+            PRIVATE_KEY.to_vec()
+        );
+    }
+
+    #[test]
+    fn recovery_key_wrong_parity() {
+        let mut bytes = RECOVERY_KEY_PREFIX.to_vec();
+        bytes.extend_from_slice(&PRIVATE_KEY);
+        let recovery_key = encode_bytes(bytes, 1);
+        assert!(matches!(
+            decode_recovery_key(&recovery_key),
+            Err(MatrixError::InvalidRecoveryKey)
+        ));
+    }
+
+    #[test]
+    fn recovery_key_wrong_prefix() {
+        let mut bytes = vec![0x8B, 0x02];
+        bytes.extend_from_slice(&PRIVATE_KEY);
+        let recovery_key = encode_bytes(bytes, 0);
+        assert!(matches!(
+            decode_recovery_key(&recovery_key),
+            Err(MatrixError::InvalidRecoveryKey)
+        ));
+    }
+
+    #[test]
+    fn recovery_key_wrong_length() {
+        let mut bytes = RECOVERY_KEY_PREFIX.to_vec();
+        bytes.extend_from_slice(&PRIVATE_KEY[..31]);
+        let recovery_key = encode_bytes(bytes, 0);
+        assert!(matches!(
+            decode_recovery_key(&recovery_key),
+            Err(MatrixError::InvalidRecoveryKey)
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+use matrix_sdk::{
+    api::r0::{
+        directory::Visibility,
+        room::{create_room, create_room::RoomPreset},
+    },
+    events::{
+        room::encryption::EncryptionEventContent, AnyInitialStateEvent, EventEncryptionAlgorithm,
+        InitialStateEvent,
+    },
+    identifiers::{RoomId, UserId},
+    Client,
+};
+use serde::{Deserialize, Serialize};
+
+/// Everything the "New room" dialog asks for
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NewRoom {
+    pub name: String,
+    pub topic: String,
+    /// The localpart of the alias, e.g. `daydream` for `#daydream:example.org`
+    pub alias: String,
+    /// Public rooms can be joined by anyone and show up in the room directory
+    pub public: bool,
+    pub encrypted: bool,
+    pub invites: Vec<UserId>,
+}
+
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|value| !value.is_empty())
+}
+
+pub async fn create_room(client: &Client, new_room: &NewRoom) -> Result<RoomId, matrix_sdk::Error> {
+    let mut initial_state = Vec::new();
+    if new_room.encrypted {
+        initial_state.push(AnyInitialStateEvent::RoomEncryption(InitialStateEvent {
+            state_key: String::new(),
+            content: EncryptionEventContent::new(EventEncryptionAlgorithm::MegolmV1AesSha2),
+        }));
+    }
+    let (preset, visibility) = if new_room.public {
+        (RoomPreset::PublicChat, Visibility::Public)
+    } else {
+        (RoomPreset::PrivateChat, Visibility::Private)
+    };
+
+    let response = client
+        .create_room(create_room::Request {
+            creation_content: None,
+            initial_state: &initial_state,
+            invite: &new_room.invites,
+            invite_3pid: &[],
+            is_direct: None,
+            name: non_empty(&new_room.name),
+            power_level_content_override: None,
+            preset: Some(preset),
+            room_alias_name: non_empty(&new_room.alias),
+            room_version: None,
+            topic: non_empty(&new_room.topic),
+            visibility,
+        })
+        .await?;
+    Ok(response.room_id)
+}
@@ -0,0 +1,143 @@
+use std::convert::TryFrom;
+
+use matrix_sdk::{
+    api::r0::{
+        directory::{
+            get_public_rooms_filtered::{self, Filter, RoomNetwork},
+            PublicRoomsChunk,
+        },
+        user_directory::search_users,
+    },
+    identifiers::{RoomAliasId, RoomId, ServerName, UserId},
+    js_int::uint,
+    Client,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::app::matrix::types::get_media_download_url;
+use crate::errors::MatrixError;
+
+/// Which page of the room directory to load
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PublicRoomsQuery {
+    /// `None` for our own homeserver
+    pub server: Option<String>,
+    pub search_term: Option<String>,
+    /// The `next_batch` token of the previous page
+    pub since: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PublicRoomsPage {
+    pub query: PublicRoomsQuery,
+    pub rooms: Vec<PublicRoom>,
+    pub next_batch: Option<String>,
+}
+
+/// A room of the room directory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PublicRoom {
+    pub room_id: RoomId,
+    pub name: Option<String>,
+    pub topic: Option<String>,
+    pub canonical_alias: Option<RoomAliasId>,
+    pub num_joined_members: u64,
+    /// Already converted to a download url of our homeserver
+    pub avatar_url: Option<String>,
+}
+
+impl PublicRoom {
+    fn from_chunk(chunk: PublicRoomsChunk, client: &Client) -> Self {
+        PublicRoom {
+            avatar_url: chunk
+                .avatar_url
+                .filter(|url| url.starts_with("mxc://"))
+                .map(|url| get_media_download_url(client.homeserver(), &url).to_string()),
+            room_id: chunk.room_id,
+            name: chunk.name,
+            topic: chunk.topic,
+            canonical_alias: chunk.canonical_alias,
+            num_joined_members: chunk.num_joined_members.into(),
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        match (&self.name, &self.canonical_alias) {
+            (Some(name), _) => name.clone(),
+            (None, Some(alias)) => alias.to_string(),
+            (None, None) => self.room_id.to_string(),
+        }
+    }
+}
+
+/// Loads one page of `/publicRooms`, filtered by the search term on the server
+pub async fn public_rooms(
+    client: &Client,
+    query: PublicRoomsQuery,
+) -> Result<PublicRoomsPage, MatrixError> {
+    let server = match query.server.as_deref() {
+        Some(server) => Some(
+            <Box<ServerName>>::try_from(server)
+                .map_err(|_| MatrixError::InvalidServerName(server.to_string()))?,
+        ),
+        None => None,
+    };
+    let response = client
+        .send(get_public_rooms_filtered::Request {
+            server: server.as_deref(),
+            limit: Some(uint!(20)),
+            since: query.since.as_deref(),
+            filter: Filter {
+                generic_search_term: query.search_term.as_deref(),
+            },
+            room_network: RoomNetwork::Matrix,
+        })
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+
+    Ok(PublicRoomsPage {
+        rooms: response
+            .chunk
+            .into_iter()
+            .map(|chunk| PublicRoom::from_chunk(chunk, client))
+            .collect(),
+        next_batch: response.next_batch,
+        query,
+    })
+}
+
+/// A match of the user directory search
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserSearchResult {
+    pub user_id: UserId,
+    pub display_name: Option<String>,
+}
+
+impl UserSearchResult {
+    pub fn name(&self) -> &str {
+        self.display_name
+            .as_deref()
+            .unwrap_or_else(|| self.user_id.as_str())
+    }
+}
+
+/// Searches the users the server knows about, usually those sharing a room or in public rooms
+pub async fn search_users(
+    client: &Client,
+    search_term: &str,
+) -> Result<Vec<UserSearchResult>, matrix_sdk::Error> {
+    let response = client
+        .send(search_users::Request {
+            search_term,
+            limit: uint!(10),
+        })
+        .await?;
+    Ok(response
+        .results
+        .into_iter()
+        .map(|user| UserSearchResult {
+            user_id: user.user_id,
+            display_name: user.display_name,
+        })
+        .collect())
+}
@@ -0,0 +1,91 @@
+#[cfg(feature = "encryption")]
+use std::io::Cursor;
+
+#[cfg(feature = "encryption")]
+use log::*;
+#[cfg(feature = "encryption")]
+use matrix_sdk::{decrypt_key_export, encrypt_key_export, events::AnySyncMessageEvent};
+use matrix_sdk::{events::AnySyncRoomEvent, identifiers::RoomId, Client};
+
+use crate::errors::MatrixError;
+
+/// PBKDF2 rounds used for key exports, same as Element uses
+#[cfg(feature = "encryption")]
+const KEY_EXPORT_ROUNDS: u32 = 500_000;
+
+/// Tries to decrypt a Megolm encrypted timeline event using the room keys the client knows about.
+///
+/// Events that are not encrypted or that can't be decrypted (yet) are returned unchanged.
+/// The UI renders the latter as a placeholder.
+#[cfg(feature = "encryption")]
+pub async fn decrypt_sync_event(
+    client: &Client,
+    room_id: &RoomId,
+    event: AnySyncRoomEvent,
+) -> AnySyncRoomEvent {
+    if let AnySyncRoomEvent::Message(AnySyncMessageEvent::RoomEncrypted(encrypted_event)) = &event {
+        match client.decrypt_room_event(encrypted_event, room_id).await {
+            Ok(raw_event) => match raw_event.deserialize() {
+                Ok(decrypted_event) => return decrypted_event,
+                Err(e) => error!(
+                    "Failed to deserialize decrypted event {}: {}",
+                    encrypted_event.event_id, e
+                ),
+            },
+            Err(e) => warn!(
+                "Unable to decrypt event {}: {}",
+                encrypted_event.event_id, e
+            ),
+        }
+    }
+    event
+}
+
+/// Without encryption support every encrypted event is shown as a placeholder
+#[cfg(not(feature = "encryption"))]
+pub async fn decrypt_sync_event(
+    _client: &Client,
+    _room_id: &RoomId,
+    event: AnySyncRoomEvent,
+) -> AnySyncRoomEvent {
+    event
+}
+
+/// Makes sure we don't send plain text into an encrypted room.
+///
+/// Without encryption support the SDK sends every message unencrypted.
+pub async fn check_can_send(client: &Client, room_id: &RoomId) -> Result<(), MatrixError> {
+    if cfg!(feature = "encryption") {
+        return Ok(());
+    }
+    match client.get_joined_room(room_id).await {
+        Some(room) if room.read().await.is_encrypted() => Err(MatrixError::EncryptionUnsupported),
+        _ => Ok(()),
+    }
+}
+
+/// Exports all inbound group sessions in the passphrase protected Megolm key export format
+#[cfg(feature = "encryption")]
+pub async fn export_room_keys(client: &Client, passphrase: &str) -> Result<String, MatrixError> {
+    let keys = client
+        .export_room_keys(|_| true)
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+    encrypt_key_export(&keys, passphrase, KEY_EXPORT_ROUNDS)
+        .map_err(|e| MatrixError::SDKError(e.to_string()))
+}
+
+/// Imports a Megolm key export into the crypto store and returns the number of imported sessions
+#[cfg(feature = "encryption")]
+pub async fn import_room_keys(
+    client: &Client,
+    export: &str,
+    passphrase: &str,
+) -> Result<usize, MatrixError> {
+    let keys = decrypt_key_export(Cursor::new(export), passphrase)
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+    client
+        .import_room_keys(keys)
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))
+}
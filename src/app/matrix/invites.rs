@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use matrix_sdk::{
+    events::{AnyStrippedStateEvent, StrippedStateEvent},
+    identifiers::{RoomAliasId, RoomId, UserId},
+};
+use serde::{Deserialize, Serialize};
+
+/// What the stripped state of an invite tells about the room before joining it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Invite {
+    pub room_id: RoomId,
+    pub inviter: Option<UserId>,
+    pub inviter_name: Option<String>,
+    pub room_name: Option<String>,
+    pub canonical_alias: Option<RoomAliasId>,
+    pub topic: Option<String>,
+    /// The inviter marked the room as a direct chat
+    pub is_direct: bool,
+}
+
+impl Invite {
+    pub fn from_stripped_state(
+        room_id: RoomId,
+        own_user_id: &UserId,
+        events: &[AnyStrippedStateEvent],
+    ) -> Self {
+        let mut invite = Invite {
+            room_id,
+            inviter: None,
+            inviter_name: None,
+            room_name: None,
+            canonical_alias: None,
+            topic: None,
+            is_direct: false,
+        };
+
+        for event in events {
+            match event {
+                AnyStrippedStateEvent::RoomName(event) => {
+                    invite.room_name = event.content.name().map(ToString::to_string);
+                }
+                AnyStrippedStateEvent::RoomCanonicalAlias(event) => {
+                    invite.canonical_alias = event.content.alias.clone();
+                }
+                AnyStrippedStateEvent::RoomTopic(event) => {
+                    invite.topic = Some(event.content.topic.clone()).filter(|t| !t.is_empty());
+                }
+                AnyStrippedStateEvent::RoomMember(StrippedStateEvent {
+                    content,
+                    sender,
+                    state_key,
+                    ..
+                }) if state_key == own_user_id.as_str() => {
+                    invite.inviter = Some(sender.clone());
+                    invite.is_direct = content.is_direct.unwrap_or(false);
+                }
+                _ => {}
+            }
+        }
+
+        // The member event of the inviter is only there if the server includes it
+        if let Some(inviter) = &invite.inviter {
+            invite.inviter_name = events.iter().find_map(|event| match event {
+                AnyStrippedStateEvent::RoomMember(event) if event.state_key == inviter.as_str() => {
+                    event.content.displayname.clone()
+                }
+                _ => None,
+            });
+        }
+
+        invite
+    }
+
+    /// Same order of fallbacks the SDK uses for joined rooms
+    pub fn display_name(&self) -> String {
+        if let Some(name) = &self.room_name {
+            return name.clone();
+        }
+        if let Some(alias) = &self.canonical_alias {
+            return alias.to_string();
+        }
+        if let Some(name) = &self.inviter_name {
+            return name.clone();
+        }
+        match &self.inviter {
+            Some(inviter) => inviter.to_string(),
+            None => self.room_id.to_string(),
+        }
+    }
+}
+
+/// Invites we haven't answered yet, kept for room lists which get created after the sync
+#[derive(Clone, Debug, Default)]
+pub struct PendingInvites {
+    invites: Arc<Mutex<HashMap<RoomId, Invite>>>,
+}
+
+impl PendingInvites {
+    pub fn all(&self) -> Vec<Invite> {
+        self.invites.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn insert(&self, invite: Invite) {
+        self.invites
+            .lock()
+            .unwrap()
+            .insert(invite.room_id.clone(), invite);
+    }
+
+    pub fn remove(&self, room_id: &RoomId) {
+        self.invites.lock().unwrap().remove(room_id);
+    }
+}
@@ -1,6 +1,17 @@
 use crate::errors::{Field, MatrixError};
 use log::*;
-use matrix_sdk::{Client, ClientConfig, Session};
+use matrix_sdk::{
+    api::{
+        r0::session::{
+            get_login_types::{self, LoginType},
+            login::{self, LoginInfo},
+            logout, logout_all,
+        },
+        unversioned::{discover_homeserver, get_supported_versions},
+    },
+    identifiers::UserId,
+    Client, ClientConfig, Session,
+};
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use url::Url;
@@ -14,6 +25,13 @@ pub struct SessionStore {
     pub(crate) homeserver_url: String,
 }
 
+/// The login flows a homeserver supports which Daydream knows about
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct LoginFlows {
+    pub password: bool,
+    pub sso: bool,
+}
+
 pub fn login(
     session: Option<&SessionStore>,
     homeserver: Option<&String>,
@@ -73,3 +91,100 @@ fn restore_client(session: &SessionStore) -> Result<Client, MatrixError> {
         Err(e) => Err(MatrixError::UrlParseError(e.to_string())),
     }
 }
+
+/// Finds the homeserver of a Matrix ID via `/.well-known/matrix/client` and makes sure it is one
+pub async fn discover_homeserver(mxid: &str) -> Result<String, MatrixError> {
+    let user_id = UserId::try_from(mxid).map_err(|_| MatrixError::InvalidMXID(mxid.to_string()))?;
+    let server_name = user_id.server_name().to_string();
+
+    let server_url = Url::parse(&format!("https://{}", server_name))
+        .map_err(|_| MatrixError::InvalidMXID(mxid.to_string()))?;
+    let discovery_client =
+        Client::new(server_url).map_err(|e| MatrixError::SDKError(e.to_string()))?;
+    let response = discovery_client
+        .send(discover_homeserver::Request {})
+        .await
+        .map_err(|_| MatrixError::DiscoveryFailed(server_name))?;
+
+    let base_url = response.homeserver.base_url;
+    let homeserver_url =
+        Url::parse(&base_url).map_err(|_| MatrixError::DiscoveryInvalid(base_url.clone()))?;
+    let homeserver_client =
+        Client::new(homeserver_url).map_err(|e| MatrixError::SDKError(e.to_string()))?;
+    homeserver_client
+        .send(get_supported_versions::Request {})
+        .await
+        .map_err(|_| MatrixError::HomeserverInvalid(base_url.clone()))?;
+
+    info!("Discovered homeserver {} for {}", base_url, mxid);
+    Ok(base_url)
+}
+
+/// Invalidates the access token of this device or, if `all_devices` is set, of every device
+pub async fn logout(client: &Client, all_devices: bool) -> Result<(), MatrixError> {
+    let result = if all_devices {
+        client.send(logout_all::Request {}).await.map(|_| ())
+    } else {
+        client.send(logout::Request {}).await.map(|_| ())
+    };
+    result.map_err(|e| MatrixError::SDKError(e.to_string()))
+}
+
+pub async fn get_login_flows(client: &Client) -> Result<LoginFlows, MatrixError> {
+    let response = client
+        .send(get_login_types::Request {})
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+
+    let mut flows = LoginFlows::default();
+    for flow in response.flows {
+        match flow {
+            LoginType::Password => flows.password = true,
+            LoginType::Sso => flows.sso = true,
+            _ => {}
+        }
+    }
+    Ok(flows)
+}
+
+/// The homeserver page that starts the SSO flow and comes back to `redirect_url` with a `loginToken`
+pub fn sso_redirect_url(homeserver: &str, redirect_url: &str) -> Result<Url, MatrixError> {
+    let mut url = Url::parse(homeserver).map_err(|e| MatrixError::UrlParseError(e.to_string()))?;
+    url.set_path("_matrix/client/r0/login/sso/redirect");
+    url.query_pairs_mut()
+        .clear()
+        .append_pair("redirectUrl", redirect_url);
+    Ok(url)
+}
+
+/// Exchanges the `loginToken` we got back from the SSO flow for an access token using `m.login.token`
+pub async fn login_with_token(client: &Client, token: &str) -> Result<SessionStore, MatrixError> {
+    let response = client
+        .send(login::Request {
+            login_info: LoginInfo::Token {
+                token: token.to_string(),
+            },
+            device_id: None,
+            initial_device_display_name: Some("Daydream".to_string()),
+        })
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+
+    // Sending the request ourselves doesn't log the client in
+    let session = Session {
+        access_token: response.access_token.clone(),
+        user_id: response.user_id.clone(),
+        device_id: response.device_id.clone(),
+    };
+    client
+        .restore_login(session)
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+
+    Ok(SessionStore {
+        access_token: response.access_token,
+        user_id: response.user_id.to_string(),
+        device_id: response.device_id.into(),
+        homeserver_url: client.homeserver().to_string(),
+    })
+}
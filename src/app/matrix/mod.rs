@@ -1,35 +1,92 @@
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use futures::future::{abortable, AbortHandle};
 use log::*;
+#[cfg(feature = "encryption")]
+use matrix_sdk::Sas;
 use matrix_sdk::{
     api::r0::{
         filter::RoomEventFilter,
         message::{
             get_message_events::Direction, get_message_events::Request as GetMessagesRequest,
         },
+        presence::set_presence,
+        receipt::create_receipt::{self, ReceiptType},
+        redact::redact_event,
+        room::get_room_event,
+        tag::{create_tag, delete_tag},
+        typing::create_typing_event::Typing,
     },
     events::{
         room::message::{FormattedBody, MessageEventContent, TextMessageEventContent},
-        AnyMessageEvent, AnyRoomEvent, AnySyncMessageEvent,
+        tag::TagInfo,
+        AnyMessageEvent, AnyMessageEventContent, AnyRoomEvent, AnySyncMessageEvent,
+        AnySyncRoomEvent,
     },
-    identifiers::RoomId,
+    identifiers::{EventId, RoomId, RoomIdOrAliasId, ServerName, UserId},
     js_int::uint,
     locks::RwLock,
+    presence::PresenceState,
     Client, Raw, Room,
 };
 use pulldown_cmark::{html, Options, Parser};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen_futures::spawn_local;
 use yew::worker::*;
 
 use crate::app::matrix::types::{get_media_download_url, get_video_media_download_url};
-use crate::errors::MatrixError;
-use login::{login, SessionStore};
+use crate::errors::{Field, MatrixError};
+use crate::utils::ruma::{AnyMessageEventExt, AnySyncMessageEventExt};
+use account::AccountId;
+use backup::{BackupStatus, KeyBackup};
+use create_room::{create_room, NewRoom};
+use directory::{public_rooms, search_users, PublicRoomsPage, PublicRoomsQuery, UserSearchResult};
+use encryption::{check_can_send, decrypt_sync_event};
+#[cfg(feature = "encryption")]
+use encryption::{export_room_keys, import_room_keys};
+use invites::{Invite, PendingInvites};
+use login::{
+    discover_homeserver, get_login_flows, login, login_with_token, logout, LoginFlows, SessionStore,
+};
+use presence::PresenceInfo;
+use receipts::{ReadReceipt, UnreadCounts};
+use register::{
+    register, registered_session, request_email_token, EmailToken, RegistrationAuth,
+    RegistrationData,
+};
+use relations::{message_with_relation, reaction_content, MessageRelation, RelatedEvent};
+use tags::RoomTags;
+#[cfg(feature = "encryption")]
+use trust::room_trust;
+use trust::RoomTrust;
+#[cfg(feature = "encryption")]
+use uiaa::password_auth;
+use uiaa::uiaa_info;
+#[cfg(feature = "encryption")]
+use verification::VerificationState;
+use verification::{DeviceInfo, VerificationInfo};
 
+pub mod account;
+pub mod backup;
+pub mod create_room;
+pub mod directory;
+mod encryption;
+pub mod invites;
 pub mod login;
+pub mod presence;
+pub mod receipts;
+pub mod register;
+pub mod relations;
 mod sync;
+pub mod tags;
+pub mod trust;
 pub mod types;
+mod uiaa;
+pub mod verification;
 
 #[derive(Default, Clone, Debug)]
 pub struct MatrixClient {
@@ -41,11 +98,23 @@ pub struct MatrixClient {
 #[derive(Clone, Debug)]
 pub struct MatrixAgent {
     link: AgentLink<MatrixAgent>,
+    subscribers: Arc<Mutex<HashSet<HandlerId>>>,
+    accounts: HashMap<AccountId, Account>,
+}
+
+/// The client and sync loop of a single account
+#[derive(Clone, Debug)]
+struct Account {
+    id: AccountId,
+    link: AgentLink<MatrixAgent>,
+    subscribers: Arc<Mutex<HashSet<HandlerId>>>,
     matrix_state: MatrixClient,
     matrix_client: Option<Client>,
-    // TODO make arc mutex :(
-    subscribers: HashSet<HandlerId>,
     session: Option<SessionStore>,
+    syncing: Option<AbortHandle>,
+    key_backup: KeyBackup,
+    room_tags: RoomTags,
+    invites: PendingInvites,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -55,11 +124,51 @@ pub enum Request {
     SetPassword(String),
     SetSession(SessionStore),
     Login,
+    LoginWithToken(String),
+    Logout,
+    LogoutAllDevices,
+    GetLoginFlows,
+    DiscoverHomeserver(String),
+    Register(RegistrationData),
+    RequestRegistrationEmail(String),
     GetLoggedIn,
     GetOldMessages((RoomId, Option<String>)),
+    GetEvent((RoomId, EventId)),
     StartSync,
+    GetJoinedRooms,
     GetJoinedRoom(RoomId),
-    SendMessage((RoomId, String)),
+    /// Joins by room id or alias, through the given servers if our homeserver isn't in the room
+    JoinRoom((RoomIdOrAliasId, Vec<Box<ServerName>>)),
+    RejectInvite(RoomId),
+    LeaveRoom(RoomId),
+    CreateRoom(NewRoom),
+    SearchUsers(String),
+    GetPublicRooms(PublicRoomsQuery),
+    /// Leaves the room if needed and removes it from the room history
+    ForgetRoom(RoomId),
+    SendMessage((RoomId, String, Option<MessageRelation>)),
+    Redact((RoomId, EventId, Option<String>)),
+    React((RoomId, EventId, String)),
+    SendReadReceipt((RoomId, EventId)),
+    /// `Some(timeout)` starts or renews the typing notice, `None` stops it
+    SetTyping((RoomId, Option<Duration>)),
+    SetPresence((PresenceState, Option<String>)),
+    /// Adds (`true`) or removes (`false`) a tag like `m.favourite`
+    SetRoomTag((RoomId, String, bool)),
+    GetUserDevices(UserId),
+    StartVerification((UserId, String)),
+    AcceptVerification(String),
+    ConfirmVerification(String),
+    CancelVerification(String),
+    ExportKeys(String),
+    ImportKeys((String, String)),
+    CreateKeyBackup,
+    GetKeyBackupStatus,
+    /// Restores the room keys from the backup with the recovery key
+    RestoreKeyBackup(String),
+    DismissKeyBackupRestore,
+    BootstrapCrossSigning(Option<(String, String)>),
+    GetRoomTrust(RoomId),
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -67,50 +176,142 @@ pub enum Request {
 pub enum Response {
     Error(MatrixError),
     LoggedIn(bool),
+    LoggedOut,
+    LoginFlows(LoginFlows),
+    HomeserverDiscovered(String),
+    RegistrationAuth(RegistrationAuth),
+    RegistrationEmailSent(EmailToken),
     // TODO properly handle sync events
-    Sync((RoomId, Raw<AnySyncMessageEvent>)),
+    Sync((RoomId, Raw<AnySyncRoomEvent>)),
     JoinedRoomSync(RoomId),
     SyncPing,
-    OldMessages((RoomId, Vec<Raw<AnyMessageEvent>>)),
+    OldMessages((RoomId, Vec<Raw<AnyRoomEvent>>)),
+    Relation((RoomId, RelatedEvent)),
+    Event((RoomId, Raw<AnySyncRoomEvent>)),
+    /// The requested event couldn't be fetched or isn't a message
+    EventUnavailable((RoomId, EventId)),
+    ReadReceipts((RoomId, Vec<ReadReceipt>)),
+    /// Sending our read receipt for the event failed
+    ReadReceiptFailed((RoomId, EventId)),
+    /// Everyone who is typing in the room right now
+    Typing((RoomId, Vec<UserId>)),
+    UnreadCounts((RoomId, UnreadCounts)),
+    /// The names of the `m.tag` tags of the room
+    RoomTags((RoomId, Vec<String>)),
+    Presence(PresenceInfo),
     JoinedRoom((RoomId, Room)),
+    Invite(Invite),
+    /// We joined the room, it shows up as `JoinedRoomSync` once the next sync is through
+    RoomJoined(RoomId),
+    InviteRejected(RoomId),
+    /// Joining or rejecting the invite failed, followed by the `Error`
+    InviteFailed(RoomId),
+    /// The room exists, it shows up as `JoinedRoomSync` once the next sync is through
+    RoomCreated(RoomId),
+    /// The results for the search term
+    UserSearchResults((String, Vec<UserSearchResult>)),
+    PublicRooms(PublicRoomsPage),
+    /// We left the room or got removed from it, possibly from another session
+    LeftRoom(RoomId),
     SaveSession(SessionStore),
+    UserDevices((UserId, Vec<DeviceInfo>)),
+    Verification(VerificationInfo),
+    KeysExported(String),
+    KeysImported(usize),
+    KeyBackupCreated(String),
+    KeyBackupStatus(BackupStatus),
+    KeyBackupRestored(usize),
+    CrossSigningAuthRequired(String),
+    CrossSigningReady,
+    RoomTrust((RoomId, RoomTrust)),
 }
 
 #[derive(Debug, Clone)]
 pub enum Msg {
-    OnSyncResponse(Response),
+    OnSyncResponse((AccountId, Response)),
 }
 
 impl Agent for MatrixAgent {
     type Reach = Public<Self>;
     type Message = Msg;
-    type Input = Request;
-    type Output = Response;
+    type Input = (AccountId, Request);
+    type Output = (AccountId, Response);
 
     fn create(link: AgentLink<Self>) -> Self {
         MatrixAgent {
             link,
-            matrix_state: Default::default(),
-            matrix_client: None,
-            subscribers: HashSet::new(),
-            session: Default::default(),
+            subscribers: Default::default(),
+            accounts: HashMap::new(),
         }
     }
 
     fn update(&mut self, msg: Self::Message) {
         match msg {
-            Msg::OnSyncResponse(resp) => {
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, resp.clone());
+            Msg::OnSyncResponse((account_id, resp)) => {
+                for sub in self.subscribers.lock().unwrap().iter() {
+                    self.link.respond(*sub, (account_id.clone(), resp.clone()));
                 }
             }
         }
     }
 
     fn connected(&mut self, id: HandlerId) {
-        self.subscribers.insert(id);
+        self.subscribers.lock().unwrap().insert(id);
     }
-    fn handle_input(&mut self, msg: Self::Input, _: HandlerId) {
+    fn handle_input(&mut self, (account_id, msg): Self::Input, _: HandlerId) {
+        let logout = matches!(msg, Request::Logout | Request::LogoutAllDevices);
+        let link = self.link.clone();
+        let subscribers = self.subscribers.clone();
+        self.accounts
+            .entry(account_id.clone())
+            .or_insert_with(|| Account::new(account_id.clone(), link, subscribers))
+            .handle_request(msg);
+        if logout {
+            self.accounts.remove(&account_id);
+        }
+    }
+
+    fn disconnected(&mut self, id: HandlerId) {
+        self.subscribers.lock().unwrap().remove(&id);
+    }
+
+    fn name_of_resource() -> &'static str {
+        "worker.js"
+    }
+}
+
+unsafe impl Send for MatrixAgent {}
+
+unsafe impl std::marker::Sync for MatrixAgent {}
+
+impl Account {
+    fn new(
+        id: AccountId,
+        link: AgentLink<MatrixAgent>,
+        subscribers: Arc<Mutex<HashSet<HandlerId>>>,
+    ) -> Self {
+        Account {
+            id,
+            link,
+            subscribers,
+            matrix_state: Default::default(),
+            matrix_client: None,
+            session: None,
+            syncing: None,
+            key_backup: Default::default(),
+            room_tags: Default::default(),
+            invites: Default::default(),
+        }
+    }
+
+    /// Sends the response to every subscriber, tagged with this account
+    fn respond(&self, resp: Response) {
+        for sub in self.subscribers.lock().unwrap().iter() {
+            self.link.respond(*sub, (self.id.clone(), resp.clone()));
+        }
+    }
+
+    fn handle_request(&mut self, msg: Request) {
         match msg {
             Request::SetSession(session) => {
                 self.session = Some(session);
@@ -132,15 +333,31 @@ impl Agent for MatrixAgent {
                 let client = login(session, homeserver);
                 match client {
                     Ok(client) => {
-                        if let Some(_session) = session {
-                            for sub in self.subscribers.iter() {
-                                let resp = Response::LoggedIn(true);
-                                self.link.respond(*sub, resp);
-                            }
+                        // The restored session is already logged in
+                        if session.is_some() {
+                            self.matrix_client = Some(client);
+                            self.respond(Response::LoggedIn(true));
+                            return;
                         }
                         self.matrix_client = Some(client.clone());
-                        let username = self.matrix_state.username.clone().unwrap();
-                        let password = self.matrix_state.password.clone().unwrap();
+                        let username = match self.matrix_state.username.clone() {
+                            Some(username) => username,
+                            None => {
+                                self.respond(Response::Error(MatrixError::MissingFields(
+                                    Field::MXID,
+                                )));
+                                return;
+                            }
+                        };
+                        let password = match self.matrix_state.password.clone() {
+                            Some(password) => password,
+                            None => {
+                                self.respond(Response::Error(MatrixError::MissingFields(
+                                    Field::Password,
+                                )));
+                                return;
+                            }
+                        };
                         let agent = self.clone();
                         spawn_local(async move {
                             // FIXME gracefully handle login errors
@@ -159,60 +376,170 @@ impl Agent for MatrixAgent {
                                         device_id: login_response.device_id.into(),
                                         homeserver_url: client.homeserver().to_string(),
                                     };
-                                    for sub in agent.subscribers.iter() {
-                                        let resp = Response::SaveSession(session_store.clone());
-                                        agent.link.respond(*sub, resp);
-                                        let resp = Response::LoggedIn(true);
-                                        agent.link.respond(*sub, resp);
-                                    }
+                                    agent.respond(Response::SaveSession(session_store));
+                                    agent.respond(Response::LoggedIn(true));
+                                    #[cfg(feature = "encryption")]
+                                    agent.offer_key_restore().await;
                                 }
                                 Err(e) => {
                                     if let matrix_sdk::Error::Reqwest(e) = e {
                                         match e.status() {
                                             None => {
-                                                for sub in agent.subscribers.iter() {
-                                                    let resp = Response::Error(
-                                                        MatrixError::SDKError(e.to_string()),
-                                                    );
-                                                    agent.link.respond(*sub, resp);
-                                                }
+                                                agent.respond(Response::Error(
+                                                    MatrixError::SDKError(e.to_string()),
+                                                ));
                                             }
                                             Some(v) => {
                                                 if v.is_server_error() {
-                                                    for sub in agent.subscribers.iter() {
-                                                        let resp = Response::Error(
-                                                            MatrixError::LoginTimeout,
-                                                        );
-                                                        agent.link.respond(*sub, resp);
-                                                    }
+                                                    agent.respond(Response::Error(
+                                                        MatrixError::LoginTimeout,
+                                                    ));
                                                 } else {
-                                                    for sub in agent.subscribers.iter() {
-                                                        let resp = Response::Error(
-                                                            MatrixError::SDKError(e.to_string()),
-                                                        );
-                                                        agent.link.respond(*sub, resp);
-                                                    }
+                                                    agent.respond(Response::Error(
+                                                        MatrixError::SDKError(e.to_string()),
+                                                    ));
                                                 }
                                             }
                                         }
                                     } else {
-                                        for sub in agent.subscribers.iter() {
-                                            let resp = Response::Error(MatrixError::SDKError(
-                                                e.to_string(),
-                                            ));
-                                            agent.link.respond(*sub, resp);
-                                        }
+                                        agent.respond(Response::Error(MatrixError::SDKError(
+                                            e.to_string(),
+                                        )));
                                     }
                                 }
                             }
                         });
                     }
                     Err(e) => {
-                        for sub in self.subscribers.iter() {
-                            let resp = Response::Error(e.clone());
-                            self.link.respond(*sub, resp);
+                        self.respond(Response::Error(e));
+                    }
+                }
+            }
+            Request::LoginWithToken(token) => {
+                info!("Starting token login");
+                let homeserver = self.matrix_state.homeserver.as_ref();
+                match login(None, homeserver) {
+                    Ok(client) => {
+                        self.matrix_client = Some(client.clone());
+                        let agent = self.clone();
+                        spawn_local(async move {
+                            match login_with_token(&client, &token).await {
+                                Ok(session_store) => {
+                                    agent.respond(Response::SaveSession(session_store));
+                                    agent.respond(Response::LoggedIn(true));
+                                    #[cfg(feature = "encryption")]
+                                    agent.offer_key_restore().await;
+                                }
+                                Err(e) => {
+                                    agent.respond(Response::Error(e));
+                                }
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        self.respond(Response::Error(e));
+                    }
+                }
+            }
+            Request::Register(registration) => {
+                info!("Starting registration");
+                let homeserver = self.matrix_state.homeserver.as_ref();
+                match login(None, homeserver) {
+                    Ok(client) => {
+                        self.matrix_client = Some(client.clone());
+                        let agent = self.clone();
+                        spawn_local(async move {
+                            match register(&client, &registration).await {
+                                Ok(response) => match registered_session(&client, response).await {
+                                    Ok(session_store) => {
+                                        agent.respond(Response::SaveSession(session_store));
+                                        agent.respond(Response::LoggedIn(true));
+                                        #[cfg(feature = "encryption")]
+                                        agent.offer_key_restore().await;
+                                    }
+                                    Err(e) => agent.respond(Response::Error(e)),
+                                },
+                                Err(e) => {
+                                    let resp = match uiaa_info(&e) {
+                                        // The server wants us to complete another stage first
+                                        Some(info) => {
+                                            Response::RegistrationAuth(RegistrationAuth::new(info))
+                                        }
+                                        None => {
+                                            Response::Error(MatrixError::SDKError(e.to_string()))
+                                        }
+                                    };
+                                    agent.respond(resp);
+                                }
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        self.respond(Response::Error(e));
+                    }
+                }
+            }
+            Request::RequestRegistrationEmail(email) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let resp = match request_email_token(client, &email).await {
+                        Ok(token) => Response::RegistrationEmailSent(token),
+                        Err(e) => Response::Error(MatrixError::SDKError(e.to_string())),
+                    };
+                    agent.respond(resp);
+                });
+            }
+            Request::DiscoverHomeserver(mxid) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let resp = match discover_homeserver(&mxid).await {
+                        Ok(homeserver) => Response::HomeserverDiscovered(homeserver),
+                        Err(e) => Response::Error(e),
+                    };
+                    agent.respond(resp);
+                });
+            }
+            Request::Logout | Request::LogoutAllDevices => {
+                let all_devices = matches!(msg, Request::LogoutAllDevices);
+                info!("Logging out");
+                if let Some(sync) = self.syncing.take() {
+                    sync.abort();
+                }
+
+                let client = self.matrix_client.take();
+                self.session = None;
+                self.matrix_state = Default::default();
+                self.key_backup = Default::default();
+
+                let agent = self.clone();
+                spawn_local(async move {
+                    if let Some(client) = client {
+                        // Forget the session locally even if the server doesn't know it anymore
+                        if let Err(e) = logout(&client, all_devices).await {
+                            error!("Failed to log out on the server: {}", e);
+                            agent.respond(Response::Error(e));
                         }
                     }
+                    agent.respond(Response::LoggedOut);
+                });
+            }
+            Request::GetLoginFlows => {
+                let homeserver = self.matrix_state.homeserver.as_ref();
+                match login(None, homeserver) {
+                    Ok(client) => {
+                        let agent = self.clone();
+                        spawn_local(async move {
+                            let resp = match get_login_flows(&client).await {
+                                Ok(flows) => Response::LoginFlows(flows),
+                                Err(e) => Response::Error(e),
+                            };
+                            agent.respond(resp);
+                        });
+                    }
+                    Err(e) => {
+                        self.respond(Response::Error(e));
+                    }
                 }
             }
             Request::GetLoggedIn => {
@@ -231,28 +558,33 @@ impl Agent for MatrixAgent {
                             if !logged_in && session.is_some() {
                                 error!("Not logged in but got session");
                             } else {
-                                for sub in agent.subscribers.iter() {
-                                    let resp = Response::LoggedIn(logged_in);
-                                    agent.link.respond(*sub, resp);
-                                }
+                                agent.respond(Response::LoggedIn(logged_in));
                             }
                         });
                     }
                     Err(e) => {
                         error!("Got no client: {:?}", e);
-                        for sub in self.subscribers.iter() {
-                            let resp = Response::Error(e.clone());
-                            self.link.respond(*sub, resp);
-                        }
+                        self.respond(Response::Error(e));
                     }
                 }
             }
             Request::StartSync => {
+                // The App asks every time the account logged in
+                if self.syncing.is_some() {
+                    return;
+                }
+
                 // Always clone agent after having tried to login!
                 let agent = self.clone();
-                spawn_local(async move {
+                let (sync, abort_handle) = abortable(async move {
                     agent.start_sync().await;
                 });
+                self.syncing = Some(abort_handle);
+                spawn_local(async move {
+                    if sync.await.is_err() {
+                        info!("Sync stopped");
+                    }
+                });
             }
             Request::GetOldMessages((room_id, from)) => {
                 let agent = self.clone();
@@ -270,7 +602,19 @@ impl Agent for MatrixAgent {
                     let mut req =
                         GetMessagesRequest::new(&room_id, &sync_token, Direction::Backward);
                     let filter = RoomEventFilter {
-                        types: Some(vec!["m.room.message".to_string()]),
+                        types: Some(vec![
+                            "m.room.message".to_string(),
+                            "m.room.encrypted".to_string(),
+                            "m.room.member".to_string(),
+                            "m.room.name".to_string(),
+                            "m.room.topic".to_string(),
+                            "m.room.avatar".to_string(),
+                            "m.room.power_levels".to_string(),
+                            "m.room.encryption".to_string(),
+                            "m.room.create".to_string(),
+                            "m.room.redaction".to_string(),
+                            "m.reaction".to_string(),
+                        ]),
                         ..Default::default()
                     };
                     // TODO find better way than cloning
@@ -287,17 +631,68 @@ impl Agent for MatrixAgent {
                         .unwrap();
                     // TODO save end point for future loading
 
-                    let mut wrapped_messages: Vec<Raw<AnyMessageEvent>> = Vec::new();
-                    let chunk_iter: Vec<Raw<AnyRoomEvent>> = messsages.chunk;
-                    let (oks, _): (Vec<_>, Vec<_>) = chunk_iter
+                    let mut wrapped_messages: Vec<Raw<AnyRoomEvent>> = Vec::new();
+                    let mut related_events: Vec<RelatedEvent> = Vec::new();
+                    // The raw events are kept around as the relations are only in the json
+                    let deserialized_events: Vec<(&Raw<AnyRoomEvent>, AnyRoomEvent)> = messsages
+                        .chunk
                         .iter()
-                        .map(|event| event.deserialize())
-                        .partition(Result::is_ok);
+                        .filter_map(|raw| Some((raw, raw.deserialize().ok()?)))
+                        .collect();
+
+                    for (raw_event, event) in deserialized_events.into_iter().rev() {
+                        // Megolm events need to be decrypted before we can look at their content
+                        let event = if let AnyRoomEvent::Message(
+                            encrypted_event @ AnyMessageEvent::RoomEncrypted(_),
+                        ) = event
+                        {
+                            let sync_event =
+                                AnySyncRoomEvent::Message(encrypted_event.without_room_id());
+                            match decrypt_sync_event(
+                                agent.matrix_client.as_ref().unwrap(),
+                                &room_id,
+                                sync_event,
+                            )
+                            .await
+                            {
+                                AnySyncRoomEvent::Message(event) => {
+                                    AnyRoomEvent::Message(event.with_room_id(room_id.clone()))
+                                }
+                                _ => continue,
+                            }
+                        } else {
+                            event
+                        };
+
+                        // Edits and similar are applied to the event they relate to
+                        if let AnyRoomEvent::Message(message) = &event {
+                            let sync_message = message.clone().without_room_id();
+                            if let Some(related) = RelatedEvent::from_raw(raw_event, &sync_message)
+                            {
+                                related_events.push(related);
+                                continue;
+                            }
+                        }
+
+                        // Keep events we can't decrypt so the UI can show a placeholder
+                        if let AnyRoomEvent::Message(
+                            encrypted_event @ AnyMessageEvent::RoomEncrypted(_),
+                        ) = event
+                        {
+                            wrapped_messages
+                                .push(Raw::from(AnyRoomEvent::Message(encrypted_event)));
+                            continue;
+                        }
 
-                    let deserialized_events: Vec<AnyRoomEvent> =
-                        oks.into_iter().map(Result::unwrap).collect();
+                        // The timeline components render those as compact lines or placeholders
+                        if let AnyRoomEvent::State(_)
+                        | AnyRoomEvent::RedactedMessage(_)
+                        | AnyRoomEvent::Message(AnyMessageEvent::RoomRedaction(_)) = event
+                        {
+                            wrapped_messages.push(Raw::from(event));
+                            continue;
+                        }
 
-                    for event in deserialized_events.into_iter().rev() {
                         // TODO deduplicate betweeen this and sync
                         if let AnyRoomEvent::Message(AnyMessageEvent::RoomMessage(mut event)) =
                             event
@@ -346,16 +741,59 @@ impl Agent for MatrixAgent {
                                 event.content = MessageEventContent::Video(video_event);
                             }
 
-                            let serialized_event =
-                                Raw::from(AnyMessageEvent::RoomMessage(event.clone()));
+                            let serialized_event = Raw::from(AnyRoomEvent::Message(
+                                AnyMessageEvent::RoomMessage(event.clone()),
+                            ));
                             wrapped_messages.push(serialized_event);
                         }
                     }
 
-                    for sub in agent.subscribers.iter() {
-                        let resp =
-                            Response::OldMessages((room_id.clone(), wrapped_messages.clone()));
-                        agent.link.respond(*sub, resp);
+                    agent.respond(Response::OldMessages((room_id.clone(), wrapped_messages)));
+                    for related in related_events {
+                        agent.respond(Response::Relation((room_id.clone(), related)));
+                    }
+                });
+            }
+            Request::GetEvent((room_id, event_id)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let request = get_room_event::Request::new(&room_id, &event_id);
+                    let event = match client.send(request).await {
+                        Ok(response) => response.event.deserialize(),
+                        Err(e) => {
+                            error!("Error fetching event {}: {}", event_id, e);
+                            agent.respond(Response::EventUnavailable((room_id, event_id)));
+                            return;
+                        }
+                    };
+                    // Only messages are fetched this way, e.g. the parent of a reply
+                    match event {
+                        Ok(AnyRoomEvent::Message(event)) => {
+                            let event = AnySyncRoomEvent::Message(event.without_room_id());
+                            let event = decrypt_sync_event(client, &room_id, event).await;
+                            agent.respond(Response::Event((room_id, Raw::from(event))));
+                        }
+                        _ => agent.respond(Response::EventUnavailable((room_id, event_id))),
+                    }
+                });
+            }
+            Request::GetJoinedRooms => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = match agent.matrix_client.as_ref() {
+                        Some(client) => client,
+                        None => return,
+                    };
+                    let joined_rooms = client.joined_rooms();
+                    for (room_id, room) in joined_rooms.read().await.iter() {
+                        let clean_room = room.read().await.clone();
+                        agent.respond(Response::JoinedRoom((room_id.clone(), clean_room)));
+                        let tags = agent.room_tags.get(room_id);
+                        agent.respond(Response::RoomTags((room_id.clone(), tags)));
+                    }
+                    for invite in agent.invites.all() {
+                        agent.respond(Response::Invite(invite));
                     }
                 });
             }
@@ -370,15 +808,128 @@ impl Agent for MatrixAgent {
                         .unwrap();
                     let read_clone = room.read().await;
                     let clean_room = (*read_clone).clone();
-                    for sub in agent.subscribers.iter() {
-                        let resp = Response::JoinedRoom((room_id.clone(), clean_room.clone()));
-                        agent.link.respond(*sub, resp);
+                    agent.respond(Response::JoinedRoom((room_id.clone(), clean_room)));
+                    let tags = agent.room_tags.get(&room_id);
+                    agent.respond(Response::RoomTags((room_id, tags)));
+                });
+            }
+            Request::JoinRoom((room, server_names)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match client.join_room_by_id_or_alias(&room, &server_names).await {
+                        Ok(response) => {
+                            agent.invites.remove(&response.room_id);
+                            agent.respond(Response::RoomJoined(response.room_id));
+                        }
+                        Err(e) => {
+                            error!("Error joining {}: {}", room, e);
+                            if let Ok(room_id) = RoomId::try_from(room.to_string()) {
+                                agent.respond(Response::InviteFailed(room_id));
+                            }
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            Request::RejectInvite(room_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match client.leave_room(&room_id).await {
+                        Ok(_) => {
+                            agent.invites.remove(&room_id);
+                            agent.respond(Response::InviteRejected(room_id));
+                        }
+                        Err(e) => {
+                            error!("Error rejecting the invite to {}: {}", room_id, e);
+                            agent.respond(Response::InviteFailed(room_id));
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            Request::CreateRoom(new_room) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match create_room(client, &new_room).await {
+                        Ok(room_id) => agent.respond(Response::RoomCreated(room_id)),
+                        Err(e) => {
+                            error!("Error creating room: {}", e);
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            Request::SearchUsers(search_term) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match search_users(client, &search_term).await {
+                        Ok(results) => {
+                            agent.respond(Response::UserSearchResults((search_term, results)))
+                        }
+                        // Typing @user:server still works without the directory
+                        Err(e) => warn!("Error searching for users: {}", e),
+                    }
+                });
+            }
+            Request::GetPublicRooms(query) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match public_rooms(client, query).await {
+                        Ok(page) => agent.respond(Response::PublicRooms(page)),
+                        Err(e) => {
+                            error!("Error loading the room directory: {}", e);
+                            agent.respond(Response::Error(e));
+                        }
                     }
                 });
             }
-            Request::SendMessage((room_id, raw_message)) => {
+            Request::LeaveRoom(room_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match client.leave_room(&room_id).await {
+                        Ok(_) => agent.respond(Response::LeftRoom(room_id)),
+                        Err(e) => {
+                            error!("Error leaving {}: {}", room_id, e);
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            Request::ForgetRoom(room_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    // Only left rooms can be forgotten
+                    if client.get_joined_room(&room_id).await.is_some() {
+                        if let Err(e) = client.leave_room(&room_id).await {
+                            error!("Error leaving {}: {}", room_id, e);
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                            return;
+                        }
+                    }
+                    match client.forget_room_by_id(&room_id).await {
+                        Ok(_) => agent.respond(Response::LeftRoom(room_id)),
+                        Err(e) => {
+                            error!("Error forgetting {}: {}", room_id, e);
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            Request::SendMessage((room_id, raw_message, relation)) => {
                 let client = self.matrix_client.clone().unwrap();
+                let agent = self.clone();
                 spawn_local(async move {
+                    if let Err(e) = check_can_send(&client, &room_id).await {
+                        agent.respond(Response::Error(e));
+                        return;
+                    }
                     let replacer = gh_emoji::Replacer::new();
                     let message = replacer.replace_all(raw_message.as_str());
 
@@ -392,46 +943,411 @@ impl Agent for MatrixAgent {
                     formatted_message = formatted_message.replace("<p>", "").replace("</p>", "");
                     formatted_message.pop();
 
-                    let content = if formatted_message == message {
-                        MessageEventContent::Text(TextMessageEventContent::plain(message))
+                    let text_content = if formatted_message == message {
+                        TextMessageEventContent::plain(message)
                     } else {
-                        MessageEventContent::Text(TextMessageEventContent {
+                        TextMessageEventContent {
                             body: message.to_string(),
                             relates_to: None,
                             formatted: Some(FormattedBody::html(formatted_message)),
-                        })
+                        }
                     };
-                    if let Err(e) = client.room_send(&room_id, content, None).await {
+                    let content = match &relation {
+                        Some(relation) => message_with_relation(&room_id, text_content, relation),
+                        None => AnyMessageEventContent::RoomMessage(MessageEventContent::Text(
+                            text_content,
+                        )),
+                    };
+                    // The SDK encrypts the content for us if the room has `m.room.encryption` set
+                    match client.room_send(&room_id, content, None).await {
+                        // Sending might have created a new session that needs to be backed up
+                        #[cfg(feature = "encryption")]
+                        Ok(_) => agent.key_backup.keys_changed(),
+                        #[cfg(not(feature = "encryption"))]
+                        Ok(_) => {}
                         // TODO show error in UI or try again if possible
-                        error!("Error sending message: {}", e);
+                        Err(e) => error!("Error sending message: {}", e),
                     }
                 });
             }
-        }
-    }
-
-    fn disconnected(&mut self, id: HandlerId) {
-        self.subscribers.remove(&id);
-    }
+            Request::Redact((room_id, event_id, reason)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let txn_id: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+                    let request = redact_event::Request {
+                        room_id: &room_id,
+                        event_id: &event_id,
+                        txn_id: &txn_id,
+                        reason: reason.as_deref(),
+                    };
+                    // The redaction itself arrives with the next sync
+                    if let Err(e) = client.send(request).await {
+                        error!("Error redacting {}: {}", event_id, e);
+                        agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                    }
+                });
+            }
+            Request::React((room_id, event_id, key)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    if let Err(e) = check_can_send(client, &room_id).await {
+                        agent.respond(Response::Error(e));
+                        return;
+                    }
+                    let content = reaction_content(&event_id, &key);
+                    if let Err(e) = client.room_send(&room_id, content, None).await {
+                        error!("Error reacting to {}: {}", event_id, e);
+                        agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                    }
+                });
+            }
+            Request::SendReadReceipt((room_id, event_id)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let request = create_receipt::Request {
+                        room_id: &room_id,
+                        receipt_type: ReceiptType::Read,
+                        event_id: &event_id,
+                    };
+                    match client.send(request).await {
+                        // The server resets the counts as well, but only tells us on the next sync
+                        Ok(_) => agent.respond(Response::UnreadCounts((
+                            room_id.clone(),
+                            UnreadCounts::default(),
+                        ))),
+                        // Not worth bothering the user, the timeline sends it again
+                        Err(e) => {
+                            warn!("Error sending read receipt for {}: {}", event_id, e);
+                            agent.respond(Response::ReadReceiptFailed((room_id, event_id)));
+                        }
+                    }
+                });
+            }
+            Request::SetTyping((room_id, timeout)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let user_id = client.user_id().await.unwrap();
+                    let typing = match timeout {
+                        Some(timeout) => Typing::Yes(timeout),
+                        None => Typing::No,
+                    };
+                    if let Err(e) = client.typing_notice(&room_id, &user_id, typing).await {
+                        warn!("Error sending typing notice for {}: {}", room_id, e);
+                    }
+                });
+            }
+            Request::SetPresence((presence, status_msg)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let user_id = client.user_id().await.unwrap();
+                    let request = set_presence::Request {
+                        user_id: &user_id,
+                        presence,
+                        status_msg: status_msg.as_deref(),
+                    };
+                    match client.send(request).await {
+                        // Shown right away instead of waiting for the next sync
+                        Ok(_) => agent.respond(Response::Presence(PresenceInfo {
+                            user_id,
+                            presence,
+                            status_msg,
+                        })),
+                        Err(e) => warn!("Error setting presence: {}", e),
+                    }
+                });
+            }
+            Request::SetRoomTag((room_id, tag, enabled)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let user_id = client.user_id().await.unwrap();
+                    let result = if enabled {
+                        let request = create_tag::Request {
+                            user_id: &user_id,
+                            room_id: &room_id,
+                            tag: &tag,
+                            tag_info: TagInfo::default(),
+                        };
+                        client.send(request).await.map(|_| ())
+                    } else {
+                        let request = delete_tag::Request {
+                            user_id: &user_id,
+                            room_id: &room_id,
+                            tag: &tag,
+                        };
+                        client.send(request).await.map(|_| ())
+                    };
+                    match result {
+                        Ok(()) => {
+                            let tags = agent.room_tags.toggle(&room_id, &tag, enabled);
+                            agent.respond(Response::RoomTags((room_id, tags)));
+                        }
+                        Err(e) => {
+                            error!("Error changing tag {} of {}: {}", tag, room_id, e);
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::GetUserDevices(user_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match client.get_user_devices(&user_id).await {
+                        Ok(devices) => {
+                            let devices: Vec<DeviceInfo> = devices
+                                .devices()
+                                .map(|device| DeviceInfo {
+                                    device_id: device.device_id().to_string(),
+                                    display_name: device.display_name().clone(),
+                                    verified: device.is_trusted(),
+                                })
+                                .collect();
+                            agent.respond(Response::UserDevices((user_id, devices)));
+                        }
+                        Err(e) => {
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::StartVerification((user_id, device_id)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let device = match client.get_device(&user_id, device_id.as_str().into()).await
+                    {
+                        Some(device) => device,
+                        None => {
+                            error!("Unknown device {} of {}", device_id, user_id);
+                            return;
+                        }
+                    };
+                    match device.start_verification().await {
+                        Ok(sas) => {
+                            let info = VerificationInfo::new(&sas, VerificationState::Started);
+                            agent.respond(Response::Verification(info));
+                        }
+                        Err(e) => {
+                            agent.respond(Response::Error(MatrixError::SDKError(e.to_string())));
+                        }
+                    }
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::AcceptVerification(flow_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    if let Some(sas) = agent.get_verification(&flow_id).await {
+                        if let Err(e) = sas.accept().await {
+                            error!("Error accepting verification: {}", e);
+                        }
+                    }
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::ConfirmVerification(flow_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    if let Some(sas) = agent.get_verification(&flow_id).await {
+                        if let Err(e) = sas.confirm().await {
+                            error!("Error confirming verification: {}", e);
+                            return;
+                        }
+                        // The flow is only done once the other side confirmed as well
+                        let state = if sas.is_done() {
+                            VerificationState::Done
+                        } else {
+                            VerificationState::Confirmed
+                        };
+                        let info = VerificationInfo::new(&sas, state);
+                        agent.respond(Response::Verification(info));
+                    }
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::CancelVerification(flow_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    if let Some(sas) = agent.get_verification(&flow_id).await {
+                        if let Err(e) = sas.cancel().await {
+                            error!("Error cancelling verification: {}", e);
+                        }
+                        let info = VerificationInfo::new(&sas, VerificationState::Cancelled);
+                        agent.respond(Response::Verification(info));
+                    }
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::ExportKeys(passphrase) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let resp = match export_room_keys(client, &passphrase).await {
+                        Ok(export) => Response::KeysExported(export),
+                        Err(e) => Response::Error(e),
+                    };
+                    agent.respond(resp);
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::CreateKeyBackup => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let resp = match agent.key_backup.create(client).await {
+                        Ok(recovery_key) => Response::KeyBackupCreated(recovery_key),
+                        Err(e) => {
+                            agent.respond(Response::Error(e));
+                            return;
+                        }
+                    };
+                    agent.respond(resp);
 
-    fn name_of_resource() -> &'static str {
-        "worker.js"
+                    // Directly upload the keys we already have
+                    let resp = match agent.key_backup.upload_new_keys(client).await {
+                        Ok(status) => Response::KeyBackupStatus(status),
+                        Err(e) => Response::Error(e),
+                    };
+                    agent.respond(resp);
+                });
+            }
+            Request::GetKeyBackupStatus => {
+                let resp = Response::KeyBackupStatus(self.key_backup.status());
+                self.respond(resp);
+            }
+            #[cfg(feature = "encryption")]
+            Request::RestoreKeyBackup(recovery_key) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    match agent.key_backup.restore(client, &recovery_key).await {
+                        Ok(count) => {
+                            agent.respond(Response::KeyBackupRestored(count));
+                            agent.respond(Response::KeyBackupStatus(agent.key_backup.status()));
+                        }
+                        Err(e) => agent.respond(Response::Error(e)),
+                    }
+                });
+            }
+            Request::DismissKeyBackupRestore => {
+                self.key_backup.dismiss_restore();
+                let resp = Response::KeyBackupStatus(self.key_backup.status());
+                self.respond(resp);
+            }
+            #[cfg(feature = "encryption")]
+            Request::BootstrapCrossSigning(auth) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let user_id = client.user_id().await.unwrap();
+                    let auth_data = auth.as_ref().map(|(session, password)| {
+                        password_auth(session, user_id.as_str(), password)
+                    });
+                    let resp = match client.bootstrap_cross_signing(auth_data).await {
+                        Ok(()) => Response::CrossSigningReady,
+                        Err(e) => match uiaa_info(&e).and_then(|info| info.session.clone()) {
+                            // The server wants us to confirm with our password first
+                            Some(session) => Response::CrossSigningAuthRequired(session),
+                            None => Response::Error(MatrixError::SDKError(e.to_string())),
+                        },
+                    };
+                    agent.respond(resp);
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::GetRoomTrust(room_id) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let room = match client.get_joined_room(&room_id).await {
+                        Some(room) => room,
+                        None => return,
+                    };
+                    let trust = {
+                        let room = room.read().await;
+                        room_trust(client, &room).await
+                    };
+                    agent.respond(Response::RoomTrust((room_id, trust)));
+                });
+            }
+            #[cfg(feature = "encryption")]
+            Request::ImportKeys((export, passphrase)) => {
+                let agent = self.clone();
+                spawn_local(async move {
+                    let client = agent.matrix_client.as_ref().unwrap();
+                    let resp = match import_room_keys(client, &export, &passphrase).await {
+                        Ok(count) => Response::KeysImported(count),
+                        Err(e) => Response::Error(e),
+                    };
+                    agent.respond(resp);
+                });
+            }
+            // The UI doesn't offer these without encryption support
+            #[cfg(not(feature = "encryption"))]
+            Request::GetUserDevices(_)
+            | Request::StartVerification(_)
+            | Request::AcceptVerification(_)
+            | Request::ConfirmVerification(_)
+            | Request::CancelVerification(_)
+            | Request::ExportKeys(_)
+            | Request::ImportKeys(_)
+            | Request::CreateKeyBackup
+            | Request::RestoreKeyBackup(_)
+            | Request::BootstrapCrossSigning(_)
+            | Request::GetRoomTrust(_) => {
+                warn!("Daydream was built without encryption support");
+            }
+        }
     }
-}
 
-unsafe impl Send for MatrixAgent {}
-
-unsafe impl std::marker::Sync for MatrixAgent {}
-
-impl MatrixAgent {
     async fn start_sync(&self) {
+        let id = self.id.clone();
         let sync = sync::Sync {
             matrix_client: self.matrix_client.clone().unwrap(),
-            callback: self.link.callback(Msg::OnSyncResponse),
+            callback: self
+                .link
+                .callback(move |resp| Msg::OnSyncResponse((id.clone(), resp))),
+            sync_number: Default::default(),
+            #[cfg(feature = "encryption")]
+            key_backup: self.key_backup.clone(),
+            room_tags: self.room_tags.clone(),
+            invites: self.invites.clone(),
         };
         sync.start_sync().await;
     }
 
+    /// A new session has none of the room keys yet, so we offer to restore them from the backup
+    #[cfg(feature = "encryption")]
+    async fn offer_key_restore(&self) {
+        let client = self.matrix_client.as_ref().unwrap();
+        match self.key_backup.check_restore(client).await {
+            Ok(()) => self.respond(Response::KeyBackupStatus(self.key_backup.status())),
+            Err(e) => error!("Failed to look up the key backup: {}", e),
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    async fn get_verification(&self, flow_id: &str) -> Option<Sas> {
+        let verification = self
+            .matrix_client
+            .as_ref()
+            .unwrap()
+            .get_verification(flow_id)
+            .await;
+        if verification.is_none() {
+            error!("Unknown verification flow: {}", flow_id);
+        }
+        verification
+    }
+
     async fn get_logged_in(&self) -> bool {
         if self.matrix_client.is_none() {
             return false;
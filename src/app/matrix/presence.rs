@@ -0,0 +1,31 @@
+use matrix_sdk::{
+    events::presence::PresenceEvent, identifiers::UserId, presence::PresenceState, RoomMember,
+};
+use serde::{Deserialize, Serialize};
+
+/// Whether a user is online and what they want others to know about it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PresenceInfo {
+    pub user_id: UserId,
+    pub presence: PresenceState,
+    pub status_msg: Option<String>,
+}
+
+impl PresenceInfo {
+    pub fn from_event(event: PresenceEvent) -> Self {
+        PresenceInfo {
+            user_id: event.sender,
+            presence: event.content.presence,
+            status_msg: event.content.status_msg.filter(|msg| !msg.is_empty()),
+        }
+    }
+
+    /// The presence the SDK stored with the member, `None` if the server never sent one
+    pub fn from_member(member: &RoomMember) -> Option<Self> {
+        Some(PresenceInfo {
+            user_id: member.user_id.clone(),
+            presence: member.presence?,
+            status_msg: member.status_msg.clone().filter(|msg| !msg.is_empty()),
+        })
+    }
+}
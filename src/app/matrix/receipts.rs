@@ -0,0 +1,61 @@
+use std::time::SystemTime;
+
+use matrix_sdk::{
+    api::r0::sync::sync_events::UnreadNotificationsCount,
+    events::receipt::ReceiptEventContent,
+    identifiers::{EventId, UserId},
+    Room,
+};
+use serde::{Deserialize, Serialize};
+
+/// The latest event a user has read in a room
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReadReceipt {
+    pub user_id: UserId,
+    pub event_id: EventId,
+    pub ts: Option<SystemTime>,
+}
+
+/// Flattens an `m.receipt` event into one `m.read` receipt per user
+pub fn read_receipts(content: &ReceiptEventContent) -> Vec<ReadReceipt> {
+    content
+        .iter()
+        .filter_map(|(event_id, receipts)| Some((event_id, receipts.read.as_ref()?)))
+        .flat_map(|(event_id, users)| {
+            users.iter().map(move |(user_id, receipt)| ReadReceipt {
+                user_id: user_id.clone(),
+                event_id: event_id.clone(),
+                ts: receipt.ts,
+            })
+        })
+        .collect()
+}
+
+/// How many messages arrived after our read receipt, as counted by the server
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct UnreadCounts {
+    pub notifications: u64,
+    /// Messages which mention us or match another highlight rule
+    pub highlights: u64,
+}
+
+impl UnreadCounts {
+    /// `None` if the sync didn't include new counts for the room
+    pub fn from_sync(counts: &UnreadNotificationsCount) -> Option<Self> {
+        if counts.notification_count.is_none() && counts.highlight_count.is_none() {
+            return None;
+        }
+        Some(UnreadCounts {
+            notifications: counts.notification_count.map(u64::from).unwrap_or(0),
+            highlights: counts.highlight_count.map(u64::from).unwrap_or(0),
+        })
+    }
+
+    /// The counts the SDK stored with the room during the last sync
+    pub fn from_room(room: &Room) -> Self {
+        UnreadCounts {
+            notifications: room.unread_notifications.map(u64::from).unwrap_or(0),
+            highlights: room.unread_highlight.map(u64::from).unwrap_or(0),
+        }
+    }
+}
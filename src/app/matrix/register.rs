@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use matrix_sdk::{
+    api::r0::{
+        account::{register, request_registration_token_via_email},
+        uiaa::{AuthData, UiaaInfo},
+    },
+    js_int::uint,
+    Client, Session,
+};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+use crate::app::matrix::login::SessionStore;
+use crate::errors::MatrixError;
+
+/// The user-interactive auth stages Daydream can complete during registration
+pub const SUPPORTED_STAGES: [&str; 3] =
+    ["m.login.dummy", "m.login.email.identity", "m.login.terms"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum RegistrationStage {
+    Dummy,
+    Terms,
+    EmailIdentity(EmailToken),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EmailToken {
+    pub sid: String,
+    pub client_secret: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RegistrationData {
+    pub username: String,
+    pub password: String,
+    pub session: Option<String>,
+    pub stage: Option<RegistrationStage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Policy {
+    pub name: String,
+    pub url: String,
+}
+
+/// What the homeserver still wants from us before it creates the account
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegistrationAuth {
+    pub session: Option<String>,
+    pub flows: Vec<Vec<String>>,
+    pub completed: Vec<String>,
+    pub policies: Vec<Policy>,
+}
+
+impl RegistrationAuth {
+    pub fn new(info: &UiaaInfo) -> Self {
+        RegistrationAuth {
+            session: info.session.clone(),
+            flows: info.flows.iter().map(|flow| flow.stages.clone()).collect(),
+            completed: info.completed.clone(),
+            policies: policies(info.params.get()),
+        }
+    }
+
+    /// The next stage of the first flow that only contains stages we support
+    pub fn next_stage(&self) -> Option<&str> {
+        self.flows
+            .iter()
+            .find(|flow| {
+                flow.iter()
+                    .all(|stage| SUPPORTED_STAGES.contains(&stage.as_str()))
+            })?
+            .iter()
+            .find(|stage| !self.completed.contains(stage))
+            .map(String::as_str)
+    }
+}
+
+/// Reads the policy documents of the `m.login.terms` stage, preferring the english version
+fn policies(params: &str) -> Vec<Policy> {
+    let params: JsonValue = serde_json::from_str(params).unwrap_or_default();
+    let policies = match params["m.login.terms"]["policies"].as_object() {
+        Some(policies) => policies,
+        None => return Vec::new(),
+    };
+
+    policies
+        .values()
+        .filter_map(|policy| {
+            let localized = policy.get("en").or_else(|| {
+                policy
+                    .as_object()?
+                    .iter()
+                    .find(|(key, _)| key.as_str() != "version")
+                    .map(|(_, value)| value)
+            })?;
+            Some(Policy {
+                name: localized["name"].as_str()?.to_string(),
+                url: localized["url"].as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn auth_data<'a>(session: Option<&'a str>, stage: &RegistrationStage) -> AuthData<'a> {
+    let mut auth_parameters = BTreeMap::new();
+    let kind = match stage {
+        RegistrationStage::Dummy => "m.login.dummy",
+        RegistrationStage::Terms => "m.login.terms",
+        RegistrationStage::EmailIdentity(token) => {
+            let creds = json!({
+                "sid": token.sid,
+                "client_secret": token.client_secret,
+            });
+            // Older servers only know the camelCase version
+            auth_parameters.insert("threepidCreds".to_string(), creds.clone());
+            auth_parameters.insert("threepid_creds".to_string(), creds);
+            "m.login.email.identity"
+        }
+    };
+    AuthData::DirectRequest {
+        kind,
+        session,
+        auth_parameters,
+    }
+}
+
+/// Sends one step of the `/register` user-interactive auth flow.
+///
+/// Returns the new account once the server is happy, otherwise the error contains the `UiaaInfo`.
+pub async fn register(
+    client: &Client,
+    data: &RegistrationData,
+) -> Result<register::Response, matrix_sdk::Error> {
+    let auth = data
+        .stage
+        .as_ref()
+        .map(|stage| auth_data(data.session.as_deref(), stage));
+    client
+        .send(register::Request {
+            auth,
+            username: Some(&data.username),
+            password: Some(&data.password),
+            device_id: None,
+            initial_device_display_name: Some("Daydream"),
+            kind: None,
+            inhibit_login: false,
+        })
+        .await
+}
+
+/// Logs the client in with the session the homeserver created for the new account
+pub async fn registered_session(
+    client: &Client,
+    response: register::Response,
+) -> Result<SessionStore, MatrixError> {
+    // We don't inhibit the login, but the server still might not hand out a device
+    let device_id = response.device_id.ok_or(MatrixError::MissingDeviceId)?;
+    let access_token = response.access_token.unwrap_or_default();
+    client
+        .restore_login(Session {
+            access_token: access_token.clone(),
+            user_id: response.user_id.clone(),
+            device_id: device_id.clone(),
+        })
+        .await
+        .map_err(|e| MatrixError::SDKError(e.to_string()))?;
+
+    Ok(SessionStore {
+        access_token,
+        user_id: response.user_id.to_string(),
+        device_id: device_id.into(),
+        homeserver_url: client.homeserver().to_string(),
+    })
+}
+
+/// Asks the homeserver to send a validation mail for the `m.login.email.identity` stage
+pub async fn request_email_token(
+    client: &Client,
+    email: &str,
+) -> Result<EmailToken, matrix_sdk::Error> {
+    let client_secret: String = thread_rng().sample_iter(&Alphanumeric).take(32).collect();
+    let response = client
+        .send(request_registration_token_via_email::Request {
+            client_secret: &client_secret,
+            email,
+            send_attempt: uint!(1),
+            next_link: None,
+            identity_server_info: None,
+        })
+        .await?;
+
+    Ok(EmailToken {
+        sid: response.sid,
+        client_secret,
+    })
+}
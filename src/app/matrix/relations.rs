@@ -0,0 +1,401 @@
+use std::convert::TryFrom;
+use std::time::SystemTime;
+
+use matrix_sdk::{
+    events::{
+        custom::CustomEventContent,
+        room::message::{
+            FormattedBody, InReplyTo, MessageEventContent, RelatesTo, TextMessageEventContent,
+        },
+        AnyMessageEventContent, AnySyncMessageEvent,
+    },
+    identifiers::{EventId, RoomId, UserId},
+    Raw,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+/// How a message we send relates to an existing one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MessageRelation {
+    /// Replaces the content of one of our own messages
+    Edit(EventId),
+    /// Quotes the message as `m.in_reply_to`
+    Reply(ReplyTo),
+    /// Adds the message to the thread of the root event.
+    ///
+    /// The latest event of the thread is used for the reply fallback.
+    Thread { root: EventId, latest: EventId },
+}
+
+/// What is needed to build the fallback of a reply for clients without reply support
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReplyTo {
+    pub event_id: EventId,
+    pub sender: UserId,
+    pub body: String,
+    pub formatted_body: Option<String>,
+}
+
+/// What an incoming event does to the event it relates to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Relation {
+    /// `m.replace`, the related event got edited and has this new content
+    Replace(EventId, MessageEventContent),
+    /// `m.annotation`, a reaction with the emoji as key
+    Annotation(EventId, String),
+    /// `m.thread`, a message in the thread of the related root event
+    Thread(EventId, Box<AnySyncMessageEvent>),
+}
+
+/// An event which isn't shown on its own but changes how another event is shown
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RelatedEvent {
+    pub event_id: EventId,
+    pub sender: UserId,
+    pub origin_server_ts: SystemTime,
+    pub relation: Relation,
+}
+
+impl RelatedEvent {
+    /// Reads the relation of a (decrypted) event.
+    ///
+    /// The SDK types don't know about most relations yet, so they are read from the raw json.
+    /// For encrypted events `m.relates_to` is in the clear, but the rest only exists in the
+    /// decrypted event.
+    pub fn from_raw<T>(raw: &Raw<T>, event: &AnySyncMessageEvent) -> Option<Self> {
+        let json: JsonValue = serde_json::from_str(raw.json().get()).ok()?;
+        let relates_to = &json["content"]["m.relates_to"];
+        let related_event_id = EventId::try_from(relates_to["event_id"].as_str()?).ok()?;
+
+        let relation = match (relates_to["rel_type"].as_str()?, event) {
+            ("m.replace", AnySyncMessageEvent::RoomMessage(message)) => {
+                let new_content = serde_json::from_value(json["content"]["m.new_content"].clone())
+                    .unwrap_or_else(|_| strip_edit_fallback(message.content.clone()));
+                Relation::Replace(related_event_id, new_content)
+            }
+            ("m.annotation", _) => {
+                Relation::Annotation(related_event_id, relates_to["key"].as_str()?.to_string())
+            }
+            ("m.thread", _) => Relation::Thread(related_event_id, Box::new(event.clone())),
+            _ => return None,
+        };
+
+        Some(RelatedEvent {
+            event_id: event.event_id().clone(),
+            sender: event.sender().clone(),
+            origin_server_ts: *event.origin_server_ts(),
+            relation,
+        })
+    }
+}
+
+/// Edits repeat the new content with a leading "* " for clients without edit support
+fn strip_edit_fallback(content: MessageEventContent) -> MessageEventContent {
+    match content {
+        MessageEventContent::Text(mut text) => {
+            if let Some(body) = text.body.strip_prefix("* ") {
+                text.body = body.to_string();
+            }
+            if let Some(formatted) = &mut text.formatted {
+                if let Some(body) = formatted.body.strip_prefix("* ") {
+                    formatted.body = body.to_string();
+                }
+            }
+            MessageEventContent::Text(text)
+        }
+        content => content,
+    }
+}
+
+/// Builds the content of an `m.room.message` which carries the relation.
+///
+/// The SDK content types can't express most relations, so the json is assembled by hand and
+/// sent as a custom event of type `m.room.message`. It still gets encrypted like any other
+/// message.
+pub fn message_with_relation(
+    room_id: &RoomId,
+    content: TextMessageEventContent,
+    relation: &MessageRelation,
+) -> AnyMessageEventContent {
+    let json = match relation {
+        // Replies are known to the SDK
+        MessageRelation::Reply(reply_to) => {
+            return AnyMessageEventContent::RoomMessage(MessageEventContent::Text(reply_content(
+                room_id, content, reply_to,
+            )));
+        }
+        MessageRelation::Edit(event_id) => {
+            let new_content = serde_json::to_value(MessageEventContent::Text(content.clone()))
+                .unwrap_or_default();
+            let mut fallback = content;
+            fallback.body = format!("* {}", fallback.body);
+            if let Some(formatted) = &mut fallback.formatted {
+                formatted.body = format!("* {}", formatted.body);
+            }
+
+            let mut json =
+                serde_json::to_value(MessageEventContent::Text(fallback)).unwrap_or_default();
+            json["m.new_content"] = new_content;
+            json["m.relates_to"] = json!({
+                "rel_type": "m.replace",
+                "event_id": event_id,
+            });
+            json
+        }
+        MessageRelation::Thread { root, latest } => {
+            let mut json =
+                serde_json::to_value(MessageEventContent::Text(content)).unwrap_or_default();
+            // Clients without thread support show it as a reply to the latest message
+            json["m.relates_to"] = json!({
+                "rel_type": "m.thread",
+                "event_id": root,
+                "is_falling_back": true,
+                "m.in_reply_to": {
+                    "event_id": latest,
+                },
+            });
+            json
+        }
+    };
+
+    AnyMessageEventContent::Custom(CustomEventContent {
+        event_type: "m.room.message".to_string(),
+        json,
+    })
+}
+
+/// The content of an `m.reaction` event, which the SDK doesn't have a type for yet
+pub fn reaction_content(event_id: &EventId, key: &str) -> AnyMessageEventContent {
+    AnyMessageEventContent::Custom(CustomEventContent {
+        event_type: "m.reaction".to_string(),
+        json: json!({
+            "m.relates_to": {
+                "rel_type": "m.annotation",
+                "event_id": event_id,
+                "key": key,
+            }
+        }),
+    })
+}
+
+/// Adds `m.in_reply_to` and the quote fallbacks described in the spec
+fn reply_content(
+    room_id: &RoomId,
+    content: TextMessageEventContent,
+    reply_to: &ReplyTo,
+) -> TextMessageEventContent {
+    let parent_body = strip_reply_fallback(&reply_to.body);
+    let quoted_lines: Vec<String> = parent_body
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => format!("> <{}> {}", reply_to.sender, line),
+            _ => format!("> {}", line),
+        })
+        .collect();
+    let body = format!("{}\n\n{}", quoted_lines.join("\n"), content.body);
+
+    let parent_html = match &reply_to.formatted_body {
+        Some(formatted) => strip_reply_fallback_html(formatted).to_string(),
+        None => escape_html(parent_body).replace('\n', "<br />"),
+    };
+    let reply_html = match &content.formatted {
+        Some(formatted) => formatted.body.clone(),
+        None => escape_html(&content.body).replace('\n', "<br />"),
+    };
+    let formatted_body = format!(
+        "<mx-reply><blockquote>\
+            <a href=\"https://matrix.to/#/{room_id}/{event_id}\">In reply to</a> \
+            <a href=\"https://matrix.to/#/{sender}\">{sender}</a><br />{parent}\
+        </blockquote></mx-reply>{reply}",
+        room_id = room_id,
+        event_id = reply_to.event_id,
+        sender = reply_to.sender,
+        parent = parent_html,
+        reply = reply_html,
+    );
+
+    TextMessageEventContent {
+        body,
+        formatted: Some(FormattedBody::html(formatted_body)),
+        relates_to: Some(RelatesTo {
+            in_reply_to: InReplyTo {
+                event_id: reply_to.event_id.clone(),
+            },
+        }),
+    }
+}
+
+/// Removes the "> <@alice:example.org> quote" lines in front of the body of a reply
+pub fn strip_reply_fallback(body: &str) -> &str {
+    if !body.starts_with("> ") {
+        return body;
+    }
+    match body.find("\n\n") {
+        Some(end) if body[..end].lines().all(|line| line.starts_with('>')) => &body[end + 2..],
+        _ => body,
+    }
+}
+
+/// The quote of the parent from the "> <@alice:example.org> quote" lines in front of a reply
+pub fn reply_fallback(body: &str) -> Option<String> {
+    if !body.starts_with("> ") {
+        return None;
+    }
+    let quote = &body[..body.find("\n\n")?];
+    if !quote.lines().all(|line| line.starts_with('>')) {
+        return None;
+    }
+    let lines: Vec<&str> = quote
+        .lines()
+        .map(|line| line.trim_start_matches('>').trim_start())
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// Removes the `<mx-reply>` quote in front of the formatted body of a reply
+pub fn strip_reply_fallback_html(formatted_body: &str) -> &str {
+    if !formatted_body.starts_with("<mx-reply>") {
+        return formatted_body;
+    }
+    // Some clients keep the fallback of the parent inside the quote
+    let mut depth = 0;
+    let mut index = 0;
+    while let Some(offset) = formatted_body[index..].find("mx-reply>") {
+        let tag = index + offset;
+        if formatted_body[..tag].ends_with("</") {
+            depth -= 1;
+        } else if formatted_body[..tag].ends_with('<') {
+            depth += 1;
+        }
+        index = tag + "mx-reply>".len();
+        if depth == 0 {
+            return &formatted_body[index..];
+        }
+    }
+    formatted_body
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply_to(body: &str, formatted_body: Option<&str>) -> ReplyTo {
+        ReplyTo {
+            event_id: EventId::try_from("$parent:example.org").unwrap(),
+            sender: UserId::try_from("@alice:example.org").unwrap(),
+            body: body.to_string(),
+            formatted_body: formatted_body.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn strip_multi_line_fallback() {
+        let body = "> <@alice:example.org> first line\n> second line\n\nthe reply";
+        assert_eq!(strip_reply_fallback(body), "the reply");
+    }
+
+    #[test]
+    fn strip_without_fallback() {
+        assert_eq!(strip_reply_fallback("no reply"), "no reply");
+        // A quote without the empty line after it is part of the message
+        assert_eq!(strip_reply_fallback("> quote\ntext"), "> quote\ntext");
+        assert_eq!(
+            strip_reply_fallback_html("<b>no reply</b>"),
+            "<b>no reply</b>"
+        );
+        assert_eq!(reply_fallback("no reply"), None);
+    }
+
+    #[test]
+    fn strip_nested_html_fallback() {
+        let formatted_body = "<mx-reply><blockquote>\
+            <mx-reply><blockquote>grandparent</blockquote></mx-reply>parent\
+            </blockquote></mx-reply>the reply";
+        assert_eq!(strip_reply_fallback_html(formatted_body), "the reply");
+    }
+
+    #[test]
+    fn strip_unclosed_html_fallback() {
+        let formatted_body = "<mx-reply><blockquote>parent</blockquote>";
+        assert_eq!(strip_reply_fallback_html(formatted_body), formatted_body);
+    }
+
+    #[test]
+    fn multi_line_reply_fallback() {
+        let body = "> <@alice:example.org> first line\n> second line\n\nthe reply";
+        assert_eq!(
+            reply_fallback(body).as_deref(),
+            Some("<@alice:example.org> first line\nsecond line")
+        );
+    }
+
+    #[test]
+    fn reply_fallback_keeps_html_as_text() {
+        // The quote is shown as text, so markup in it must come out unchanged
+        let body = "> <@alice:example.org> <b>bold</b> & co\n\nthe reply";
+        assert_eq!(
+            reply_fallback(body).as_deref(),
+            Some("<@alice:example.org> <b>bold</b> & co")
+        );
+    }
+
+    #[test]
+    fn reply_content_quotes_multi_line_parent() {
+        let room_id = RoomId::try_from("!room:example.org").unwrap();
+        let parent = reply_to("first line\nsecond line", None);
+        let content = reply_content(
+            &room_id,
+            TextMessageEventContent::plain("the reply"),
+            &parent,
+        );
+        assert_eq!(
+            content.body,
+            "> <@alice:example.org> first line\n> second line\n\nthe reply"
+        );
+        assert_eq!(strip_reply_fallback(&content.body), "the reply");
+    }
+
+    #[test]
+    fn reply_content_escapes_html() {
+        let room_id = RoomId::try_from("!room:example.org").unwrap();
+        let parent = reply_to("<b>bold</b> & \"quoted\"", None);
+        let content = reply_content(
+            &room_id,
+            TextMessageEventContent::plain("<i>reply</i>"),
+            &parent,
+        );
+        let formatted_body = content.formatted.unwrap().body;
+        assert!(formatted_body.contains("&lt;b&gt;bold&lt;/b&gt; &amp; &quot;quoted&quot;"));
+        assert_eq!(
+            strip_reply_fallback_html(&formatted_body),
+            "&lt;i&gt;reply&lt;/i&gt;"
+        );
+    }
+
+    #[test]
+    fn reply_content_drops_the_fallback_of_the_parent() {
+        let room_id = RoomId::try_from("!room:example.org").unwrap();
+        let parent = reply_to(
+            "> <@bob:example.org> grandparent\n\nparent",
+            Some("<mx-reply><blockquote>grandparent</blockquote></mx-reply><b>parent</b>"),
+        );
+        let content = reply_content(
+            &room_id,
+            TextMessageEventContent::plain("the reply"),
+            &parent,
+        );
+        assert_eq!(content.body, "> <@alice:example.org> parent\n\nthe reply");
+        let formatted_body = content.formatted.unwrap().body;
+        assert!(!formatted_body.contains("grandparent"));
+        assert!(formatted_body.contains("<b>parent</b>"));
+    }
+}
@@ -4,13 +4,15 @@ use std::sync::Mutex;
 use std::time::Duration;
 
 use log::*;
+#[cfg(feature = "encryption")]
+use matrix_sdk::events::AnyToDeviceEvent;
 use matrix_sdk::{
     api::r0::filter::{FilterDefinition, LazyLoadOptions, RoomEventFilter, RoomFilter},
     api::r0::sync::sync_events::Filter,
     api::r0::sync::sync_events::Response as SyncResponse,
     events::{
-        room::message::MessageEventContent, AnySyncMessageEvent, AnySyncRoomEvent,
-        AnySyncStateEvent,
+        room::message::MessageEventContent, AnyBasicEvent, AnyStrippedStateEvent,
+        AnySyncEphemeralRoomEvent, AnySyncMessageEvent, AnySyncRoomEvent, AnySyncStateEvent,
     },
     identifiers::RoomId,
     locks::RwLock,
@@ -19,21 +21,34 @@ use matrix_sdk::{
 use wasm_bindgen_futures::spawn_local;
 use yew::Callback;
 
-use lazy_static::lazy_static;
 use matrix_sdk::js_int::UInt;
 
 use crate::app::components::events::RoomExt;
+#[cfg(feature = "encryption")]
+use crate::app::matrix::backup::KeyBackup;
+use crate::app::matrix::encryption::decrypt_sync_event;
+use crate::app::matrix::invites::{Invite, PendingInvites};
+use crate::app::matrix::presence::PresenceInfo;
+use crate::app::matrix::receipts::{read_receipts, UnreadCounts};
+use crate::app::matrix::relations::RelatedEvent;
+use crate::app::matrix::tags::{tag_names, RoomTags};
 use crate::app::matrix::types::{get_media_download_url, get_video_media_download_url};
+#[cfg(feature = "encryption")]
+use crate::app::matrix::verification::{verification_flow_id, VerificationInfo, VerificationState};
 use crate::app::matrix::Response;
+#[cfg(feature = "encryption")]
+use crate::errors::MatrixError;
 use crate::utils::notifications::Notifications;
 
-lazy_static! {
-    static ref SYNC_NUMBER: Mutex<i32> = Mutex::new(0);
-}
-
 pub struct Sync {
     pub(crate) matrix_client: Client,
     pub(crate) callback: Callback<Response>,
+    #[cfg(feature = "encryption")]
+    pub(crate) key_backup: KeyBackup,
+    pub(crate) room_tags: RoomTags,
+    pub(crate) invites: PendingInvites,
+    /// Notifications are only shown after the initial sync
+    pub(crate) sync_number: Mutex<i32>,
 }
 
 impl Sync {
@@ -51,12 +66,21 @@ impl Sync {
                         },
                         ..Default::default()
                     }),
+                    ephemeral: Some(RoomEventFilter {
+                        types: Some(vec!["m.receipt".to_string(), "m.typing".to_string()]),
+                        ..Default::default()
+                    }),
                     ..Default::default()
                 }),
                 ..Default::default()
             }));
         //.full_state(true);
 
+        #[cfg(feature = "encryption")]
+        if let Err(e) = self.key_backup.load(&client).await {
+            info!("Not uploading keys to a backup: {}", e);
+        }
+
         debug!("start sync_forever!");
         client
             .sync_forever(settings, |response| self.on_sync_response(response))
@@ -66,25 +90,155 @@ impl Sync {
     async fn on_sync_response(&self, response: SyncResponse) {
         debug!("got sync!");
 
-        // FIXME: Is there a smarter way?
-        let resp = Response::SyncPing;
-        self.callback.emit(resp);
+        #[cfg(feature = "encryption")]
+        for event in response.to_device.events {
+            if let Ok(event) = event.deserialize() {
+                self.on_to_device_event(event).await
+            }
+        }
+        for event in response.presence.events {
+            if let Ok(event) = event.deserialize() {
+                let resp = Response::Presence(PresenceInfo::from_event(event));
+                self.callback.emit(resp);
+            }
+        }
         for (room_id, room) in response.rooms.join {
+            // Accepted from another session
+            self.invites.remove(&room_id);
             for event in room.state.events {
                 if let Ok(event) = event.deserialize() {
                     self.on_state_event(&room_id, event).await
                 }
             }
-            for event in room.timeline.events {
+            for event in room.account_data.events {
+                if let Ok(AnyBasicEvent::Tag(event)) = event.deserialize() {
+                    let tags = tag_names(&event.content);
+                    self.room_tags.set(room_id.clone(), tags.clone());
+                    let resp = Response::RoomTags((room_id.clone(), tags));
+                    self.callback.emit(resp);
+                }
+            }
+            for event in room.ephemeral.events {
                 if let Ok(event) = event.deserialize() {
+                    self.on_ephemeral_event(&room_id, event).await
+                }
+            }
+            for raw_event in room.timeline.events {
+                if let Ok(event) = raw_event.deserialize() {
+                    let event = decrypt_sync_event(&self.matrix_client, &room_id, event).await;
+                    // Edits and similar are applied to the event they relate to
+                    if let AnySyncRoomEvent::Message(message) = &event {
+                        if let Some(related) = RelatedEvent::from_raw(&raw_event, message) {
+                            let resp = Response::Relation((room_id.clone(), related));
+                            self.callback.emit(resp);
+                            continue;
+                        }
+                    }
                     self.on_room_message(&room_id, event).await
                 }
             }
+            if let Some(counts) = UnreadCounts::from_sync(&room.unread_notifications) {
+                let resp = Response::UnreadCounts((room_id.clone(), counts));
+                self.callback.emit(resp);
+            }
         }
-        let mut sync_number = SYNC_NUMBER.lock().unwrap();
+        // Rejected invites end up here as well
+        for room_id in response.rooms.leave.keys() {
+            self.invites.remove(room_id);
+            let resp = Response::LeftRoom(room_id.clone());
+            self.callback.emit(resp);
+        }
+        if !response.rooms.invite.is_empty() {
+            let own_user_id = self.matrix_client.user_id().await.unwrap();
+            for (room_id, room) in response.rooms.invite {
+                let events: Vec<AnyStrippedStateEvent> = room
+                    .invite_state
+                    .events
+                    .iter()
+                    .filter_map(|event| event.deserialize().ok())
+                    .collect();
+                let invite = Invite::from_stripped_state(room_id, &own_user_id, &events);
+                self.invites.insert(invite.clone());
+                let resp = Response::Invite(invite);
+                self.callback.emit(resp);
+            }
+        }
+        #[cfg(feature = "encryption")]
+        if self.key_backup.has_new_keys() {
+            self.upload_room_keys().await;
+        }
+
+        let mut sync_number = self.sync_number.lock().unwrap();
         if *sync_number == 0 {
             *sync_number = 1;
         }
+
+        // FIXME: Is there a smarter way?
+        // Sent after the events so everything before the first ping belongs to the initial sync
+        let resp = Response::SyncPing;
+        self.callback.emit(resp);
+    }
+
+    #[cfg(feature = "encryption")]
+    async fn upload_room_keys(&self) {
+        match self.key_backup.upload_new_keys(&self.matrix_client).await {
+            Ok(status) => {
+                let resp = Response::KeyBackupStatus(status);
+                self.callback.emit(resp);
+            }
+            // No backup set up, nothing to do
+            Err(MatrixError::MissingKeyBackup) => {}
+            Err(e) => error!("Failed to upload keys to backup: {}", e),
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    async fn on_to_device_event(&self, event: AnyToDeviceEvent) {
+        if let AnyToDeviceEvent::RoomKey(_) | AnyToDeviceEvent::ForwardedRoomKey(_) = event {
+            self.key_backup.keys_changed();
+            return;
+        }
+
+        let flow_id = match verification_flow_id(&event) {
+            Some(flow_id) => flow_id.to_string(),
+            None => return,
+        };
+
+        // The SDK already advanced the SAS state machine before we got the response
+        let info = match self.matrix_client.get_verification(&flow_id).await {
+            Some(sas) => {
+                let initial_state = match event {
+                    AnyToDeviceEvent::KeyVerificationStart(_) => VerificationState::Requested,
+                    _ => VerificationState::Started,
+                };
+                VerificationInfo::from_sas(&sas, initial_state)
+            }
+            None => match event {
+                // Cancelled flows might already be gone from the SDK
+                AnyToDeviceEvent::KeyVerificationCancel(event) => VerificationInfo {
+                    flow_id,
+                    other_user_id: event.sender,
+                    other_device_id: "".to_string(),
+                    state: VerificationState::Cancelled,
+                },
+                _ => return,
+            },
+        };
+        let resp = Response::Verification(info);
+        self.callback.emit(resp);
+    }
+
+    async fn on_ephemeral_event(&self, room_id: &RoomId, event: AnySyncEphemeralRoomEvent) {
+        let resp = match event {
+            AnySyncEphemeralRoomEvent::Receipt(event) => {
+                Response::ReadReceipts((room_id.clone(), read_receipts(&event.content)))
+            }
+            AnySyncEphemeralRoomEvent::Typing(event) => {
+                Response::Typing((room_id.clone(), event.content.user_ids))
+            }
+            _ => return,
+        };
+        self.callback.emit(resp);
     }
 
     async fn on_state_event(&self, room_id: &RoomId, event: AnySyncStateEvent) {
@@ -105,6 +259,24 @@ impl Sync {
             self.callback.emit(resp);
         }
 
+        // Still encrypted means we are missing the keys. The UI shows a placeholder for it.
+        if let AnySyncRoomEvent::Message(AnySyncMessageEvent::RoomEncrypted(_)) = event {
+            let resp = Response::Sync((room_id.clone(), Raw::from(event)));
+            self.callback.emit(resp);
+            return;
+        }
+
+        // Membership and other room changes are shown as compact lines in the timeline
+        // and redactions replace the redacted event with a placeholder
+        if let AnySyncRoomEvent::State(_)
+        | AnySyncRoomEvent::RedactedMessage(_)
+        | AnySyncRoomEvent::Message(AnySyncMessageEvent::RoomRedaction(_)) = event
+        {
+            let resp = Response::Sync((room_id.clone(), Raw::from(event)));
+            self.callback.emit(resp);
+            return;
+        }
+
         if let AnySyncRoomEvent::Message(AnySyncMessageEvent::RoomMessage(mut event)) = event {
             if let MessageEventContent::Text(text_event) = event.content.clone() {
                 let homeserver_url = self.matrix_client.clone().homeserver().clone();
@@ -112,7 +284,7 @@ impl Sync {
                 let cloned_event = event.clone();
                 let client = self.matrix_client.clone();
                 let local_room_id = room_id.clone();
-                let sync_number = SYNC_NUMBER.lock().unwrap();
+                let sync_number = self.sync_number.lock().unwrap();
                 if *sync_number == 1 {
                     spawn_local(async move {
                         let room: Arc<RwLock<Room>> = client
@@ -192,7 +364,9 @@ impl Sync {
                 }
             }
 
-            let serialized_event = Raw::from(AnySyncMessageEvent::RoomMessage(event));
+            let serialized_event = Raw::from(AnySyncRoomEvent::Message(
+                AnySyncMessageEvent::RoomMessage(event),
+            ));
             let resp = Response::Sync((room_id.clone(), serialized_event));
             self.callback.emit(resp);
         }
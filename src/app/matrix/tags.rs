@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use matrix_sdk::{events::tag::TagEventContent, identifiers::RoomId};
+
+pub const FAVOURITE: &str = "m.favourite";
+pub const LOW_PRIORITY: &str = "m.lowpriority";
+
+/// The `m.tag` room account data of every joined room.
+///
+/// The SDK doesn't keep it, so the sync stores it here for room lists which get created later.
+#[derive(Clone, Debug, Default)]
+pub struct RoomTags {
+    tags: Arc<Mutex<HashMap<RoomId, Vec<String>>>>,
+}
+
+impl RoomTags {
+    pub fn get(&self, room_id: &RoomId) -> Vec<String> {
+        self.tags
+            .lock()
+            .unwrap()
+            .get(room_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, room_id: RoomId, tags: Vec<String>) {
+        self.tags.lock().unwrap().insert(room_id, tags);
+    }
+
+    /// Adds or removes a single tag and returns the new tags of the room
+    pub fn toggle(&self, room_id: &RoomId, tag: &str, enabled: bool) -> Vec<String> {
+        let mut all_tags = self.tags.lock().unwrap();
+        let tags = all_tags.entry(room_id.clone()).or_default();
+        tags.retain(|t| t != tag);
+        if enabled {
+            tags.push(tag.to_string());
+        }
+        tags.clone()
+    }
+}
+
+pub fn tag_names(content: &TagEventContent) -> Vec<String> {
+    content.tags.keys().cloned().collect()
+}
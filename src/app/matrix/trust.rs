@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use matrix_sdk::identifiers::UserId;
+#[cfg(feature = "encryption")]
+use matrix_sdk::{Client, Room};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TrustLevel {
+    /// The user is verified and so are all of their devices
+    Verified,
+    /// The user is verified but has devices that are not
+    UnverifiedDevices,
+    /// The user itself isn't verified
+    Unverified,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RoomTrust {
+    pub users: HashMap<UserId, TrustLevel>,
+}
+
+impl RoomTrust {
+    /// Whether a member of the room has a device we don't trust
+    pub fn has_unverified_devices(&self) -> bool {
+        self.users
+            .values()
+            .any(|trust| *trust != TrustLevel::Verified)
+    }
+}
+
+#[cfg(feature = "encryption")]
+pub async fn user_trust(client: &Client, user_id: &UserId) -> TrustLevel {
+    let user_verified = client
+        .get_user_identity(user_id)
+        .await
+        .map(|identity| identity.is_verified())
+        .unwrap_or(false);
+    if !user_verified {
+        return TrustLevel::Unverified;
+    }
+
+    let devices_verified = match client.get_user_devices(user_id).await {
+        Ok(devices) => devices.devices().all(|device| device.is_trusted()),
+        Err(_) => false,
+    };
+    if devices_verified {
+        TrustLevel::Verified
+    } else {
+        TrustLevel::UnverifiedDevices
+    }
+}
+
+#[cfg(feature = "encryption")]
+pub async fn room_trust(client: &Client, room: &Room) -> RoomTrust {
+    let mut users = HashMap::new();
+    for user_id in room.joined_members.keys() {
+        users.insert(user_id.clone(), user_trust(client, user_id).await);
+    }
+    RoomTrust { users }
+}
@@ -0,0 +1,38 @@
+use std::collections::BTreeMap;
+
+use matrix_sdk::api::{
+    error::{FromHttpResponseError, ServerError},
+    r0::uiaa::{AuthData, UiaaInfo, UiaaResponse},
+};
+use serde_json::json;
+
+/// Returns the user-interactive auth info if the server asks for (more) authentication
+pub fn uiaa_info(error: &matrix_sdk::Error) -> Option<&UiaaInfo> {
+    if let matrix_sdk::Error::UiaaError(FromHttpResponseError::Http(ServerError::Known(
+        UiaaResponse::AuthResponse(info),
+    ))) = error
+    {
+        Some(info)
+    } else {
+        None
+    }
+}
+
+/// Builds the `m.login.password` stage for the given session
+pub fn password_auth<'a>(session: &'a str, user_id: &str, password: &str) -> AuthData<'a> {
+    let mut auth_parameters = BTreeMap::new();
+    auth_parameters.insert(
+        "identifier".to_string(),
+        json!({
+            "type": "m.id.user",
+            "user": user_id,
+        }),
+    );
+    auth_parameters.insert("password".to_string(), json!(password));
+
+    AuthData::DirectRequest {
+        kind: "m.login.password",
+        session: Some(session),
+        auth_parameters,
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(feature = "encryption")]
+use matrix_sdk::Sas;
+use matrix_sdk::{
+    events::{key::verification::start::StartEventContent, AnyToDeviceEvent},
+    identifiers::UserId,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum VerificationState {
+    /// Another device wants to verify with us and waits for us to accept
+    Requested,
+    /// We asked another device and wait for it to accept
+    Started,
+    /// Both sides exchanged keys, the emoji need to be compared
+    Emoji(Vec<(String, String)>),
+    /// We confirmed the emoji and wait for the other side to do the same
+    Confirmed,
+    Done,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VerificationInfo {
+    pub flow_id: String,
+    pub other_user_id: UserId,
+    pub other_device_id: String,
+    pub state: VerificationState,
+}
+
+#[cfg(feature = "encryption")]
+impl VerificationInfo {
+    pub fn new(sas: &Sas, state: VerificationState) -> Self {
+        let other_device = sas.other_device();
+        VerificationInfo {
+            flow_id: sas.flow_id().to_string(),
+            other_user_id: other_device.user_id().clone(),
+            other_device_id: other_device.device_id().to_string(),
+            state,
+        }
+    }
+
+    /// Reads the current state from the SAS flow.
+    ///
+    /// `Requested` and `Started` can't be told apart by the flow itself so they have to be passed in
+    /// as `initial_state`.
+    pub fn from_sas(sas: &Sas, initial_state: VerificationState) -> Self {
+        let state = if sas.is_canceled() {
+            VerificationState::Cancelled
+        } else if sas.is_done() {
+            VerificationState::Done
+        } else if let Some(emoji) = sas.emoji() {
+            VerificationState::Emoji(
+                emoji
+                    .into_iter()
+                    .map(|(emoji, description)| (emoji.to_string(), description.to_string()))
+                    .collect(),
+            )
+        } else {
+            initial_state
+        };
+        VerificationInfo::new(sas, state)
+    }
+}
+
+/// Returns the flow id of `m.key.verification.*` to-device events
+pub fn verification_flow_id(event: &AnyToDeviceEvent) -> Option<&str> {
+    match event {
+        AnyToDeviceEvent::KeyVerificationStart(event) => match &event.content {
+            StartEventContent::MSasV1(content) => Some(&content.transaction_id),
+        },
+        AnyToDeviceEvent::KeyVerificationAccept(event) => Some(&event.content.transaction_id),
+        AnyToDeviceEvent::KeyVerificationKey(event) => Some(&event.content.transaction_id),
+        AnyToDeviceEvent::KeyVerificationMac(event) => Some(&event.content.transaction_id),
+        AnyToDeviceEvent::KeyVerificationCancel(event) => Some(&event.content.transaction_id),
+        _ => None,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub device_id: String,
+    pub display_name: Option<String>,
+    pub verified: bool,
+}
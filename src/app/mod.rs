@@ -2,11 +2,22 @@ use yew::{prelude::*, virtual_dom::VNode};
 use yew_router::agent::RouteRequest::ChangeRoute;
 use yew_router::{prelude::*, Switch};
 
-use crate::app::matrix::{login::SessionStore, MatrixAgent, Response};
-use crate::app::views::{login::Login, main_view::MainView};
-use crate::constants::AUTH_KEY;
+use crate::app::matrix::{
+    account::{new_account_id, AccountId, AccountSummary},
+    login::SessionStore,
+    relations::Relation,
+    MatrixAgent, Response,
+};
+use crate::app::views::{
+    explore::Explore, login::Login, main_view::MainView, register::Register, settings::Settings,
+};
+use crate::constants::{ACCOUNTS_KEY, ACTIVE_ACCOUNT_KEY, AUTH_KEY, SSO_HOMESERVER_KEY};
 use log::*;
+use matrix_sdk::{events::AnySyncRoomEvent, identifiers::EventId};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, Mutex};
+use url::Url;
+use wasm_bindgen::JsValue;
 use yew::format::Json;
 use yew::services::storage::Area;
 use yew::services::StorageService;
@@ -19,6 +30,12 @@ mod views;
 pub enum AppRoute {
     #[to = "/login"]
     Login,
+    #[to = "/register"]
+    Register,
+    #[to = "/settings"]
+    Settings,
+    #[to = "/explore"]
+    Explore,
     #[to = "/"]
     MainView,
 }
@@ -27,15 +44,24 @@ pub enum AppRoute {
 pub enum Msg {
     RouteChanged(Route<()>),
     ChangeRoute(AppRoute),
-    NewMessage(Response),
+    NewMessage((AccountId, Response)),
+    SwitchAccount(AccountId),
+    AddAccount,
 }
 
 pub struct App {
-    // While unused this needs to stay :(
-    _matrix_agent: Box<dyn Bridge<MatrixAgent>>,
+    link: ComponentLink<Self>,
+    matrix_agent: Box<dyn Bridge<MatrixAgent>>,
     route: Option<Route<()>>,
     route_agent: Box<dyn Bridge<RouteAgent<()>>>,
     storage: Arc<Mutex<StorageService>>,
+    accounts: BTreeMap<AccountId, SessionStore>,
+    /// Either a logged in account or the one that is about to log in
+    active_account: AccountId,
+    /// The new messages of the other accounts, a thread message can arrive as sync and relation
+    unread: HashMap<AccountId, HashSet<EventId>>,
+    /// Accounts which are done with their initial sync
+    synced: HashSet<AccountId>,
 }
 
 impl Component for App {
@@ -47,24 +73,67 @@ impl Component for App {
             StorageService::new(Area::Local).expect("storage was disabled by the user"),
         ));
 
-        let session: Option<SessionStore> = {
-            if let Json(Ok(restored_model)) = storage.lock().unwrap().restore(AUTH_KEY) {
+        let accounts = restore_accounts(&mut storage.lock().unwrap());
+        let active_account: Option<AccountId> = {
+            if let Json(Ok(restored_model)) = storage.lock().unwrap().restore(ACTIVE_ACCOUNT_KEY) {
                 Some(restored_model)
             } else {
                 None
             }
         };
+        let mut active_account = active_account
+            .filter(|account| accounts.contains_key(account))
+            .or_else(|| accounts.keys().next().cloned())
+            .unwrap_or_else(new_account_id);
+
         let route_agent = RouteAgent::bridge(link.callback(Msg::RouteChanged));
         let mut matrix_agent = MatrixAgent::bridge(link.callback(Msg::NewMessage));
-        if let Some(session) = session {
-            matrix_agent.send(matrix::Request::SetSession(session));
+        for (account, session) in accounts.iter() {
+            matrix_agent.send((
+                account.clone(),
+                matrix::Request::SetSession(session.clone()),
+            ));
+            matrix_agent.send((account.clone(), matrix::Request::GetLoggedIn));
+        }
+        if let Some(login_token) = take_sso_login_token() {
+            // We are coming back from the SSO flow of the homeserver
+            let homeserver: Option<String> = {
+                let mut storage = storage.lock().unwrap();
+                let homeserver = if let Json(Ok(homeserver)) = storage.restore(SSO_HOMESERVER_KEY) {
+                    Some(homeserver)
+                } else {
+                    None
+                };
+                storage.remove(SSO_HOMESERVER_KEY);
+                homeserver
+            };
+            match homeserver {
+                Some(homeserver) => {
+                    active_account = new_account_id();
+                    matrix_agent.send((
+                        active_account.clone(),
+                        matrix::Request::SetHomeserver(homeserver),
+                    ));
+                    matrix_agent.send((
+                        active_account.clone(),
+                        matrix::Request::LoginWithToken(login_token),
+                    ));
+                }
+                None => {
+                    error!("Got a login token but don't know the homeserver");
+                }
+            }
         }
-        matrix_agent.send(matrix::Request::GetLoggedIn);
         App {
-            _matrix_agent: matrix_agent,
+            link,
+            matrix_agent,
             route_agent,
             route: None,
             storage,
+            accounts,
+            active_account,
+            unread: HashMap::new(),
+            synced: HashSet::new(),
         }
     }
 
@@ -79,15 +148,36 @@ impl Component for App {
                 info!("{:?}", self.route);
                 self.route_agent.send(ChangeRoute(route));
             }
-            Msg::NewMessage(response) => {
+            Msg::SwitchAccount(account) => {
+                info!("Switching to account {}", account);
+                self.unread.remove(&account);
+                self.set_active_account(account);
+                self.link.send_message(Msg::ChangeRoute(AppRoute::MainView));
+            }
+            Msg::AddAccount => {
+                self.active_account = new_account_id();
+                self.link.send_message(Msg::ChangeRoute(AppRoute::Login));
+            }
+            Msg::NewMessage((account, response)) => {
                 //info!("NewMessage: {:#?}", response);
                 match response {
                     Response::SaveSession(session) => {
-                        let mut storage = self.storage.lock().unwrap();
-                        storage.store(AUTH_KEY, Json(&session));
+                        self.accounts.insert(account.clone(), session);
+                        self.store_accounts();
+                        if account == self.active_account {
+                            self.set_active_account(account);
+                        }
                     }
                     Response::Error(_) => {}
                     Response::LoggedIn(logged_in) => {
+                        if logged_in {
+                            // Every account keeps syncing, not only the active one
+                            self.matrix_agent
+                                .send((account.clone(), matrix::Request::StartSync));
+                        }
+                        if account != self.active_account {
+                            return false;
+                        }
                         let route: Route = if logged_in {
                             //self.state.logged_in = true;
 
@@ -102,6 +192,64 @@ impl Component for App {
                         self.route = Some(route.clone());
                         self.route_agent.send(ChangeRoute(route));
                     }
+                    Response::LoggedOut => {
+                        self.accounts.remove(&account);
+                        self.unread.remove(&account);
+                        self.synced.remove(&account);
+                        self.store_accounts();
+
+                        if account == self.active_account {
+                            // Continue with one of the other accounts if there is one
+                            match self.accounts.keys().next().cloned() {
+                                Some(next_account) => {
+                                    self.set_active_account(next_account);
+                                    self.link.send_message(Msg::ChangeRoute(AppRoute::MainView));
+                                }
+                                None => {
+                                    self.active_account = new_account_id();
+                                    self.link.send_message(Msg::ChangeRoute(AppRoute::Login));
+                                }
+                            }
+                        }
+                    }
+                    Response::SyncPing => {
+                        self.synced.insert(account);
+                        return false;
+                    }
+                    Response::Sync((_, raw_event)) => {
+                        // Events of the initial sync are not new
+                        if account == self.active_account || !self.synced.contains(&account) {
+                            return false;
+                        }
+                        let user_id = match self.accounts.get(&account) {
+                            Some(session) => &session.user_id,
+                            None => return false,
+                        };
+                        if let Ok(AnySyncRoomEvent::Message(event)) = raw_event.deserialize() {
+                            if event.sender().as_str() != user_id {
+                                let event_id = event.event_id().clone();
+                                self.unread.entry(account).or_default().insert(event_id);
+                            }
+                        }
+                    }
+                    // Messages in threads are not part of the main timeline
+                    Response::Relation((_, related)) => {
+                        if account == self.active_account || !self.synced.contains(&account) {
+                            return false;
+                        }
+                        let is_own = match self.accounts.get(&account) {
+                            Some(session) => related.sender.as_str() == session.user_id,
+                            None => return false,
+                        };
+                        if let Relation::Thread(..) = related.relation {
+                            if !is_own {
+                                self.unread
+                                    .entry(account)
+                                    .or_default()
+                                    .insert(related.event_id);
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -114,13 +262,92 @@ impl Component for App {
     }
 
     fn view(&self) -> Html {
+        let account = self.active_account.clone();
+        let login = html! { <Login key=account.clone() account=account.clone() /> };
         match &self.route {
-            None => html! { <Login /> },
+            None => login,
             Some(route) => match AppRoute::switch(route.clone()) {
-                Some(AppRoute::MainView) => html! { <MainView /> },
-                Some(AppRoute::Login) => html! { <Login /> },
+                // An account that is about to log in has nothing to show yet
+                Some(AppRoute::MainView) if !self.accounts.contains_key(&account) => login,
+                Some(AppRoute::MainView) => html! {
+                    <MainView
+                        key=account.clone()
+                        account=account
+                        accounts=self.account_summaries()
+                        switch_account=self.link.callback(Msg::SwitchAccount)
+                        add_account=self.link.callback(|_| Msg::AddAccount)
+                    />
+                },
+                Some(AppRoute::Login) => login,
+                Some(AppRoute::Register) => {
+                    html! { <Register key=account.clone() account=account /> }
+                }
+                Some(AppRoute::Settings) => {
+                    html! { <Settings key=account.clone() account=account /> }
+                }
+                Some(AppRoute::Explore) => {
+                    html! { <Explore key=account.clone() account=account /> }
+                }
                 None => VNode::from("404"),
             },
         }
     }
 }
+
+impl App {
+    fn set_active_account(&mut self, account: AccountId) {
+        let mut storage = self.storage.lock().unwrap();
+        storage.store(ACTIVE_ACCOUNT_KEY, Json(&account));
+        self.active_account = account;
+    }
+
+    fn store_accounts(&self) {
+        let mut storage = self.storage.lock().unwrap();
+        storage.store(ACCOUNTS_KEY, Json(&self.accounts));
+    }
+
+    fn account_summaries(&self) -> Vec<AccountSummary> {
+        self.accounts
+            .iter()
+            .map(|(id, session)| AccountSummary {
+                id: id.clone(),
+                user_id: session.user_id.clone(),
+                unread: self.unread.get(id).map_or(0, HashSet::len),
+            })
+            .collect()
+    }
+}
+
+/// Loads the stored accounts, including the single session older versions stored
+fn restore_accounts(storage: &mut StorageService) -> BTreeMap<AccountId, SessionStore> {
+    if let Json(Ok(accounts)) = storage.restore(ACCOUNTS_KEY) {
+        return accounts;
+    }
+
+    let mut accounts = BTreeMap::new();
+    if let Json(Ok(session)) = storage.restore(AUTH_KEY) {
+        accounts.insert(new_account_id(), session);
+        storage.store(ACCOUNTS_KEY, Json(&accounts));
+        storage.remove(AUTH_KEY);
+    }
+    accounts
+}
+
+/// Takes the `loginToken` the homeserver appends when redirecting back from SSO out of the URL
+fn take_sso_login_token() -> Option<String> {
+    let window = web_sys::window()?;
+    let mut url = Url::parse(&window.location().href().ok()?).ok()?;
+    let login_token = url
+        .query_pairs()
+        .find(|(key, _)| key == "loginToken")
+        .map(|(_, value)| value.into_owned())?;
+
+    // Make sure a reload doesn't try to use the token again
+    url.set_query(None);
+    if let Ok(history) = window.history() {
+        if let Err(e) = history.replace_state_with_url(&JsValue::NULL, "", Some(url.as_str())) {
+            error!("Failed to remove the login token from the URL: {:?}", e);
+        }
+    }
+    Some(login_token)
+}
@@ -0,0 +1,387 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use matrix_sdk::identifiers::{RoomId, RoomIdOrAliasId, ServerName};
+use yew::prelude::*;
+use yew_router::components::RouterAnchor;
+
+use tr::tr;
+
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    directory::{PublicRoom, PublicRoomsQuery},
+    Request, Response,
+};
+use crate::app::AppRoute;
+use crate::errors::MatrixError;
+
+/// Browses the public room directory of a server and joins rooms by address
+pub struct Explore {
+    link: ComponentLink<Self>,
+    state: State,
+    matrix_agent: AccountBridge,
+}
+
+pub enum Msg {
+    NewMessage(Response),
+    SetServer(String),
+    SetSearchTerm(String),
+    Search,
+    LoadMore,
+    SetAddress(String),
+    JoinAddress,
+    Join(PublicRoom),
+}
+
+#[derive(Default)]
+pub struct State {
+    server: String,
+    search_term: String,
+    /// The query of the rooms shown right now, without the page token
+    query: PublicRoomsQuery,
+    rooms: Vec<PublicRoom>,
+    next_batch: Option<String>,
+    loading: bool,
+    address: String,
+    joining: bool,
+    joined: HashSet<RoomId>,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+}
+
+impl Component for Explore {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let matrix_callback = link.callback(Msg::NewMessage);
+        let mut matrix_agent = AccountBridge::new(props.account, matrix_callback);
+        matrix_agent.send(Request::GetPublicRooms(Default::default()));
+        Explore {
+            link,
+            state: State {
+                loading: true,
+                ..Default::default()
+            },
+            matrix_agent,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::NewMessage(Response::PublicRooms(mut page)) => {
+                let since = page.query.since.take();
+                // Pages of an older search might arrive late
+                if page.query != self.state.query {
+                    return false;
+                }
+                if since.is_none() {
+                    self.state.rooms.clear();
+                }
+                self.state.rooms.append(&mut page.rooms);
+                self.state.next_batch = page.next_batch;
+                self.state.loading = false;
+            }
+            Msg::NewMessage(Response::RoomJoined(room_id)) => {
+                self.state.joining = false;
+                self.state.joined.insert(room_id);
+            }
+            Msg::NewMessage(Response::Error(e)) if self.state.loading || self.state.joining => {
+                self.state.loading = false;
+                self.state.joining = false;
+                self.state.error = Some(e.to_string());
+            }
+            Msg::NewMessage(_) => return false,
+            Msg::SetServer(server) => self.state.server = server,
+            Msg::SetSearchTerm(search_term) => self.state.search_term = search_term,
+            Msg::Search => {
+                self.state.query = PublicRoomsQuery {
+                    server: non_empty(&self.state.server),
+                    search_term: non_empty(&self.state.search_term),
+                    since: None,
+                };
+                self.state.rooms.clear();
+                self.state.next_batch = None;
+                self.state.loading = true;
+                self.state.error = None;
+                self.matrix_agent
+                    .send(Request::GetPublicRooms(self.state.query.clone()));
+            }
+            Msg::LoadMore => {
+                let query = PublicRoomsQuery {
+                    since: self.state.next_batch.take(),
+                    ..self.state.query.clone()
+                };
+                self.state.loading = true;
+                self.matrix_agent.send(Request::GetPublicRooms(query));
+            }
+            Msg::SetAddress(address) => self.state.address = address,
+            Msg::JoinAddress => {
+                let address = self.state.address.trim();
+                match RoomIdOrAliasId::try_from(address) {
+                    Ok(room) => {
+                        // The server of the address knows the room for sure
+                        let server_names = vec![room.server_name().into()];
+                        self.join(room, server_names);
+                        self.state.address.clear();
+                    }
+                    Err(_) => {
+                        let error = MatrixError::InvalidRoomAddress(address.to_string());
+                        self.state.error = Some(error.to_string());
+                    }
+                }
+            }
+            Msg::Join(room) => {
+                // Our homeserver might not be in rooms of other servers yet
+                let server_names = self
+                    .state
+                    .query
+                    .server
+                    .as_deref()
+                    .and_then(|server| <Box<ServerName>>::try_from(server).ok())
+                    .into_iter()
+                    .collect();
+                let address = match room.canonical_alias {
+                    Some(alias) => alias.into(),
+                    None => room.room_id.into(),
+                };
+                self.join(address, server_names);
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let error = match &self.state.error {
+            Some(error) => html! {
+                <h4 class="error">
+                    {
+                        tr!(
+                            // {0} is the Error that happened while exploring rooms
+                            "Error: {0}",
+                            error
+                        )
+                    }
+                </h4>
+            },
+            None => html! {},
+        };
+
+        let rooms: Html = self
+            .state
+            .rooms
+            .iter()
+            .map(|room| self.view_room(room))
+            .collect();
+        let footer = if self.state.loading {
+            html! { <p>{ tr!("Loading…") }</p> }
+        } else if self.state.next_batch.is_some() {
+            html! {
+                <button class="uk-button uk-button-default" onclick=self.link.callback(|_| Msg::LoadMore)>
+                    {
+                        tr!(
+                            // Button to load the next page of the room directory
+                            "Load more"
+                        )
+                    }
+                </button>
+            }
+        } else if self.state.rooms.is_empty() {
+            html! {
+                <p>
+                    {
+                        tr!(
+                            // Shown if the room directory has no matching rooms
+                            "No rooms found."
+                        )
+                    }
+                </p>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <div class="scrollable explore uk-padding">
+                <RouterAnchor<AppRoute> route=AppRoute::MainView>
+                    <span class="material-icons">{"arrow_back"}</span>
+                </RouterAnchor<AppRoute>>
+                <h1>
+                    {
+                        tr!(
+                            // Title of the room directory page
+                            "Explore rooms"
+                        )
+                    }
+                </h1>
+                { error }
+
+                <form
+                    class="uk-grid-small"
+                    uk-grid=""
+                    onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::JoinAddress })
+                >
+                    <div class="uk-width-expand">
+                        <input
+                            class="uk-input"
+                            placeholder={
+                                tr!(
+                                    // Placeholder of the input to join a room by its address
+                                    "#room:example.org or !id:example.org"
+                                )
+                            }
+                            value=&self.state.address
+                            oninput=self.link.callback(|e: InputData| Msg::SetAddress(e.value))
+                        />
+                    </div>
+                    <div class="uk-width-auto">
+                        <button class="uk-button uk-button-primary" disabled=self.state.joining>
+                            {
+                                tr!(
+                                    // Button to join the room with the typed address
+                                    "Join"
+                                )
+                            }
+                        </button>
+                    </div>
+                </form>
+
+                <form
+                    class="uk-grid-small explore-search"
+                    uk-grid=""
+                    onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::Search })
+                >
+                    <div class="uk-width-1-3">
+                        <input
+                            class="uk-input"
+                            placeholder={
+                                tr!(
+                                    // Placeholder of the server input of the room directory
+                                    "Server (default: your homeserver)"
+                                )
+                            }
+                            value=&self.state.server
+                            oninput=self.link.callback(|e: InputData| Msg::SetServer(e.value))
+                        />
+                    </div>
+                    <div class="uk-width-expand">
+                        <input
+                            class="uk-input"
+                            type="search"
+                            placeholder={
+                                tr!(
+                                    // Placeholder of the search input of the room directory
+                                    "Search rooms"
+                                )
+                            }
+                            value=&self.state.search_term
+                            oninput=self.link.callback(|e: InputData| Msg::SetSearchTerm(e.value))
+                        />
+                    </div>
+                    <div class="uk-width-auto">
+                        <button class="uk-button uk-button-default">
+                            <span class="material-icons">{"search"}</span>
+                        </button>
+                    </div>
+                </form>
+
+                <ul class="uk-list uk-list-divider public-rooms">{rooms}</ul>
+                { footer }
+            </div>
+        }
+    }
+}
+
+impl Explore {
+    fn join(&mut self, room: RoomIdOrAliasId, server_names: Vec<Box<ServerName>>) {
+        self.state.joining = true;
+        self.state.error = None;
+        self.matrix_agent
+            .send(Request::JoinRoom((room, server_names)));
+    }
+
+    //noinspection RsTypeCheck
+    fn view_room(&self, room: &PublicRoom) -> Html {
+        let avatar = match &room.avatar_url {
+            Some(url) => html! { <img class="avatar" src=url /> },
+            None => {
+                // "Daydream" becomes "D"
+                let initial = room
+                    .display_name()
+                    .trim_start_matches(|c| c == '#' || c == '!')
+                    .chars()
+                    .next()
+                    .map(|c| c.to_uppercase().to_string())
+                    .unwrap_or_default();
+                html! { <span class="avatar avatar-initial">{initial}</span> }
+            }
+        };
+        let alias = match &room.canonical_alias {
+            Some(alias) if room.name.is_some() => {
+                html! { <p class="uk-text-meta">{alias.to_string()}</p> }
+            }
+            _ => html! {},
+        };
+        let topic = match &room.topic {
+            Some(topic) => html! { <p class="topic">{topic}</p> },
+            None => html! {},
+        };
+        let join = if self.state.joined.contains(&room.room_id) {
+            html! {
+                <span class="uk-text-success">
+                    {
+                        tr!(
+                            // Shown instead of the join button once the room is joined
+                            "Joined"
+                        )
+                    }
+                </span>
+            }
+        } else {
+            let public_room = room.clone();
+            html! {
+                <button
+                    class="uk-button uk-button-primary uk-button-small"
+                    disabled=self.state.joining
+                    onclick=self.link.callback(move |_| Msg::Join(public_room.clone()))
+                >
+                    { tr!("Join") }
+                </button>
+            }
+        };
+
+        html! {
+            <li class="public-room">
+                { avatar }
+                <div class="uk-width-expand">
+                    <h4>{room.display_name()}</h4>
+                    { alias }
+                    { topic }
+                    <p class="uk-text-meta">
+                        {
+                            tr!(
+                                // Member count of a room in the room directory
+                                "{0} members",
+                                room.num_joined_members
+                            )
+                        }
+                    </p>
+                </div>
+                { join }
+            </li>
+        }
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
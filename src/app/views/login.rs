@@ -4,18 +4,28 @@ use std::time::Duration;
 
 use log::*;
 use serde::{Deserialize, Serialize};
+use yew::format::Json;
 use yew::prelude::*;
+use yew::services::storage::Area;
+use yew::services::StorageService;
+use yew_router::components::RouterAnchor;
 
 use tr::tr;
 
 use crate::app::components::raw_html::RawHTML;
-use crate::app::matrix::{MatrixAgent, Request, Response};
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    login::{sso_redirect_url, LoginFlows},
+    Request, Response,
+};
+use crate::app::AppRoute;
+use crate::constants::SSO_HOMESERVER_KEY;
 use crate::errors::{Field, MatrixError};
 
 pub struct Login {
     link: ComponentLink<Self>,
     state: State,
-    matrix_agent: Box<dyn Bridge<MatrixAgent>>,
+    matrix_agent: AccountBridge,
 }
 
 pub enum Msg {
@@ -23,7 +33,10 @@ pub enum Msg {
     SetHomeserver(String),
     SetUsername(String),
     SetPassword(String),
+    FetchLoginFlows,
+    DiscoverHomeserver,
     Login,
+    LoginSso,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -35,15 +48,22 @@ pub struct State {
     error: Option<String>,
     error_field: Option<Field>,
     retries: u64,
+    login_flows: Option<LoginFlows>,
+    login_after_discovery: bool,
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
 }
 
 impl Component for Login {
     type Message = Msg;
-    type Properties = ();
+    type Properties = Props;
 
-    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let matrix_callback = link.callback(Msg::NewMessage);
-        let matrix_agent = MatrixAgent::bridge(matrix_callback);
+        let matrix_agent = AccountBridge::new(props.account, matrix_callback);
         let state = State {
             loading: false,
             homeserver: "".to_string(),
@@ -52,6 +72,8 @@ impl Component for Login {
             error: None,
             error_field: None,
             retries: 0,
+            login_flows: None,
+            login_after_discovery: false,
         };
         Login {
             link,
@@ -78,15 +100,92 @@ impl Component for Login {
                 true
             }
             Msg::Login => {
+                // Enter submits the form even if the server only offers SSO
+                if !self.shows_password_login() {
+                    return false;
+                }
+
                 // Reset Errors
                 self.state.error = None;
                 self.state.error_field = None;
 
+                let missing_field = if self.state.username.is_empty() {
+                    Some(Field::MXID)
+                } else if self.state.password.is_empty() {
+                    Some(Field::Password)
+                } else {
+                    None
+                };
+                if let Some(field) = missing_field {
+                    self.state.error = Some(MatrixError::MissingFields(field.clone()).to_string());
+                    self.state.error_field = Some(field);
+                    return true;
+                }
+
                 // Start loading
-                self.matrix_agent.send(Request::Login);
                 self.state.loading = true;
+                if self.state.homeserver.is_empty() && self.state.username.starts_with('@') {
+                    // Login once we know where to
+                    self.state.login_after_discovery = true;
+                    self.matrix_agent
+                        .send(Request::DiscoverHomeserver(self.state.username.clone()));
+                } else {
+                    self.matrix_agent.send(Request::Login);
+                }
+                true
+            }
+            Msg::DiscoverHomeserver => {
+                if self.state.homeserver.is_empty() && self.state.username.starts_with('@') {
+                    self.matrix_agent
+                        .send(Request::DiscoverHomeserver(self.state.username.clone()));
+                }
+                false
+            }
+            Msg::NewMessage(Response::HomeserverDiscovered(homeserver)) => {
+                self.state.homeserver = homeserver.clone();
+                self.matrix_agent.send(Request::SetHomeserver(homeserver));
+                self.link.send_message(Msg::FetchLoginFlows);
+                if self.state.login_after_discovery {
+                    self.state.login_after_discovery = false;
+                    self.matrix_agent.send(Request::Login);
+                }
                 true
             }
+            Msg::FetchLoginFlows => {
+                self.state.login_flows = None;
+                self.matrix_agent.send(Request::GetLoginFlows);
+                false
+            }
+            Msg::NewMessage(Response::LoginFlows(login_flows)) => {
+                self.state.login_flows = Some(login_flows);
+                true
+            }
+            Msg::LoginSso => {
+                let window = web_sys::window().unwrap();
+                let location = window.location();
+                let redirect_url = format!(
+                    "{}{}",
+                    location.origin().unwrap(),
+                    location.pathname().unwrap()
+                );
+                match sso_redirect_url(&self.state.homeserver, &redirect_url) {
+                    Ok(sso_url) => {
+                        // We need to know where to send the login token to after the redirect
+                        let mut storage = StorageService::new(Area::Local)
+                            .expect("storage was disabled by the user");
+                        storage.store(SSO_HOMESERVER_KEY, Json(&self.state.homeserver));
+                        if let Err(e) = location.set_href(sso_url.as_str()) {
+                            error!("Failed to redirect to SSO: {:?}", e);
+                        }
+                        false
+                    }
+                    Err(e) => {
+                        self.state.error = Some(e.to_string());
+                        self.state.error_field = Some(Field::Homeserver);
+                        true
+                    }
+                }
+            }
             Msg::NewMessage(Response::Error(error)) => {
                 match &error {
                     MatrixError::MissingFields(field) => {
@@ -95,6 +194,22 @@ impl Component for Login {
                         self.state.error_field = Some(field.clone());
                         true
                     }
+                    MatrixError::InvalidMXID(_) => {
+                        self.state.loading = false;
+                        self.state.login_after_discovery = false;
+                        self.state.error = Some(error.to_string());
+                        self.state.error_field = Some(Field::MXID);
+                        true
+                    }
+                    MatrixError::DiscoveryFailed(_)
+                    | MatrixError::DiscoveryInvalid(_)
+                    | MatrixError::HomeserverInvalid(_) => {
+                        self.state.loading = false;
+                        self.state.login_after_discovery = false;
+                        self.state.error = Some(error.to_string());
+                        self.state.error_field = Some(Field::Homeserver);
+                        true
+                    }
                     MatrixError::LoginTimeout => {
                         // If we had less than 10 tries try again
                         if self.state.retries < 10 {
@@ -163,6 +278,84 @@ impl Component for Login {
                 None => html! {},
             };
 
+            let password_login = if self.shows_password_login() {
+                html! {
+                    <>
+                        <div class="login-inline">
+                           <span class="material-icons login-icons" id="ma-icon">{"person"}</span>
+                            <input
+                                required=true
+                                pattern="^(@[\x21-\x39\x3B-\x7E]+:.*|[\x21-\x39\x3B-\x7E]+)$"
+                                class=mxid_classes
+                                id="username"
+                                name="username"
+                                placeholder=
+                                {
+                                    tr!(
+                                        // The Matrix ID Field of the Login page
+                                        "MXID"
+                                    )
+                                }
+                                value=&self.state.username
+                                oninput=self.link.callback(|e: InputData| Msg::SetUsername(e.value))
+                                onchange=self.link.callback(|_| Msg::DiscoverHomeserver)
+                            />
+                        </div>
+                        <div class="login-inline">
+                           <span class="material-icons login-icons" id="ma-icon">{"vpn_key"}</span>
+                            <input
+                                required=true
+                                class=password_classes
+                                type="password"
+                                id="password"
+                                name="password"
+                                placeholder=
+                                {
+                                    tr!(
+                                        // The Password Field of the Login page
+                                        "Password"
+                                    )
+                                }
+                                value=&self.state.password
+                                oninput=self.link.callback(|e: InputData| Msg::SetPassword(e.value))
+                            />
+                        </div>
+
+                        <button class="login-button">
+                            {
+                                tr!(
+                                    // The Login Button of the Login page
+                                    "Login"
+                                )
+                            }
+                        </button>
+                    </>
+                }
+            } else {
+                html! {}
+            };
+
+            let sso_login = if self
+                .state
+                .login_flows
+                .as_ref()
+                .map(|flows| flows.sso)
+                .unwrap_or(false)
+            {
+                html! {
+                    <button class="login-button" onclick=self.link.callback(|_| Msg::LoginSso)>
+                        {
+                            tr!(
+                                // The SSO Button of the Login page
+                                "Login with SSO"
+                            )
+                        }
+                    </button>
+                }
+            } else {
+                html! {}
+            };
+
             html! {
                 <>
                     <div class="login-page-bg"></div>
@@ -186,7 +379,6 @@ impl Component for Login {
                                            <span class="material-icons login-icons" id="ma-icon" style="font-size: 28px !important;">{"http"}</span>
                                             <input
                                                 pattern="^https:\\/\\/.*$"
-                                                required=true
                                                 class=homeserver_classes
                                                 type="url"
                                                 id="homeserver"
@@ -194,62 +386,26 @@ impl Component for Login {
                                                 placeholder=
                                                 {
                                                     tr!(
-                                                        // The URL Field of the Login page
-                                                        "Homeserver URL"
+                                                        // The URL Field of the Login page. It gets filled in from the MXID if left empty
+                                                        "Homeserver URL (optional with a full MXID)"
                                                     )
                                                 }
                                                 value=&self.state.homeserver
                                                 oninput=self.link.callback(|e: InputData| Msg::SetHomeserver(e.value))
+                                                onchange=self.link.callback(|_| Msg::FetchLoginFlows)
                                             />
                                         </div>
-                                        <div class="login-inline">
-                                           <span class="material-icons login-icons" id="ma-icon">{"person"}</span>
-                                            <input
-                                                required=true
-                                                pattern="^(@[\x21-\x39\x3B-\x7E]+:.*|[\x21-\x39\x3B-\x7E]+)$"
-                                                class=mxid_classes
-                                                id="username"
-                                                name="username"
-                                                placeholder=
-                                                {
-                                                    tr!(
-                                                        // The Matrix ID Field of the Login page
-                                                        "MXID"
-                                                    )
-                                                }
-                                                value=&self.state.username
-                                                oninput=self.link.callback(|e: InputData| Msg::SetUsername(e.value))
-                                            />
-                                        </div>
-                                        <div class="login-inline">
-                                           <span class="material-icons login-icons" id="ma-icon">{"vpn_key"}</span>
-                                            <input
-                                                required=true
-                                                class=password_classes
-                                                type="password"
-                                                id="password"
-                                                name="password"
-                                                placeholder=
-                                                {
-                                                    tr!(
-                                                        // The Password Field of the Login page
-                                                        "Password"
-                                                    )
-                                                }
-                                                value=&self.state.password
-                                                oninput=self.link.callback(|e: InputData| Msg::SetPassword(e.value))
-                                            />
-                                        </div>
-
-                                        <button class="login-button">
-                                            {
-                                                tr!(
-                                                    // The Login Button of the Login page
-                                                    "Login"
-                                                )
-                                            }
-                                        </button>
+                                        { password_login }
                                     </form>
+                                    { sso_login }
+                                    <RouterAnchor<AppRoute> route=AppRoute::Register>
+                                        {
+                                            tr!(
+                                                // Link from the Login page to the Register page
+                                                "Create an account"
+                                            )
+                                        }
+                                    </RouterAnchor<AppRoute>>
                                 </div>
                             </div>
                         </div>
@@ -259,3 +415,14 @@ impl Component for Login {
         }
     }
 }
+
+impl Login {
+    /// Only hide the password login if we know for sure that the server doesn't support it
+    fn shows_password_login(&self) -> bool {
+        self.state
+            .login_flows
+            .as_ref()
+            .map(|flows| flows.password)
+            .unwrap_or(true)
+    }
+}
@@ -5,19 +5,23 @@ use matrix_sdk::Room;
 use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 use yew::ComponentLink;
+use yewtil::NeqAssign;
 
-use tr::tr;
-
-use crate::app::components::{event_list::EventList, room_list::RoomList};
-use crate::app::matrix::{MatrixAgent, Request};
+use crate::app::components::{
+    event_list::EventList, key_restore::KeyRestoreModal, room_list::RoomList,
+    verification::VerificationModal,
+};
+use crate::app::matrix::account::{AccountId, AccountSummary};
 
 pub struct MainView {
     link: ComponentLink<Self>,
     state: State,
+    props: Props,
 }
 
 pub enum Msg {
     ChangeRoom(Rc<Room>),
+    CloseRoom,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -26,19 +30,28 @@ pub struct State {
     pub current_room_displayname: String,
 }
 
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+    #[prop_or_default]
+    pub accounts: Vec<AccountSummary>,
+    #[prop_or_default]
+    pub switch_account: Callback<AccountId>,
+    #[prop_or_default]
+    pub add_account: Callback<()>,
+}
+
 impl Component for MainView {
     type Message = Msg;
-    type Properties = ();
+    type Properties = Props;
 
-    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let mut matrix_agent = MatrixAgent::dispatcher();
-        matrix_agent.send(Request::StartSync);
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let state = State {
             current_room: None,
             current_room_displayname: Default::default(),
         };
 
-        MainView { link, state }
+        MainView { link, state, props }
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
@@ -47,50 +60,55 @@ impl Component for MainView {
                 info!("Changing room to: {}", room.room_id);
                 self.state.current_room = Some(room);
             }
+            Msg::CloseRoom => {
+                self.state.current_room = None;
+            }
         }
         true
     }
 
-    fn change(&mut self, _props: Self::Properties) -> bool {
-        false
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.neq_assign(props)
     }
 
     //noinspection RsTypeCheck
     fn view(&self) -> Html {
-        match &self.state.current_room {
-            None => html! {
-                <div class="uk-flex auto-scrollable-container uk-background-default" style="height: 100%">
-                    <RoomList change_room_callback=self.link.callback(Msg::ChangeRoom)/>
+        let room_list = html! {
+            <RoomList
+                account=self.props.account.clone()
+                accounts=self.props.accounts.clone()
+                change_room_callback=self.link.callback(Msg::ChangeRoom)
+                close_room_callback=self.link.callback(|_| Msg::CloseRoom)
+                switch_account=self.props.switch_account.clone()
+                add_account=self.props.add_account.clone()
+            />
+        };
 
+        let (classes, content) = match &self.state.current_room {
+            None => (
+                "uk-flex auto-scrollable-container uk-background-default",
+                html! {
                     <div class="container uk-flex uk-width-5-6 uk-padding">
                         <div class="scrollable">
                             // TODO add some content to the empty page
                         </div>
                     </div>
-                </div>
-            },
-            Some(room) if room.is_encrypted() => html! {
-                <div class="uk-flex auto-scrollable-container" style="height: 100%">
-                    <RoomList change_room_callback=self.link.callback(Msg::ChangeRoom)/>
-                    <div class="event-list">
-                        <div class="room-title"><h1>{ room.display_name() }</h1></div>
-                        <h4>
-                            {
-                                tr!(
-                                    // A warning for encrypted rooms
-                                    "Daydream currently does not support encryption."
-                                )
-                            }
-                        </h4>
-                    </div>
-                </div>
-            },
-            Some(room) => html! {
-                <div class="uk-flex auto-scrollable-container" style="height: 100%">
-                    <RoomList change_room_callback=self.link.callback(Msg::ChangeRoom)/>
-                    <EventList current_room=room />
-                </div>
-            },
+                },
+            ),
+            Some(room) => (
+                "uk-flex auto-scrollable-container",
+                html! { <EventList account=self.props.account.clone() current_room=room /> },
+            ),
+        };
+
+        // Outside of the room content so running dialogs survive switching rooms
+        html! {
+            <div class=classes style="height: 100%">
+                { room_list }
+                { content }
+                <VerificationModal account=self.props.account.clone() />
+                <KeyRestoreModal account=self.props.account.clone() />
+            </div>
         }
     }
 }
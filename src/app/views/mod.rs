@@ -1,2 +1,5 @@
+pub mod explore;
 pub mod login;
 pub mod main_view;
+pub mod register;
+pub mod settings;
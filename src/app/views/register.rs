@@ -0,0 +1,413 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+use yew_router::components::RouterAnchor;
+
+use tr::tr;
+
+use crate::app::components::raw_html::RawHTML;
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    register::{EmailToken, RegistrationAuth, RegistrationData, RegistrationStage},
+    Request, Response,
+};
+use crate::app::AppRoute;
+
+pub struct Register {
+    link: ComponentLink<Self>,
+    state: State,
+    matrix_agent: AccountBridge,
+}
+
+pub enum Msg {
+    NewMessage(Response),
+    SetHomeserver(String),
+    SetUsername(String),
+    SetPassword(String),
+    SetEmail(String),
+    ToggleTerms,
+    Register,
+    SendEmail,
+    CompleteStage(RegistrationStage),
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    loading: bool,
+    homeserver: String,
+    username: String,
+    password: String,
+    email: String,
+    terms_accepted: bool,
+    auth: Option<RegistrationAuth>,
+    email_token: Option<EmailToken>,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+}
+
+impl Component for Register {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let matrix_callback = link.callback(Msg::NewMessage);
+        let matrix_agent = AccountBridge::new(props.account, matrix_callback);
+        Register {
+            link,
+            state: Default::default(),
+            matrix_agent,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::SetHomeserver(homeserver) => {
+                self.state.homeserver = homeserver.clone();
+                self.matrix_agent.send(Request::SetHomeserver(homeserver));
+                true
+            }
+            Msg::SetUsername(username) => {
+                self.state.username = username;
+                true
+            }
+            Msg::SetPassword(password) => {
+                self.state.password = password;
+                true
+            }
+            Msg::SetEmail(email) => {
+                self.state.email = email;
+                true
+            }
+            Msg::ToggleTerms => {
+                self.state.terms_accepted = !self.state.terms_accepted;
+                true
+            }
+            Msg::Register => {
+                self.state.error = None;
+                self.state.auth = None;
+                self.state.loading = true;
+                self.matrix_agent.send(Request::Register(RegistrationData {
+                    username: self.state.username.clone(),
+                    password: self.state.password.clone(),
+                    session: None,
+                    stage: None,
+                }));
+                true
+            }
+            Msg::SendEmail => {
+                self.state.error = None;
+                self.state.loading = true;
+                self.matrix_agent
+                    .send(Request::RequestRegistrationEmail(self.state.email.clone()));
+                true
+            }
+            Msg::CompleteStage(stage) => {
+                self.state.error = None;
+                self.state.loading = true;
+                let session = self
+                    .state
+                    .auth
+                    .as_ref()
+                    .and_then(|auth| auth.session.clone());
+                self.matrix_agent.send(Request::Register(RegistrationData {
+                    username: self.state.username.clone(),
+                    password: self.state.password.clone(),
+                    session,
+                    stage: Some(stage),
+                }));
+                true
+            }
+            Msg::NewMessage(Response::RegistrationAuth(auth)) => {
+                self.state.loading = false;
+                match auth.next_stage() {
+                    // Nothing to show for this one
+                    Some("m.login.dummy") => {
+                        self.state.auth = Some(auth);
+                        self.link
+                            .send_message(Msg::CompleteStage(RegistrationStage::Dummy));
+                    }
+                    Some(stage) => {
+                        info!("Next registration stage: {}", stage);
+                        self.state.auth = Some(auth);
+                    }
+                    None => {
+                        self.state.auth = None;
+                        self.state.error = Some(tr!(
+                            // Shown if the homeserver only offers registration flows we don't support
+                            "This homeserver requires a registration step Daydream doesn't support yet."
+                        ));
+                    }
+                }
+                true
+            }
+            Msg::NewMessage(Response::RegistrationEmailSent(token)) => {
+                self.state.loading = false;
+                self.state.email_token = Some(token);
+                true
+            }
+            Msg::NewMessage(Response::Error(error)) => {
+                self.state.loading = false;
+                self.state.error = Some(error.to_string());
+                true
+            }
+            Msg::NewMessage(_) => false,
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        if self.state.loading {
+            return html! {
+                <div class="container">
+                    <div class="uk-position-center uk-padding sun-animation">
+                        <RawHTML inner_html=include_str!("../svgs/loading_animation.svg")/>
+                    </div>
+                </div>
+            };
+        }
+
+        let error = match &self.state.error {
+            Some(v) => html! {
+                <h4 class="error">
+                    {
+                        tr!(
+                            // {0} is the Error that happened on registration
+                            "Error: {0}",
+                            v
+                        )
+                    }
+                </h4>
+            },
+            None => html! {},
+        };
+
+        let content = match self
+            .state
+            .auth
+            .as_ref()
+            .and_then(|auth| auth.next_stage().map(|stage| (auth, stage)))
+        {
+            Some((auth, "m.login.terms")) => self.view_terms(auth),
+            Some((_, "m.login.email.identity")) => self.view_email(),
+            _ => self.view_form(),
+        };
+
+        html! {
+            <>
+                <div class="login-page-bg"></div>
+                <div class="scrollable" style="width:100vw; padding:2px;">
+                    <div>
+                        <div class="login-bg">
+                            <div class="daydream-title"><RawHTML inner_html=include_str!("../svgs/DaydreamLogo_v0_light.svg")/></div>
+                            <div class="login-content">
+                                <h1 class="login-title">
+                                    {
+                                        tr!(
+                                            // The title of the Register page
+                                            "Create account"
+                                        )
+                                    }
+                                </h1>
+                                { error }
+                                { content }
+                                <RouterAnchor<AppRoute> route=AppRoute::Login>
+                                    {
+                                        tr!(
+                                            // Link from the Register page back to the Login page
+                                            "I already have an account"
+                                        )
+                                    }
+                                </RouterAnchor<AppRoute>>
+                            </div>
+                        </div>
+                    </div>
+                </div>
+            </>
+        }
+    }
+}
+
+impl Register {
+    //noinspection RsTypeCheck
+    fn view_form(&self) -> Html {
+        html! {
+            <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::Register })>
+                <div class="login-inline login-input-first">
+                   <span class="material-icons login-icons" id="ma-icon" style="font-size: 28px !important;">{"http"}</span>
+                    <input
+                        pattern="^https:\\/\\/.*$"
+                        required=true
+                        class="login-input"
+                        type="url"
+                        id="homeserver"
+                        name="homeserver"
+                        placeholder=
+                        {
+                            tr!(
+                                // The URL Field of the Register page
+                                "Homeserver URL"
+                            )
+                        }
+                        value=&self.state.homeserver
+                        oninput=self.link.callback(|e: InputData| Msg::SetHomeserver(e.value))
+                    />
+                </div>
+                <div class="login-inline">
+                   <span class="material-icons login-icons" id="ma-icon">{"person"}</span>
+                    <input
+                        required=true
+                        pattern="^[a-z0-9._=\\-/]+$"
+                        class="login-input"
+                        id="username"
+                        name="username"
+                        placeholder=
+                        {
+                            tr!(
+                                // The Username Field of the Register page
+                                "Username"
+                            )
+                        }
+                        value=&self.state.username
+                        oninput=self.link.callback(|e: InputData| Msg::SetUsername(e.value))
+                    />
+                </div>
+                <div class="login-inline">
+                   <span class="material-icons login-icons" id="ma-icon">{"vpn_key"}</span>
+                    <input
+                        required=true
+                        class="login-input"
+                        type="password"
+                        id="password"
+                        name="password"
+                        placeholder=
+                        {
+                            tr!(
+                                // The Password Field of the Register page
+                                "Password"
+                            )
+                        }
+                        value=&self.state.password
+                        oninput=self.link.callback(|e: InputData| Msg::SetPassword(e.value))
+                    />
+                </div>
+
+                <button class="login-button">
+                    {
+                        tr!(
+                            // The Register Button of the Register page
+                            "Register"
+                        )
+                    }
+                </button>
+            </form>
+        }
+    }
+
+    //noinspection RsTypeCheck
+    fn view_terms(&self, auth: &RegistrationAuth) -> Html {
+        let policies: Html = auth
+            .policies
+            .iter()
+            .map(|policy| {
+                html! {
+                    <li><a href=&policy.url target="_blank" rel="noopener noreferrer">{&policy.name}</a></li>
+                }
+            })
+            .collect();
+
+        html! {
+            <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::CompleteStage(RegistrationStage::Terms) })>
+                <p>
+                    {
+                        tr!(
+                            // Shown above the policy documents of the homeserver
+                            "Please review and accept the policies of this homeserver:"
+                        )
+                    }
+                </p>
+                <ul class="uk-list">{policies}</ul>
+                <label>
+                    <input
+                        class="uk-checkbox"
+                        type="checkbox"
+                        required=true
+                        checked=self.state.terms_accepted
+                        onclick=self.link.callback(|_| Msg::ToggleTerms)
+                    />
+                    { " " }
+                    { tr!("I accept the policies") }
+                </label>
+                <button class="login-button" disabled=!self.state.terms_accepted>
+                    { tr!("Continue") }
+                </button>
+            </form>
+        }
+    }
+
+    //noinspection RsTypeCheck
+    fn view_email(&self) -> Html {
+        match &self.state.email_token {
+            Some(token) => {
+                let token = token.clone();
+                html! {
+                    <>
+                        <p>
+                            {
+                                tr!(
+                                    // {0} is the email address the validation mail was sent to
+                                    "We sent an email to {0}. Click the link in it, then continue here.",
+                                    self.state.email
+                                )
+                            }
+                        </p>
+                        <button
+                            class="login-button"
+                            onclick=self.link.callback(move |_| Msg::CompleteStage(RegistrationStage::EmailIdentity(token.clone())))
+                        >
+                            { tr!("Continue") }
+                        </button>
+                    </>
+                }
+            }
+            None => html! {
+                <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::SendEmail })>
+                    <div class="login-inline login-input-first">
+                       <span class="material-icons login-icons" id="ma-icon">{"email"}</span>
+                        <input
+                            required=true
+                            class="login-input"
+                            type="email"
+                            id="email"
+                            name="email"
+                            placeholder=
+                            {
+                                tr!(
+                                    // The Email Field of the Register page
+                                    "Email address"
+                                )
+                            }
+                            value=&self.state.email
+                            oninput=self.link.callback(|e: InputData| Msg::SetEmail(e.value))
+                        />
+                    </div>
+                    <button class="login-button">
+                        {
+                            tr!(
+                                // Button to send the validation email during registration
+                                "Send verification email"
+                            )
+                        }
+                    </button>
+                </form>
+            },
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use log::*;
+use url::form_urlencoded::byte_serialize;
+use yew::prelude::*;
+use yew::services::reader::{File, FileData, ReaderService, ReaderTask};
+use yew_router::components::RouterAnchor;
+
+use tr::tr;
+
+use crate::app::matrix::{
+    account::{AccountBridge, AccountId},
+    backup::BackupStatus,
+    Request, Response,
+};
+use crate::app::AppRoute;
+
+pub struct Settings {
+    link: ComponentLink<Self>,
+    state: State,
+    matrix_agent: AccountBridge,
+    reader: ReaderService,
+    reader_task: Option<ReaderTask>,
+}
+
+pub enum Msg {
+    NewMessage(Response),
+    SetExportPassphrase(String),
+    SetImportPassphrase(String),
+    SetImportFile(Option<File>),
+    ImportFileLoaded(FileData),
+    ExportKeys,
+    ImportKeys,
+    CreateKeyBackup,
+    SetCrossSigningPassword(String),
+    BootstrapCrossSigning,
+    Logout,
+    LogoutAllDevices,
+}
+
+#[derive(Default)]
+pub struct State {
+    loading: bool,
+    export_passphrase: String,
+    import_passphrase: String,
+    import_file: Option<String>,
+    exported_keys: Option<String>,
+    status: Option<String>,
+    error: Option<String>,
+    backup_status: BackupStatus,
+    recovery_key: Option<String>,
+    cross_signing_session: Option<String>,
+    cross_signing_password: String,
+    cross_signing_ready: bool,
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub struct Props {
+    pub account: AccountId,
+}
+
+impl Component for Settings {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let matrix_callback = link.callback(Msg::NewMessage);
+        let mut matrix_agent = AccountBridge::new(props.account, matrix_callback);
+        if cfg!(feature = "encryption") {
+            matrix_agent.send(Request::GetKeyBackupStatus);
+        }
+        Settings {
+            link,
+            state: Default::default(),
+            matrix_agent,
+            reader: ReaderService::new(),
+            reader_task: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::NewMessage(Response::KeysExported(export)) => {
+                self.state.loading = false;
+                self.state.exported_keys = Some(export);
+                self.state.status = Some(tr!(
+                    // Shown after the key export finished
+                    "Your keys were exported."
+                ));
+                true
+            }
+            Msg::NewMessage(Response::KeysImported(count)) => {
+                self.state.loading = false;
+                self.state.status = Some(tr!(
+                    // {0} is the number of imported keys
+                    "Imported {0} keys.",
+                    count
+                ));
+                true
+            }
+            Msg::NewMessage(Response::KeyBackupCreated(recovery_key)) => {
+                self.state.loading = false;
+                self.state.recovery_key = Some(recovery_key);
+                true
+            }
+            Msg::NewMessage(Response::KeyBackupStatus(backup_status)) => {
+                self.state.backup_status = backup_status;
+                true
+            }
+            Msg::NewMessage(Response::CrossSigningAuthRequired(session)) => {
+                self.state.loading = false;
+                self.state.cross_signing_session = Some(session);
+                true
+            }
+            Msg::NewMessage(Response::CrossSigningReady) => {
+                self.state.loading = false;
+                self.state.cross_signing_session = None;
+                self.state.cross_signing_password = "".to_string();
+                self.state.cross_signing_ready = true;
+                true
+            }
+            Msg::NewMessage(Response::Error(error)) => {
+                if self.state.loading {
+                    self.state.loading = false;
+                    self.state.error = Some(error.to_string());
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::NewMessage(_) => false,
+            Msg::SetExportPassphrase(passphrase) => {
+                self.state.export_passphrase = passphrase;
+                true
+            }
+            Msg::SetImportPassphrase(passphrase) => {
+                self.state.import_passphrase = passphrase;
+                true
+            }
+            Msg::SetImportFile(file) => {
+                self.state.import_file = None;
+                if let Some(file) = file {
+                    let callback = self.link.callback(Msg::ImportFileLoaded);
+                    match self.reader.read_file(file, callback) {
+                        Ok(task) => self.reader_task = Some(task),
+                        Err(e) => error!("Failed to read key file: {}", e),
+                    }
+                }
+                false
+            }
+            Msg::ImportFileLoaded(file) => {
+                self.reader_task = None;
+                match String::from_utf8(file.content) {
+                    Ok(content) => self.state.import_file = Some(content),
+                    Err(e) => self.state.error = Some(e.to_string()),
+                }
+                true
+            }
+            Msg::ExportKeys => {
+                self.state.error = None;
+                self.state.status = None;
+                self.state.exported_keys = None;
+                self.state.loading = true;
+                self.matrix_agent
+                    .send(Request::ExportKeys(self.state.export_passphrase.clone()));
+                true
+            }
+            Msg::CreateKeyBackup => {
+                self.state.error = None;
+                self.state.status = None;
+                self.state.loading = true;
+                self.matrix_agent.send(Request::CreateKeyBackup);
+                true
+            }
+            Msg::SetCrossSigningPassword(password) => {
+                self.state.cross_signing_password = password;
+                true
+            }
+            Msg::BootstrapCrossSigning => {
+                self.state.error = None;
+                self.state.status = None;
+                self.state.loading = true;
+                let auth = self
+                    .state
+                    .cross_signing_session
+                    .clone()
+                    .map(|session| (session, self.state.cross_signing_password.clone()));
+                self.matrix_agent.send(Request::BootstrapCrossSigning(auth));
+                true
+            }
+            Msg::Logout => {
+                self.state.loading = true;
+                self.matrix_agent.send(Request::Logout);
+                true
+            }
+            Msg::LogoutAllDevices => {
+                self.state.loading = true;
+                self.matrix_agent.send(Request::LogoutAllDevices);
+                true
+            }
+            Msg::ImportKeys => {
+                if let Some(import_file) = self.state.import_file.clone() {
+                    self.state.error = None;
+                    self.state.status = None;
+                    self.state.loading = true;
+                    self.matrix_agent.send(Request::ImportKeys((
+                        import_file,
+                        self.state.import_passphrase.clone(),
+                    )));
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    //noinspection RsTypeCheck
+    fn view(&self) -> Html {
+        let status = match (&self.state.error, &self.state.status) {
+            (Some(error), _) => html! {
+                <h4 class="error">
+                    {
+                        tr!(
+                            // {0} is the Error that happened in the settings
+                            "Error: {0}",
+                            error
+                        )
+                    }
+                </h4>
+            },
+            (None, Some(status)) => html! { <p class="uk-text-success">{status}</p> },
+            (None, None) => html! {},
+        };
+
+        html! {
+            <div class="scrollable settings uk-padding">
+                <RouterAnchor<AppRoute> route=AppRoute::MainView>
+                    <span class="material-icons">{"arrow_back"}</span>
+                </RouterAnchor<AppRoute>>
+                <h1>
+                    {
+                        tr!(
+                            // Title of the settings page
+                            "Settings"
+                        )
+                    }
+                </h1>
+                { status }
+
+                { self.view_encryption() }
+
+                <h3>
+                    {
+                        tr!(
+                            // Title of the logout section
+                            "Log out"
+                        )
+                    }
+                </h3>
+                <p>
+                    {
+                        tr!(
+                            // Explanation of the logout buttons
+                            "Make sure you have a key backup or exported your room keys, otherwise you can't read your encrypted messages after logging in again."
+                        )
+                    }
+                </p>
+                <button class="uk-button uk-button-danger" disabled=self.state.loading onclick=self.link.callback(|_| Msg::Logout)>
+                    {
+                        tr!(
+                            // Button to log out of this account on this device
+                            "Log out"
+                        )
+                    }
+                </button>
+                <button class="uk-button uk-button-default" disabled=self.state.loading onclick=self.link.callback(|_| Msg::LogoutAllDevices)>
+                    {
+                        tr!(
+                            // Logs out every session of the account, not only this one
+                            "Log out all devices"
+                        )
+                    }
+                </button>
+            </div>
+        }
+    }
+}
+
+impl Settings {
+    /// Backup, cross-signing and the key export, which only exist with encryption support
+    //noinspection RsTypeCheck
+    fn view_encryption(&self) -> Html {
+        if !cfg!(feature = "encryption") {
+            return html! {};
+        }
+
+        let download = match &self.state.exported_keys {
+            Some(export) => {
+                let href = format!(
+                    "data:text/plain;charset=utf-8,{}",
+                    byte_serialize(export.as_bytes()).collect::<String>()
+                );
+                html! {
+                    <a class="uk-button uk-button-default" href=href download="daydream-keys.txt">
+                        {
+                            tr!(
+                                // Button to save the exported keys as a file
+                                "Download key file"
+                            )
+                        }
+                    </a>
+                }
+            }
+            None => html! {},
+        };
+
+        let backup_status = &self.state.backup_status;
+        let backup = match &backup_status.version {
+            Some(version) => {
+                let progress = if backup_status.total_keys == 0 {
+                    100
+                } else {
+                    backup_status.backed_up_keys * 100 / backup_status.total_keys
+                };
+                html! {
+                    <>
+                        <p>
+                            {
+                                tr!(
+                                    // {0} is the backup version, {1} the backed up keys and {2} all keys
+                                    "Backup version {0}: {1} of {2} keys backed up.",
+                                    version,
+                                    backup_status.backed_up_keys,
+                                    backup_status.total_keys
+                                )
+                            }
+                        </p>
+                        <progress class="uk-progress" value=progress max="100"></progress>
+                    </>
+                }
+            }
+            None => html! {
+                <>
+                    <p>
+                        {
+                            tr!(
+                                // Explanation of the key backup
+                                "Your keys are not backed up. Set up a backup on your homeserver to not lose access to encrypted messages when you log out."
+                            )
+                        }
+                    </p>
+                    <button class="uk-button uk-button-primary" disabled=self.state.loading onclick=self.link.callback(|_| Msg::CreateKeyBackup)>
+                        {
+                            tr!(
+                                // Button to create a new key backup
+                                "Set up backup"
+                            )
+                        }
+                    </button>
+                </>
+            },
+        };
+        let recovery_key = match &self.state.recovery_key {
+            Some(recovery_key) => html! {
+                <div class="uk-alert-primary" uk-alert="">
+                    <p>
+                        {
+                            tr!(
+                                // Shown above the recovery key after creating a backup
+                                "Store this recovery key somewhere safe. You need it to restore your keys:"
+                            )
+                        }
+                    </p>
+                    <code class="recovery-key">{recovery_key}</code>
+                </div>
+            },
+            None => html! {},
+        };
+
+        let cross_signing = if self.state.cross_signing_ready {
+            html! {
+                <p class="uk-text-success">
+                    {
+                        tr!(
+                            // Shown after cross-signing was set up
+                            "Cross-signing is set up."
+                        )
+                    }
+                </p>
+            }
+        } else if self.state.cross_signing_session.is_some() {
+            html! {
+                <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::BootstrapCrossSigning })>
+                    <p>
+                        {
+                            tr!(
+                                // Shown when the homeserver wants the password to set up cross-signing
+                                "Confirm with your password to set up cross-signing."
+                            )
+                        }
+                    </p>
+                    <input
+                        class="uk-input uk-form-width-large"
+                        type="password"
+                        required=true
+                        disabled=self.state.loading
+                        placeholder=
+                        {
+                            tr!(
+                                // The account password the homeserver asks for to set up cross-signing
+                                "Password"
+                            )
+                        }
+                        value=&self.state.cross_signing_password
+                        oninput=self.link.callback(|e: InputData| Msg::SetCrossSigningPassword(e.value))
+                    />
+                    <button class="uk-button uk-button-primary" disabled=self.state.loading>
+                        {
+                            tr!(
+                                // Button to send the account password the homeserver asked for to set up cross-signing
+                                "Continue"
+                            )
+                        }
+                    </button>
+                </form>
+            }
+        } else {
+            html! {
+                <>
+                    <p>
+                        {
+                            tr!(
+                                // Explanation of cross-signing
+                                "Cross-signing lets you verify users once instead of every single session they use."
+                            )
+                        }
+                    </p>
+                    <button class="uk-button uk-button-primary" disabled=self.state.loading onclick=self.link.callback(|_| Msg::BootstrapCrossSigning)>
+                        {
+                            tr!(
+                                // Button to create the cross-signing keys
+                                "Set up cross-signing"
+                            )
+                        }
+                    </button>
+                </>
+            }
+        };
+
+        html! {
+            <>
+                <h3>
+                    {
+                        tr!(
+                            // Title of the key backup section
+                            "Secure backup"
+                        )
+                    }
+                </h3>
+                { backup }
+                { recovery_key }
+
+                <h3>
+                    {
+                        tr!(
+                            // Title of the cross-signing section
+                            "Cross-signing"
+                        )
+                    }
+                </h3>
+                { cross_signing }
+
+                <h3>
+                    {
+                        tr!(
+                            // Title of the key export section
+                            "Export room keys"
+                        )
+                    }
+                </h3>
+                <p>
+                    {
+                        tr!(
+                            // Explanation of the key export
+                            "Export the keys for messages in encrypted rooms to a file protected by a passphrase. You can import it in another Matrix client to read your message history there."
+                        )
+                    }
+                </p>
+                <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::ExportKeys })>
+                    <input
+                        class="uk-input uk-form-width-large"
+                        type="password"
+                        required=true
+                        disabled=self.state.loading
+                        placeholder={
+                            tr!(
+                                // The passphrase that protects the exported key file
+                                "Passphrase"
+                            )
+                        }
+                        value=&self.state.export_passphrase
+                        oninput=self.link.callback(|e: InputData| Msg::SetExportPassphrase(e.value))
+                    />
+                    <button class="uk-button uk-button-primary" disabled=self.state.loading>
+                        {
+                            tr!(
+                                // Button to export the room keys to a file
+                                "Export"
+                            )
+                        }
+                    </button>
+                </form>
+                { download }
+
+                <h3>
+                    {
+                        tr!(
+                            // Title of the key import section
+                            "Import room keys"
+                        )
+                    }
+                </h3>
+                <form onsubmit=self.link.callback(|e: FocusEvent| { e.prevent_default(); Msg::ImportKeys })>
+                    <input
+                        type="file"
+                        required=true
+                        disabled=self.state.loading
+                        onchange=self.link.callback(|e: ChangeData| match e {
+                            ChangeData::Files(files) => Msg::SetImportFile(files.get(0)),
+                            _ => Msg::SetImportFile(None),
+                        })
+                    />
+                    <input
+                        class="uk-input uk-form-width-large"
+                        type="password"
+                        required=true
+                        disabled=self.state.loading
+                        placeholder={
+                            tr!(
+                                // The passphrase the key file was protected with when it was exported
+                                "Passphrase"
+                            )
+                        }
+                        value=&self.state.import_passphrase
+                        oninput=self.link.callback(|e: InputData| Msg::SetImportPassphrase(e.value))
+                    />
+                    <button class="uk-button uk-button-primary" disabled={ self.state.loading || self.state.import_file.is_none() }>
+                        {
+                            tr!(
+                                // Button to import the keys from the chosen file
+                                "Import"
+                            )
+                        }
+                    </button>
+                </form>
+            </>
+        }
+    }
+}
@@ -1 +1,4 @@
 pub const AUTH_KEY: &str = "nordgedanken.daydream.auth_data";
+pub const SSO_HOMESERVER_KEY: &str = "nordgedanken.daydream.sso_homeserver";
+pub const ACCOUNTS_KEY: &str = "nordgedanken.daydream.accounts";
+pub const ACTIVE_ACCOUNT_KEY: &str = "nordgedanken.daydream.active_account";
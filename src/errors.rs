@@ -41,4 +41,44 @@ pub enum MatrixError {
     /// This can't use transparent as we need Serialize, Deserialize
     #[error("An error occurred in the URL parse library: `{0}`")]
     UrlParseError(String),
+
+    #[error("`{0}` is not a valid Matrix ID")]
+    InvalidMXID(String),
+
+    /// The homeserver registered the account without creating a device for us
+    #[error("The homeserver did not create a session for the new account")]
+    MissingDeviceId,
+
+    #[error("`{0}` is not a valid server name")]
+    InvalidServerName(String),
+
+    /// Neither a `#alias:server` nor a `!room:server` id
+    #[error("`{0}` is not a valid room address")]
+    InvalidRoomAddress(String),
+
+    /// `/.well-known/matrix/client` couldn't be fetched for the server name
+    #[error("Could not find the homeserver of `{0}`, please enter it manually")]
+    DiscoveryFailed(String),
+
+    /// `/.well-known/matrix/client` points to an invalid URL
+    #[error("The homeserver URL `{0}` is invalid")]
+    DiscoveryInvalid(String),
+
+    /// `/_matrix/client/versions` failed for the discovered URL
+    #[error("`{0}` is not a Matrix homeserver")]
+    HomeserverInvalid(String),
+
+    /// Daydream was built without the `encryption` feature
+    #[error("Encrypted rooms are not supported yet")]
+    EncryptionUnsupported,
+
+    #[error("There is no key backup on the server")]
+    MissingKeyBackup,
+
+    /// Neither our master key nor one of our verified sessions signed the backup
+    #[error("The key backup on the server is not signed by one of your verified sessions")]
+    UntrustedKeyBackup,
+
+    #[error("The recovery key is invalid")]
+    InvalidRecoveryKey,
 }
@@ -0,0 +1,90 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use log::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use yew::utils::{document, window};
+use yew::Callback;
+
+/// Activity only triggers the callback this often, mouse moves come in by the hundreds
+const THROTTLE_MS: f64 = 10_000.0;
+
+/// Watches for user input and tab visibility changes to tell whether somebody is around.
+///
+/// The listeners are removed again when the tracker is dropped.
+pub(crate) struct ActivityTracker {
+    last_activity: Rc<Cell<f64>>,
+    listeners: Vec<(&'static str, Closure<dyn FnMut()>)>,
+    unload_listener: Closure<dyn FnMut()>,
+}
+
+impl ActivityTracker {
+    /// `on_change` is called on activity and visibility changes, `on_unload` when the tab closes
+    pub fn new(on_change: Callback<()>, on_unload: Callback<()>) -> Self {
+        let last_activity = Rc::new(Cell::new(js_sys::Date::now()));
+
+        let mut listeners = Vec::new();
+        for event in &["mousemove", "keydown", "touchstart"] {
+            let last_activity = last_activity.clone();
+            let on_change = on_change.clone();
+            let listener = Closure::wrap(Box::new(move || {
+                let now = js_sys::Date::now();
+                if now - last_activity.get() >= THROTTLE_MS {
+                    on_change.emit(());
+                }
+                last_activity.set(now);
+            }) as Box<dyn FnMut()>);
+            listeners.push((*event, listener));
+        }
+        let visibility_listener = Closure::wrap(Box::new(move || {
+            on_change.emit(());
+        }) as Box<dyn FnMut()>);
+        listeners.push(("visibilitychange", visibility_listener));
+
+        for (event, listener) in &listeners {
+            if let Err(e) = document()
+                .add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())
+            {
+                warn!("Failed to listen to {}: {:?}", event, e);
+            }
+        }
+
+        let unload_listener =
+            Closure::wrap(Box::new(move || on_unload.emit(())) as Box<dyn FnMut()>);
+        if let Err(e) = window().add_event_listener_with_callback(
+            "beforeunload",
+            unload_listener.as_ref().unchecked_ref(),
+        ) {
+            warn!("Failed to listen to beforeunload: {:?}", e);
+        }
+
+        ActivityTracker {
+            last_activity,
+            listeners,
+            unload_listener,
+        }
+    }
+
+    /// Milliseconds since the last input
+    pub fn idle_ms(&self) -> f64 {
+        js_sys::Date::now() - self.last_activity.get()
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        document().hidden()
+    }
+}
+
+impl Drop for ActivityTracker {
+    fn drop(&mut self) {
+        for (event, listener) in &self.listeners {
+            let _ = document()
+                .remove_event_listener_with_callback(event, listener.as_ref().unchecked_ref());
+        }
+        let _ = window().remove_event_listener_with_callback(
+            "beforeunload",
+            self.unload_listener.as_ref().unchecked_ref(),
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod activity;
 pub mod notifications;
 pub mod ruma;
 pub mod string_utils;
+pub mod time;
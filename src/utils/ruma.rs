@@ -1,7 +1,14 @@
-use matrix_sdk::events::{
-    room::redaction::SyncRedactionEvent, AnyMessageEvent, AnySyncMessageEvent, MessageEvent,
-    MessageEventContent, SyncMessageEvent,
+use std::time::SystemTime;
+
+use matrix_sdk::{
+    events::{
+        room::redaction::{RedactionEvent, SyncRedactionEvent},
+        AnyMessageEvent, AnyRoomEvent, AnySyncMessageEvent, AnySyncRoomEvent, MessageEvent,
+        MessageEventContent, SyncMessageEvent,
+    },
+    identifiers::{EventId, RoomId, UserId},
 };
+use serde::{de::DeserializeOwned, Serialize};
 
 pub trait AnyMessageEventExt {
     fn without_room_id(self) -> AnySyncMessageEvent;
@@ -45,3 +52,105 @@ impl AnyMessageEventExt for AnyMessageEvent {
         }
     }
 }
+
+pub trait AnySyncMessageEventExt {
+    fn with_room_id(self, room_id: RoomId) -> AnyMessageEvent;
+}
+
+impl AnySyncMessageEventExt for AnySyncMessageEvent {
+    fn with_room_id(self, room_id: RoomId) -> AnyMessageEvent {
+        fn with_room_id<C>(ev: SyncMessageEvent<C>, room_id: RoomId) -> MessageEvent<C>
+        where
+            C: MessageEventContent,
+        {
+            MessageEvent {
+                content: ev.content,
+                event_id: ev.event_id,
+                sender: ev.sender,
+                origin_server_ts: ev.origin_server_ts,
+                room_id,
+                unsigned: ev.unsigned,
+            }
+        }
+
+        use AnyMessageEvent::*;
+
+        match self {
+            Self::CallAnswer(ev) => CallAnswer(with_room_id(ev, room_id)),
+            Self::CallInvite(ev) => CallInvite(with_room_id(ev, room_id)),
+            Self::CallHangup(ev) => CallHangup(with_room_id(ev, room_id)),
+            Self::CallCandidates(ev) => CallCandidates(with_room_id(ev, room_id)),
+            Self::RoomEncrypted(ev) => RoomEncrypted(with_room_id(ev, room_id)),
+            Self::RoomMessage(ev) => RoomMessage(with_room_id(ev, room_id)),
+            Self::RoomMessageFeedback(ev) => RoomMessageFeedback(with_room_id(ev, room_id)),
+            Self::Sticker(ev) => Sticker(with_room_id(ev, room_id)),
+            Self::Custom(ev) => Custom(with_room_id(ev, room_id)),
+            Self::RoomRedaction(ev) => RoomRedaction(RedactionEvent {
+                content: ev.content,
+                event_id: ev.event_id,
+                sender: ev.sender,
+                origin_server_ts: ev.origin_server_ts,
+                room_id,
+                unsigned: ev.unsigned,
+                redacts: ev.redacts,
+            }),
+        }
+    }
+}
+
+pub trait AnyRoomEventExt {
+    fn without_room_id(self) -> Option<AnySyncRoomEvent>;
+}
+
+impl AnyRoomEventExt for AnyRoomEvent {
+    fn without_room_id(self) -> Option<AnySyncRoomEvent> {
+        // There are too many other events to map them by hand, but the sync format is a subset
+        fn convert<T: Serialize, U: DeserializeOwned>(ev: T) -> Option<U> {
+            serde_json::to_value(ev)
+                .and_then(serde_json::from_value)
+                .ok()
+        }
+
+        match self {
+            Self::Message(ev) => Some(AnySyncRoomEvent::Message(ev.without_room_id())),
+            Self::State(ev) => convert(ev).map(AnySyncRoomEvent::State),
+            Self::RedactedMessage(ev) => convert(ev).map(AnySyncRoomEvent::RedactedMessage),
+            Self::RedactedState(ev) => convert(ev).map(AnySyncRoomEvent::RedactedState),
+        }
+    }
+}
+
+pub trait AnySyncRoomEventExt {
+    fn event_id(&self) -> &EventId;
+    fn sender(&self) -> &UserId;
+    fn origin_server_ts(&self) -> &SystemTime;
+}
+
+impl AnySyncRoomEventExt for AnySyncRoomEvent {
+    fn event_id(&self) -> &EventId {
+        match self {
+            Self::Message(ev) => ev.event_id(),
+            Self::State(ev) => ev.event_id(),
+            Self::RedactedMessage(ev) => ev.event_id(),
+            Self::RedactedState(ev) => ev.event_id(),
+        }
+    }
+
+    fn sender(&self) -> &UserId {
+        match self {
+            Self::Message(ev) => ev.sender(),
+            Self::State(ev) => ev.sender(),
+            Self::RedactedMessage(ev) => ev.sender(),
+            Self::RedactedState(ev) => ev.sender(),
+        }
+    }
+
+    fn origin_server_ts(&self) -> &SystemTime {
+        match self {
+            Self::Message(ev) => ev.origin_server_ts(),
+            Self::State(ev) => ev.origin_server_ts(),
+            Self::RedactedMessage(ev) => ev.origin_server_ts(),
+            Self::RedactedState(ev) => ev.origin_server_ts(),
+        }
+    }
+}
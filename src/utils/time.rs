@@ -0,0 +1,14 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use wasm_bindgen::JsValue;
+
+/// Formats a timestamp as date and time in the locale of the browser
+pub fn format_timestamp(timestamp: SystemTime) -> String {
+    let millis = timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as f64)
+        .unwrap_or_default();
+    js_sys::Date::new(&JsValue::from_f64(millis))
+        .to_locale_string("default", &JsValue::UNDEFINED)
+        .into()
+}